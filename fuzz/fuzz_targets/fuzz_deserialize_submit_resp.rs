@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tig_api::{ApiError, SubmitBenchmarkResp};
+use tig_utils::dejsonify;
+
+// a body that fails to parse goes through the same truncation and redaction as a real response
+fuzz_target!(|data: &[u8]| {
+    let Ok(body) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Err(e) = dejsonify::<SubmitBenchmarkResp>(body) {
+        let _ = ApiError::deserialization_failed(body, e).to_string();
+    }
+});
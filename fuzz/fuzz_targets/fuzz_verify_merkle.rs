@@ -0,0 +1,58 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tig_proof::{calc_solutions_merkle_root, verify_solutions_merkle};
+use tig_structs::core::{SolutionData, SolutionMetaData};
+
+const LEAF_LEN: usize = 12;
+
+// input layout:
+//   [flags: u8][root: 32 bytes][meta_data_len in bytes: u16 le][meta_data: 12 byte leaves]
+//   [solution_data json]
+// flags bit 0 replaces the root with the computed one and bit 1 commits the solution as a leaf,
+// so the fuzzer also reaches the checks after the root comparison
+fuzz_target!(|data: &[u8]| {
+    let Some((&flags, data)) = data.split_first() else {
+        return;
+    };
+    if data.len() < 34 {
+        return;
+    }
+    let (root, data) = data.split_at(32);
+    let mut root: [u8; 32] = root.try_into().unwrap();
+    let (meta_data_len, data) = data.split_at(2);
+    let meta_data_len = u16::from_le_bytes([meta_data_len[0], meta_data_len[1]]) as usize;
+    let (meta_data_bytes, solution_bytes) = data.split_at(meta_data_len.min(data.len()));
+    let mut solutions_meta_data: Vec<SolutionMetaData> = meta_data_bytes
+        .chunks_exact(LEAF_LEN)
+        .map(|leaf| SolutionMetaData {
+            nonce: u64::from_le_bytes(leaf[..8].try_into().unwrap()),
+            solution_signature: u32::from_le_bytes(leaf[8..].try_into().unwrap()),
+        })
+        .collect();
+    let Ok(solution_data) = serde_json::from_slice::<SolutionData>(solution_bytes) else {
+        return;
+    };
+    if flags & 0b10 != 0 {
+        let leaf = SolutionMetaData::from(solution_data.clone());
+        match solutions_meta_data
+            .iter_mut()
+            .find(|d| d.nonce == solution_data.nonce)
+        {
+            Some(d) => *d = leaf,
+            None => solutions_meta_data.push(leaf),
+        }
+    }
+    let computed_root = calc_solutions_merkle_root(&solutions_meta_data);
+    if flags & 0b01 != 0 {
+        if let Ok(computed_root) = computed_root {
+            root = computed_root;
+        }
+    }
+    match verify_solutions_merkle(&root, &solutions_meta_data, &solution_data) {
+        Ok(()) => assert_eq!(computed_root, Ok(root)),
+        Err(e) => {
+            let _ = e.to_string();
+        }
+    }
+});
@@ -0,0 +1,207 @@
+use super::{Api, ApiError, FailoverStrategy, DEFAULT_MAX_RESPONSE_BODY_BYTES, DEFAULT_USER_AGENT};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tig_utils::{HttpClient, HttpClientConfig};
+pub use url::Url;
+
+// the client built by `ApiClientBuilder`
+pub type ApiClient = Api;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// tried in order by `PrimaryWithFallback`, rotated through by `RoundRobin`
+    pub endpoints: Vec<String>,
+    pub failover_strategy: FailoverStrategy,
+    /// pem file of root certificates to trust in addition to the system ones, e.g. those of a
+    /// corporate TLS-intercepting proxy. not supported in the browser
+    pub tls_ca_bundle: Option<PathBuf>,
+    /// e.g. `http://proxy.corp:3128`. not supported in the browser
+    pub proxy: Option<Url>,
+    pub connect_timeout_ms: Option<u64>,
+    /// covers a whole request, including reading the response
+    pub request_timeout_ms: Option<u64>,
+    /// defaults to `TIG API`
+    pub user_agent: Option<String>,
+    /// a response with a longer body is aborted. defaults to 10 MB
+    pub max_response_body_bytes: Option<usize>,
+    /// use HTTP/2 without negotiating it, so concurrent submissions share one connection. the
+    /// server has to support HTTP/2. not supported in the browser
+    pub http2_prior_knowledge: bool,
+    /// how often unhealthy endpoints are sent a `/health` check. 0 leaves them to be re-probed
+    /// by requests
+    pub health_check_interval_ms: u64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            failover_strategy: FailoverStrategy::PrimaryWithFallback,
+            tls_ca_bundle: None,
+            proxy: None,
+            connect_timeout_ms: None,
+            request_timeout_ms: None,
+            user_agent: None,
+            max_response_body_bytes: None,
+            http2_prior_knowledge: false,
+            health_check_interval_ms: 30000,
+        }
+    }
+}
+
+pub struct ApiClientBuilder {
+    endpoints: Vec<String>,
+    failover_strategy: FailoverStrategy,
+    api_key: String,
+    http: HttpClientConfig,
+    user_agent: String,
+}
+
+impl ApiClientBuilder {
+    pub fn new(endpoints: Vec<String>, api_key: String) -> Self {
+        Self {
+            endpoints,
+            failover_strategy: FailoverStrategy::PrimaryWithFallback,
+            api_key,
+            http: HttpClientConfig {
+                max_response_body_bytes: Some(DEFAULT_MAX_RESPONSE_BODY_BYTES),
+                ..Default::default()
+            },
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+        }
+    }
+
+    // the api key is not part of the config. set it with `with_api_key`
+    pub fn from_config(config: &ApiConfig) -> Self {
+        let mut builder = Self::new(config.endpoints.clone(), String::new())
+            .with_failover_strategy(config.failover_strategy);
+        if let Some(path) = &config.tls_ca_bundle {
+            builder = builder.with_tls_ca_bundle(path);
+        }
+        if let Some(url) = &config.proxy {
+            builder = builder.with_proxy(url.clone());
+        }
+        if let Some(ms) = config.connect_timeout_ms {
+            builder = builder.with_connect_timeout(Duration::from_millis(ms));
+        }
+        if let Some(ms) = config.request_timeout_ms {
+            builder = builder.with_request_timeout(Duration::from_millis(ms));
+        }
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.with_user_agent(user_agent.clone());
+        }
+        if let Some(max_bytes) = config.max_response_body_bytes {
+            builder = builder.with_max_response_body_bytes(max_bytes);
+        }
+        builder.with_http2_prior_knowledge(config.http2_prior_knowledge)
+    }
+
+    pub fn with_api_key(mut self, api_key: String) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn with_failover_strategy(mut self, strategy: FailoverStrategy) -> Self {
+        self.failover_strategy = strategy;
+        self
+    }
+
+    pub fn with_tls_ca_bundle(mut self, path: &Path) -> Self {
+        self.http.tls_ca_bundle = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn with_proxy(mut self, url: Url) -> Self {
+        self.http.proxy = Some(url.to_string());
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.http.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    // applies to every response, successful or not
+    pub fn with_max_response_body_bytes(mut self, max_bytes: usize) -> Self {
+        self.http.max_response_body_bytes = Some(max_bytes);
+        self
+    }
+
+    // off by default, in which case HTTP/2 is only used if the server picks it during ALPN
+    pub fn with_http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.http.http2_prior_knowledge = enabled;
+        self
+    }
+
+    // fails if there are no endpoints, or the CA bundle cannot be read
+    pub fn build(self) -> Result<ApiClient> {
+        if self.endpoints.is_empty() {
+            return Err(ApiError::NoEndpoints.into());
+        }
+        let mut api = Api::with_endpoints(self.endpoints, self.failover_strategy, self.api_key);
+        api.http = HttpClient::new(&self.http)?;
+        api.user_agent = self.user_agent;
+        Ok(api)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        let config = ApiConfig {
+            endpoints: vec!["https://mainnet-api.tig.foundation".to_string()],
+            proxy: Some(Url::parse("http://proxy.corp:3128").unwrap()),
+            connect_timeout_ms: Some(5000),
+            user_agent: Some("my-benchmarker".to_string()),
+            ..Default::default()
+        };
+        let builder = ApiClientBuilder::from_config(&config).with_api_key("key".to_string());
+        assert_eq!(
+            builder.http.proxy.as_deref(),
+            Some("http://proxy.corp:3128/")
+        );
+        assert_eq!(builder.http.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(builder.http.request_timeout, None);
+        assert!(!builder.http.http2_prior_knowledge);
+        assert_eq!(
+            builder.http.max_response_body_bytes,
+            Some(DEFAULT_MAX_RESPONSE_BODY_BYTES)
+        );
+        let api = builder.build().unwrap();
+        assert_eq!(api.user_agent, "my-benchmarker");
+        assert_eq!(api.api_key, "key");
+    }
+
+    #[test]
+    fn test_build_requires_endpoint() {
+        assert!(ApiClientBuilder::new(Vec::new(), String::new())
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_proxy_in_config() {
+        assert!(serde_json::from_str::<ApiConfig>(r#"{"proxy": "not a url"}"#).is_err());
+        let config: ApiConfig =
+            serde_json::from_str(r#"{"proxy": "http://proxy.corp:3128"}"#).unwrap();
+        assert_eq!(config.proxy.unwrap().port(), Some(3128));
+    }
+}
@@ -0,0 +1,211 @@
+use super::Error;
+use uuid::Uuid;
+
+// how much of an unparseable response body is kept in `ApiError::DeserializationFailed`
+pub const MAX_RAW_BODY_LEN: usize = 4096;
+// string values of fields whose name contains one of these are redacted from a kept body
+const SENSITIVE_FIELDS: [&str; 5] = ["key", "secret", "token", "password", "signature"];
+const REDACTED: &str = "<redacted>";
+
+// raised by tig-api itself. errors from sending a request come from tig-utils and are passed
+// on untouched, so e.g. a `reqwest::Error` is still reachable through `Error::chain`
+#[derive(Debug)]
+pub enum ApiError {
+    DeserializationFailed {
+        raw_body: String,
+        source: serde_json::Error,
+    },
+    InvalidSigningKey,
+    MissingLatestBlock,
+    NoEndpoints,
+    PubkeyMismatch {
+        sent: String,
+        received: Option<String>,
+    },
+    ResponseTooLarge {
+        limit: usize,
+        received_so_far: usize,
+    },
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::DeserializationFailed { raw_body, source } => write!(
+                f,
+                "Failed to dejsonify: {}. Response body: '{}'",
+                source, raw_body
+            ),
+            ApiError::InvalidSigningKey => {
+                write!(f, "Signing key has to be 32 bytes, hex encoded")
+            }
+            ApiError::MissingLatestBlock => write!(f, "Expecting latest block to exist"),
+            ApiError::NoEndpoints => write!(f, "At least one API endpoint is required"),
+            ApiError::PubkeyMismatch { sent, received } => write!(
+                f,
+                "Request was signed with public key '{}' but the server verified '{}'",
+                sent,
+                received.as_deref().unwrap_or("none")
+            ),
+            ApiError::ResponseTooLarge {
+                limit,
+                received_so_far,
+            } => write!(
+                f,
+                "Response body exceeds the limit of {} bytes. Aborted after {} bytes",
+                limit, received_so_far
+            ),
+        }
+    }
+}
+
+impl ApiError {
+    // keeps the first `MAX_RAW_BODY_LEN` bytes of `body`, with sensitive fields redacted
+    pub fn deserialization_failed(body: &str, source: serde_json::Error) -> Self {
+        let mut raw_body = redact_sensitive_fields(body);
+        if raw_body.len() > MAX_RAW_BODY_LEN {
+            let mut len = MAX_RAW_BODY_LEN;
+            while !raw_body.is_char_boundary(len) {
+                len -= 1;
+            }
+            raw_body.truncate(len);
+        }
+        ApiError::DeserializationFailed { raw_body, source }
+    }
+}
+
+// the body failed to parse, so fields are found by scanning for `"name": "value"` rather than
+// by walking the json
+fn redact_sensitive_fields(body: &str) -> String {
+    let mut redacted = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some((name, after_name)) = next_string(rest) {
+        let value = after_name
+            .trim_start()
+            .strip_prefix(':')
+            .map(str::trim_start)
+            .filter(|v| v.starts_with('"'));
+        match value {
+            Some(value) => {
+                // an unterminated value runs to the end of the body
+                let after_value = next_string(value).map_or("", |(_, after_value)| after_value);
+                let name = name.to_lowercase();
+                if SENSITIVE_FIELDS.iter().any(|f| name.contains(f)) {
+                    redacted.push_str(&rest[..rest.len() - value.len()]);
+                    redacted.push('"');
+                    redacted.push_str(REDACTED);
+                    redacted.push('"');
+                } else {
+                    redacted.push_str(&rest[..rest.len() - after_value.len()]);
+                }
+                rest = after_value;
+            }
+            None => {
+                redacted.push_str(&rest[..rest.len() - after_name.len()]);
+                rest = after_name;
+            }
+        }
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+// the contents of the first string literal in `s` and what follows its closing quote
+fn next_string(s: &str) -> Option<(&str, &str)> {
+    let start = s.find('"')? + 1;
+    let mut escaped = false;
+    for (i, c) in s[start..].char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some((&s[start..start + i], &s[start + i + 1..])),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::DeserializationFailed { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// a non-2xx response. tig-utils reports these as a plain string so callers can match on it;
+// `StatusError::parse` reads one back
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusError {
+    pub status: u16,
+    pub retry_after: Option<String>,
+    pub body: String,
+}
+
+impl StatusError {
+    pub fn parse(e: &Error) -> Option<Self> {
+        let err_str = e.downcast_ref::<String>()?;
+        let rest = err_str
+            .strip_prefix("Request error (status: ")?
+            .strip_suffix(')')?;
+        let (status, rest) = rest.split_once(", ")?;
+        let (retry_after, body) = match rest.strip_prefix("retry-after: ") {
+            Some(rest) => {
+                let (retry_after, rest) = rest.split_once(", ")?;
+                (Some(retry_after.to_string()), rest)
+            }
+            None => (None, rest),
+        };
+        Some(Self {
+            status: status.parse().ok()?,
+            retry_after,
+            body: body.strip_prefix("body: ")?.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API error {}: {}", self.status, self.body)?;
+        match &self.retry_after {
+            Some(secs) if secs.parse::<u64>().is_ok() => write!(f, ", retry after {}s", secs),
+            Some(date) => write!(f, ", retry after {}", date),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+// the benchmark a failed request was for. attached as context, so the request error underneath
+// can still be downcast (e.g. `e.downcast_ref::<String>()`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorContext {
+    pub benchmark_id: Option<String>,
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.benchmark_id {
+            Some(benchmark_id) => write!(f, "Request for benchmark '{}' failed", benchmark_id),
+            None => write!(f, "Request failed"),
+        }
+    }
+}
+
+impl std::error::Error for ErrorContext {}
+
+// the `x-trace-id` a failed request was sent with, for finding it in the server's logs.
+// attached to every error from a request, underneath any `ErrorContext`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: Uuid,
+}
+
+impl std::fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request with trace id '{}' failed", self.trace_id)
+    }
+}
+
+impl std::error::Error for TraceContext {}
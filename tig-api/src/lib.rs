@@ -3,61 +3,465 @@ compile_error!("Either feature `request` or `request-js` must be enabled");
 #[cfg(all(feature = "request", feature = "request-js"))]
 compile_error!("features `request` and `request-js` are mutually exclusive");
 
-use anyhow::{anyhow, Result};
+mod client;
+mod error;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+mod signer;
+
+pub use anyhow::Error;
+use anyhow::Result;
+use async_trait::async_trait;
+pub use client::{ApiClient, ApiClientBuilder, ApiConfig, Url};
+pub use error::{ApiError, ErrorContext, StatusError, TraceContext};
 use query_map::QueryMap;
-use serde::de::DeserializeOwned;
-use std::{collections::HashMap, vec};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+pub use signer::{
+    Ed25519Signer, Signature, Signer, PUBLIC_KEY_HEADER, SIGNATURE_HEADER, VERIFIED_PUBKEY_HEADER,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    vec,
+};
 pub use tig_structs::api::*;
-use tig_utils::{dejsonify, get, jsonify, post};
+use tig_structs::core::Block;
+use tig_utils::{
+    dejsonify, jsonify, time_ms, BodyTooLargeError, CircuitBreaker, HttpClient, HttpResponse,
+};
+pub use tig_utils::{
+    CircuitBreakerConfig, CircuitOpenError, CircuitState, CompressionAlgorithm, CompressionConfig,
+};
+use tracing::{debug, field, info_span, warn, Instrument};
+pub use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FailoverStrategy {
+    /// always prefer the first healthy endpoint in the list
+    PrimaryWithFallback,
+    /// spread requests across healthy endpoints
+    RoundRobin,
+}
+
+// each endpoint has its own circuit breaker. an open breaker marks the endpoint unhealthy
+// until its probe interval passes and the next request re-probes it
+struct Endpoint {
+    url: String,
+    circuit_breaker: CircuitBreaker,
+}
 
 pub struct Api {
-    api_url: String,
+    endpoints: Mutex<Vec<Endpoint>>,
+    strategy: FailoverStrategy,
+    next_endpoint: AtomicUsize,
     api_key: String,
+    compression: CompressionConfig,
+    protocol: Mutex<Option<GetProtocolResp>>,
+    multipart_unsupported: AtomicBool,
+    http: HttpClient,
+    user_agent: String,
+}
+
+const DEFAULT_USER_AGENT: &str = "TIG API";
+pub const DEFAULT_MAX_RESPONSE_BODY_BYTES: usize = 10 * 1024 * 1024;
+const TRACE_ID_HEADER: &str = "x-trace-id";
+// sent with every attempt at a benchmark submission, see `SubmitBenchmarkReq::idempotency_key`
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+pub fn error_benchmark_id(e: &Error) -> Option<&str> {
+    e.downcast_ref::<ErrorContext>()
+        .and_then(|c| c.benchmark_id.as_deref())
+}
+
+pub fn error_trace_id(e: &Error) -> Option<Uuid> {
+    e.downcast_ref::<TraceContext>().map(|c| c.trace_id)
+}
+
+fn with_response_too_large(e: Error) -> Error {
+    match e.downcast_ref::<BodyTooLargeError>() {
+        Some(e) => ApiError::ResponseTooLarge {
+            limit: e.limit,
+            received_so_far: e.received_so_far,
+        }
+        .into(),
+        None => e,
+    }
+}
+
+// keeps the body of an unparseable response, which is also logged at debug level
+fn dejsonify_response<T: DeserializeOwned>(body: &str) -> Result<T> {
+    dejsonify::<T>(body).map_err(|e| {
+        let e = ApiError::deserialization_failed(body, e);
+        if let ApiError::DeserializationFailed { raw_body, source } = &e {
+            debug!(%raw_body, %source, "Failed to dejsonify response");
+        }
+        e.into()
+    })
+}
+
+// servers that echo `x-trace-id` should echo the one they were sent. the request has been
+// handled either way, so a different one is only logged: retrying it could submit twice
+fn check_trace_id(trace_id: Uuid, resp: HttpResponse<String>) -> String {
+    if let Some(received) = resp.header(TRACE_ID_HEADER) {
+        if !received.eq_ignore_ascii_case(&trace_id.to_string()) {
+            warn!(
+                %trace_id,
+                received,
+                "Response echoed a trace id other than the one the request was sent with"
+            );
+        }
+    }
+    resp.body
+}
+
+// a signed request has to come back with the public key the server verified it with
+fn check_verified_pubkey(signature: Option<&Signature>, resp: &HttpResponse<String>) -> Result<()> {
+    let Some(signature) = signature else {
+        return Ok(());
+    };
+    let sent = hex::encode(&signature.public_key);
+    match resp.header(VERIFIED_PUBKEY_HEADER) {
+        Some(received) if received.eq_ignore_ascii_case(&sent) => Ok(()),
+        received => Err(ApiError::PubkeyMismatch {
+            sent,
+            received: received.map(str::to_string),
+        }
+        .into()),
+    }
+}
+
+// prefers the benchmark_id in the server's response body over the one known to the client
+fn with_benchmark_id(e: Error, client_benchmark_id: Option<String>) -> Error {
+    let server_benchmark_id = StatusError::parse(&e)
+        .and_then(|s| dejsonify::<Value>(&s.body).ok())
+        .and_then(|body| body.get("benchmark_id")?.as_str().map(str::to_string));
+    e.context(ErrorContext {
+        benchmark_id: server_benchmark_id.or(client_benchmark_id),
+    })
+}
+
+// 404 Not Found, 405 Method Not Allowed or 415 Unsupported Media Type
+fn is_multipart_unsupported(e: &anyhow::Error) -> bool {
+    StatusError::parse(e).is_some_and(|s| matches!(s.status, 404 | 405 | 415))
+}
+
+fn multipart_part(boundary: &str, name: &str, body: &str) -> Vec<u8> {
+    format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\nContent-Type: application/json\r\n\r\n{}\r\n",
+        boundary, name, body
+    )
+    .into_bytes()
+}
+
+// settings, protocol_version and idempotency_key, then one part per chunk of
+// solutions_meta_data, then solution_data. each part is serialised when the iterator reaches it
+fn multipart_parts(
+    req: Arc<SubmitBenchmarkReq>,
+    chunk_size: usize,
+    boundary: String,
+) -> impl Iterator<Item = Vec<u8>> + Send + 'static {
+    let num_chunks = (req.solutions_meta_data().len() + chunk_size - 1) / chunk_size;
+    (0..num_chunks + 4).map(move |i| match i {
+        0 => multipart_part(&boundary, "settings", &jsonify(req.settings())),
+        1 => multipart_part(
+            &boundary,
+            "protocol_version",
+            &jsonify(&req.protocol_version()),
+        ),
+        2 => multipart_part(
+            &boundary,
+            "idempotency_key",
+            &jsonify(&req.idempotency_key()),
+        ),
+        i if i < num_chunks + 3 => {
+            let start = (i - 3) * chunk_size;
+            let end = (start + chunk_size).min(req.solutions_meta_data().len());
+            let chunk = &req.solutions_meta_data()[start..end];
+            multipart_part(&boundary, "solutions_meta_data", &jsonify(&chunk))
+        }
+        _ => {
+            let mut part =
+                multipart_part(&boundary, "solution_data", &jsonify(req.solution_data()));
+            part.extend(format!("--{}--\r\n", boundary).into_bytes());
+            part
+        }
+    })
+}
+
+// 5xx responses and transport errors indicate the server is down. other
+// failures (e.g. 4xx) mean the server is up but rejected the request
+fn is_server_failure(e: &anyhow::Error) -> bool {
+    match StatusError::parse(e) {
+        Some(s) => s.status >= 500,
+        None => true,
+    }
 }
 
 impl Api {
     pub fn new(api_url: String, api_key: String) -> Self {
-        Self { api_url, api_key }
+        Self::with_endpoints(
+            vec![api_url],
+            FailoverStrategy::PrimaryWithFallback,
+            api_key,
+        )
+    }
+
+    pub fn with_endpoints(
+        api_urls: Vec<String>,
+        strategy: FailoverStrategy,
+        api_key: String,
+    ) -> Self {
+        assert!(
+            !api_urls.is_empty(),
+            "At least one API endpoint is required"
+        );
+        Self {
+            endpoints: Mutex::new(
+                api_urls
+                    .into_iter()
+                    .map(|url| Endpoint {
+                        url,
+                        circuit_breaker: CircuitBreaker::new(CircuitBreakerConfig::default()),
+                    })
+                    .collect(),
+            ),
+            strategy,
+            next_endpoint: AtomicUsize::new(0),
+            api_key,
+            compression: CompressionConfig::default(),
+            protocol: Mutex::new(None),
+            multipart_unsupported: AtomicBool::new(false),
+            http: HttpClient::default()
+                .with_max_response_body_bytes(Some(DEFAULT_MAX_RESPONSE_BODY_BYTES)),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+        }
+    }
+
+    pub fn with_compression(mut self, config: CompressionConfig) -> Self {
+        self.compression = config;
+        self
+    }
+
+    pub fn with_circuit_breaker(self, config: CircuitBreakerConfig) -> Self {
+        for endpoint in self.endpoints.lock().unwrap().iter_mut() {
+            endpoint.circuit_breaker = CircuitBreaker::new(config.clone());
+        }
+        self
+    }
+
+    // the healthiest state across all endpoints
+    pub fn circuit_state(&self) -> CircuitState {
+        let states: Vec<CircuitState> = self
+            .endpoints
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.circuit_breaker.state())
+            .collect();
+        [CircuitState::Closed, CircuitState::HalfOpen]
+            .into_iter()
+            .find(|s| states.contains(s))
+            .unwrap_or(CircuitState::Open)
+    }
+
+    // order in which endpoints are tried for the next request
+    fn endpoint_order(&self) -> Vec<usize> {
+        let num_endpoints = self.endpoints.lock().unwrap().len();
+        let start = match self.strategy {
+            FailoverStrategy::PrimaryWithFallback => 0,
+            FailoverStrategy::RoundRobin => {
+                self.next_endpoint.fetch_add(1, Ordering::SeqCst) % num_endpoints
+            }
+        };
+        (0..num_endpoints)
+            .map(|i| (start + i) % num_endpoints)
+            .collect()
+    }
+
+    fn acquire_endpoint(&self, idx: usize) -> Result<String> {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let endpoint = &mut endpoints[idx];
+        endpoint
+            .circuit_breaker
+            .try_acquire(time_ms())
+            .map_err(anyhow::Error::new)?;
+        Ok(endpoint.url.clone())
+    }
+
+    fn record_outcome<T>(&self, idx: usize, result: &Result<T>) {
+        let circuit_breaker = &mut self.endpoints.lock().unwrap()[idx].circuit_breaker;
+        match result {
+            Err(e) if is_server_failure(e) => circuit_breaker.record_failure(time_ms()),
+            _ => circuit_breaker.record_success(),
+        }
+    }
+
+    // sends `path` to each healthy endpoint in turn until one of them does not fail with a
+    // server error. every attempt carries the same `x-trace-id`, which is attached to the error.
+    // each attempt gets its own client span, exported when the benchmarker enables OpenTelemetry
+    async fn send<F, Fut>(
+        &self,
+        method: &'static str,
+        path: &str,
+        trace_id: Uuid,
+        signature: Option<&Signature>,
+        send_to: F,
+    ) -> Result<String>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<HttpResponse<String>>>,
+    {
+        let mut last_error = None;
+        for idx in self.endpoint_order() {
+            let api_url = match self.acquire_endpoint(idx) {
+                Ok(api_url) => api_url,
+                Err(e) => {
+                    last_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            let url = format!("{}/{}", api_url, path);
+            let span = info_span!(
+                "tig_api.http_request",
+                otel.kind = "client",
+                http.method = method,
+                http.url = %url,
+                http.status_code = field::Empty,
+                %trace_id,
+            );
+            let resp = send_to(url.clone())
+                .instrument(span.clone())
+                .await
+                .map_err(with_response_too_large);
+            let status = match &resp {
+                Ok(resp) => Some(resp.status),
+                Err(e) => StatusError::parse(e).map(|s| s.status),
+            };
+            if let Some(status) = status {
+                span.record("http.status_code", status);
+            }
+            debug!(%trace_id, %url, ?status, "API request");
+            self.record_outcome(idx, &resp);
+            match resp {
+                Err(e) if is_server_failure(&e) => last_error = Some(e),
+                Err(e) => return Err(e.context(TraceContext { trace_id })),
+                Ok(resp) => {
+                    return check_verified_pubkey(signature, &resp)
+                        .map(|_| check_trace_id(trace_id, resp))
+                        .map_err(|e| e.context(TraceContext { trace_id }))
+                }
+            }
+        }
+        Err(last_error
+            .expect("at least one endpoint is tried")
+            .context(TraceContext { trace_id }))
+    }
+
+    fn headers(
+        &self,
+        trace_id: Uuid,
+        signature: Option<&Signature>,
+        idempotency_key: Option<Uuid>,
+    ) -> Vec<(String, String)> {
+        let mut headers = vec![
+            ("x-api-key".to_string(), self.api_key.clone()),
+            ("user-agent".to_string(), self.user_agent.clone()),
+            (TRACE_ID_HEADER.to_string(), trace_id.to_string()),
+        ];
+        if let Some(signature) = signature {
+            headers.extend(signature.headers());
+        }
+        if let Some(idempotency_key) = idempotency_key {
+            headers.push((
+                IDEMPOTENCY_KEY_HEADER.to_string(),
+                idempotency_key.to_string(),
+            ));
+        }
+        headers
     }
 
     async fn get<T>(&self, path: String) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let resp = get::<String>(
-            format!("{}/{}", self.api_url, path).as_str(),
-            Some(
-                vec![
-                    ("x-api-key".to_string(), self.api_key.clone()),
-                    ("user-agent".to_string(), "TIG API".to_string()),
-                ]
-                .into_iter()
-                .collect(),
-            ),
-        )
-        .await?;
-        dejsonify::<T>(&resp).map_err(|e| anyhow!("Failed to dejsonify: {}", e))
+        let resp = self.get_raw(path).await?;
+        dejsonify_response(&resp)
+    }
+
+    async fn get_raw(&self, path: String) -> Result<String> {
+        let trace_id = Uuid::new_v4();
+        let headers = self.headers(trace_id, None, None);
+        let (http, headers) = (&self.http, &headers);
+        self.send("GET", &path, trace_id, None, move |url| {
+            let headers = headers.clone();
+            async move {
+                http.get::<HttpResponse<String>>(url.as_str(), Some(headers))
+                    .await
+            }
+        })
+        .await
     }
     async fn post<T>(&self, path: String, body: String) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let resp = post::<String>(
-            format!("{}/{}", self.api_url, path).as_str(),
+        self.post_signed(path, body, None, None).await
+    }
+
+    async fn post_signed<T>(
+        &self,
+        path: String,
+        body: String,
+        signature: Option<&Signature>,
+        idempotency_key: Option<Uuid>,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let trace_id = Uuid::new_v4();
+        let mut headers = self.headers(trace_id, signature, idempotency_key);
+        headers.push(("accept".to_string(), "application/json".to_string()));
+        headers.push(("content-type".to_string(), "application/json".to_string()));
+        let compressed_body = match self.compression.algorithm.content_encoding() {
+            Some(encoding) => {
+                headers.push(("content-encoding".to_string(), encoding.to_string()));
+                Some(self.compression.compress(body.as_bytes())?)
+            }
+            None => None,
+        };
+        let (http, headers, body, compressed_body) = (
+            &self.http,
+            &headers,
             body.as_str(),
-            Some(
-                vec![
-                    ("x-api-key".to_string(), self.api_key.clone()),
-                    ("user-agent".to_string(), "TIG API".to_string()),
-                    ("accept".to_string(), "application/json".to_string()),
-                    ("content-type".to_string(), "application/json".to_string()),
-                ]
-                .into_iter()
-                .collect(),
-            ),
-        )
-        .await?;
-        dejsonify::<T>(&resp).map_err(|e| anyhow!("Failed to dejsonify: {}", e))
+            compressed_body.as_deref(),
+        );
+        let resp = self
+            .send("POST", &path, trace_id, signature, move |url| {
+                let headers = headers.clone();
+                async move {
+                    match compressed_body {
+                        Some(compressed_body) => {
+                            http.post_bytes::<HttpResponse<String>>(
+                                url.as_str(),
+                                compressed_body,
+                                Some(headers),
+                            )
+                            .await
+                        }
+                        None => {
+                            http.post::<HttpResponse<String>>(url.as_str(), body, Some(headers))
+                                .await
+                        }
+                    }
+                }
+            })
+            .await?;
+        dejsonify_response(&resp)
     }
 
     pub async fn get_challenges(&self, req: GetChallengesReq) -> Result<GetChallengesResp> {
@@ -99,10 +503,12 @@ impl Api {
         req: GetBenchmarkDataReq,
     ) -> Result<GetBenchmarkDataResp> {
         let mut query = HashMap::<String, String>::new();
+        let benchmark_id = req.benchmark_id.clone();
         query.insert("benchmark_id".to_string(), req.benchmark_id);
         let query = QueryMap::from(query);
         self.get(format!("get-benchmark-data?{}", query.to_query_string()))
             .await
+            .map_err(|e| with_benchmark_id(e, Some(benchmark_id)))
     }
 
     pub async fn get_block(&self, req: GetBlockReq) -> Result<GetBlockResp> {
@@ -121,17 +527,607 @@ impl Api {
             .await
     }
 
+    // the server's protocol version only changes with a redeploy, so it is fetched once
+    pub async fn get_protocol(&self) -> Result<GetProtocolResp> {
+        if let Some(resp) = self.protocol.lock().unwrap().clone() {
+            return Ok(resp);
+        }
+        let resp: GetProtocolResp = self.get("v1/protocol".to_string()).await?;
+        *self.protocol.lock().unwrap() = Some(resp.clone());
+        Ok(resp)
+    }
+
+    // any 2xx response counts as healthy, whatever its body
+    pub async fn get_health(&self) -> Result<()> {
+        self.get_raw("v1/health".to_string()).await?;
+        Ok(())
+    }
+
+    // sends `v1/health` straight to each unhealthy endpoint whose probe interval has passed,
+    // and records the result on its breaker. without this an endpoint is only re-probed by the
+    // next request that falls through to it
+    pub async fn probe_endpoints(&self) {
+        let trace_id = Uuid::new_v4();
+        let headers = self.headers(trace_id, None, None);
+        let (http, headers) = (&self.http, &headers);
+        self.probe_endpoints_with(trace_id, move |url| {
+            let headers = headers.clone();
+            async move {
+                http.get::<HttpResponse<String>>(url.as_str(), Some(headers))
+                    .await
+            }
+        })
+        .await
+    }
+
+    async fn probe_endpoints_with<F, Fut>(&self, trace_id: Uuid, send_to: F)
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<HttpResponse<String>>>,
+    {
+        let num_endpoints = self.endpoints.lock().unwrap().len();
+        for idx in 0..num_endpoints {
+            let state = self.endpoints.lock().unwrap()[idx].circuit_breaker.state();
+            if state == CircuitState::Closed {
+                continue;
+            }
+            // still within its probe interval
+            let Ok(api_url) = self.acquire_endpoint(idx) else {
+                continue;
+            };
+            let url = format!("{}/v1/health", api_url);
+            let resp = send_to(url.clone()).await.map_err(with_response_too_large);
+            debug!(%trace_id, %url, healthy = resp.is_ok(), "API health check");
+            self.record_outcome(idx, &resp);
+        }
+    }
+
     pub async fn submit_algorithm(&self, req: SubmitAlgorithmReq) -> Result<SubmitAlgorithmResp> {
         self.post("submit-algorithm".to_string(), jsonify(&req))
             .await
     }
 
     pub async fn submit_benchmark(&self, req: SubmitBenchmarkReq) -> Result<SubmitBenchmarkResp> {
-        self.post("submit-benchmark".to_string(), jsonify(&req))
+        self.submit_benchmark_with(req, None).await
+    }
+
+    async fn submit_benchmark_with(
+        &self,
+        req: SubmitBenchmarkReq,
+        signature: Option<&Signature>,
+    ) -> Result<SubmitBenchmarkResp> {
+        let benchmark_id = req.settings().calc_id();
+        let idempotency_key = Some(req.idempotency_key());
+        self.post_signed(
+            "submit-benchmark".to_string(),
+            jsonify(&req),
+            signature,
+            idempotency_key,
+        )
+        .await
+        .map_err(|e| with_benchmark_id(e, Some(benchmark_id)))
+    }
+
+    // sends solutions_meta_data in parts of `chunk_size` entries, so the request is never
+    // serialised in one piece. falls back to `submit_benchmark` for good once the server
+    // turns out not to support it, or when `chunk_size` is 0
+    pub async fn submit_benchmark_chunked(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+    ) -> Result<SubmitBenchmarkResp> {
+        self.submit_benchmark_chunked_with(req, chunk_size, None)
+            .await
+    }
+
+    // signs the json serialisation of `req`, however it ends up being sent, and fails unless
+    // the server echoes the signer's public key in `x-verified-pubkey`
+    pub async fn submit_benchmark_signed(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+        signer: &dyn Signer,
+    ) -> Result<SubmitBenchmarkResp> {
+        let signature = signer.sign(jsonify(&req).as_bytes());
+        self.submit_benchmark_chunked_with(req, chunk_size, Some(&signature))
+            .await
+    }
+
+    async fn submit_benchmark_chunked_with(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+        signature: Option<&Signature>,
+    ) -> Result<SubmitBenchmarkResp> {
+        if chunk_size == 0 || self.multipart_unsupported.load(Ordering::SeqCst) {
+            return self.submit_benchmark_with(req, signature).await;
+        }
+        let benchmark_id = req.settings().calc_id();
+        let req = Arc::new(req);
+        let boundary = format!("tig-boundary-{}-{}", benchmark_id, time_ms());
+        let trace_id = Uuid::new_v4();
+        let mut headers = self.headers(trace_id, signature, Some(req.idempotency_key()));
+        headers.push(("accept".to_string(), "application/json".to_string()));
+        headers.push((
+            "content-type".to_string(),
+            format!("multipart/form-data; boundary={}", boundary),
+        ));
+        let (http, req_ref, headers, boundary) = (&self.http, &req, &headers, boundary.as_str());
+        let resp = self
+            .send(
+                "POST",
+                "v1/benchmarks/multipart",
+                trace_id,
+                signature,
+                move |url| {
+                    let headers = headers.clone();
+                    let parts = multipart_parts(req_ref.clone(), chunk_size, boundary.to_string());
+                    async move {
+                        http.post_parts::<HttpResponse<String>, _>(
+                            url.as_str(),
+                            parts,
+                            Some(headers),
+                        )
+                        .await
+                    }
+                },
+            )
+            .await;
+        match resp {
+            Ok(resp) => dejsonify_response::<SubmitBenchmarkResp>(&resp),
+            Err(e) if is_multipart_unsupported(&e) => {
+                self.multipart_unsupported.store(true, Ordering::SeqCst);
+                let req = Arc::try_unwrap(req).unwrap_or_else(|req| (*req).clone());
+                self.submit_benchmark_with(req, signature).await
+            }
+            Err(e) => Err(with_benchmark_id(e, Some(benchmark_id))),
+        }
+    }
+
+    pub async fn submit_benchmarks_batch(&self, req: SubmitBatchReq) -> Result<SubmitBatchResp> {
+        self.post("v1/benchmarks/batch".to_string(), jsonify(&req))
             .await
     }
 
     pub async fn submit_proof(&self, req: SubmitProofReq) -> Result<SubmitProofResp> {
-        self.post("submit-proof".to_string(), jsonify(&req)).await
+        let benchmark_id = req.benchmark_id.clone();
+        self.post("submit-proof".to_string(), jsonify(&req))
+            .await
+            .map_err(|e| with_benchmark_id(e, Some(benchmark_id)))
+    }
+}
+
+// the calls a benchmarker makes. implemented by `Api` and, for tests, by `mock::MockBenchmarkApi`.
+// futures are not `Send` under request-js as browser requests are bound to the js thread
+#[cfg_attr(not(feature = "request-js"), async_trait)]
+#[cfg_attr(feature = "request-js", async_trait(?Send))]
+pub trait BenchmarkApi: Send + Sync {
+    async fn get_challenges(&self, req: GetChallengesReq) -> Result<GetChallengesResp>;
+    async fn get_algorithms(&self, req: GetAlgorithmsReq) -> Result<GetAlgorithmsResp>;
+    async fn get_players(&self, req: GetPlayersReq) -> Result<GetPlayersResp>;
+    async fn get_benchmarks(&self, req: GetBenchmarksReq) -> Result<GetBenchmarksResp>;
+    async fn get_block(&self, req: GetBlockReq) -> Result<GetBlockResp>;
+    async fn get_protocol(&self) -> Result<GetProtocolResp>;
+    async fn submit_benchmark(&self, req: SubmitBenchmarkReq) -> Result<SubmitBenchmarkResp>;
+    async fn submit_benchmark_chunked(
+        &self,
+        req: SubmitBenchmarkReq,
+        _chunk_size: usize,
+    ) -> Result<SubmitBenchmarkResp> {
+        self.submit_benchmark(req).await
+    }
+    async fn submit_benchmark_signed(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+        signer: &dyn Signer,
+    ) -> Result<SubmitBenchmarkResp>;
+    async fn submit_benchmarks_batch(&self, req: SubmitBatchReq) -> Result<SubmitBatchResp>;
+    async fn submit_proof(&self, req: SubmitProofReq) -> Result<SubmitProofResp>;
+    // clients without per-endpoint health have nothing to probe
+    async fn probe_endpoints(&self) {}
+
+    async fn get_latest_block(&self) -> Result<Block> {
+        let GetBlockResp { block } = self
+            .get_block(GetBlockReq {
+                id: None,
+                round: None,
+                height: None,
+                include_data: false,
+            })
+            .await?;
+        Ok(block.ok_or(ApiError::MissingLatestBlock)?)
+    }
+}
+
+#[cfg_attr(not(feature = "request-js"), async_trait)]
+#[cfg_attr(feature = "request-js", async_trait(?Send))]
+impl BenchmarkApi for Api {
+    async fn get_challenges(&self, req: GetChallengesReq) -> Result<GetChallengesResp> {
+        Api::get_challenges(self, req).await
+    }
+    async fn get_algorithms(&self, req: GetAlgorithmsReq) -> Result<GetAlgorithmsResp> {
+        Api::get_algorithms(self, req).await
+    }
+    async fn get_players(&self, req: GetPlayersReq) -> Result<GetPlayersResp> {
+        Api::get_players(self, req).await
+    }
+    async fn get_benchmarks(&self, req: GetBenchmarksReq) -> Result<GetBenchmarksResp> {
+        Api::get_benchmarks(self, req).await
+    }
+    async fn get_block(&self, req: GetBlockReq) -> Result<GetBlockResp> {
+        Api::get_block(self, req).await
+    }
+    async fn get_protocol(&self) -> Result<GetProtocolResp> {
+        Api::get_protocol(self).await
+    }
+    async fn submit_benchmark(&self, req: SubmitBenchmarkReq) -> Result<SubmitBenchmarkResp> {
+        Api::submit_benchmark(self, req).await
+    }
+    async fn submit_benchmark_chunked(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+    ) -> Result<SubmitBenchmarkResp> {
+        Api::submit_benchmark_chunked(self, req, chunk_size).await
+    }
+    async fn submit_benchmark_signed(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+        signer: &dyn Signer,
+    ) -> Result<SubmitBenchmarkResp> {
+        Api::submit_benchmark_signed(self, req, chunk_size, signer).await
+    }
+    async fn submit_benchmarks_batch(&self, req: SubmitBatchReq) -> Result<SubmitBatchResp> {
+        Api::submit_benchmarks_batch(self, req).await
+    }
+    async fn submit_proof(&self, req: SubmitProofReq) -> Result<SubmitProofResp> {
+        Api::submit_proof(self, req).await
+    }
+    async fn probe_endpoints(&self) {
+        Api::probe_endpoints(self).await
+    }
+}
+
+// lets a test keep a handle to the api it installed
+#[cfg_attr(not(feature = "request-js"), async_trait)]
+#[cfg_attr(feature = "request-js", async_trait(?Send))]
+impl<T: BenchmarkApi + ?Sized> BenchmarkApi for Arc<T> {
+    async fn get_challenges(&self, req: GetChallengesReq) -> Result<GetChallengesResp> {
+        (**self).get_challenges(req).await
+    }
+    async fn get_algorithms(&self, req: GetAlgorithmsReq) -> Result<GetAlgorithmsResp> {
+        (**self).get_algorithms(req).await
+    }
+    async fn get_players(&self, req: GetPlayersReq) -> Result<GetPlayersResp> {
+        (**self).get_players(req).await
+    }
+    async fn get_benchmarks(&self, req: GetBenchmarksReq) -> Result<GetBenchmarksResp> {
+        (**self).get_benchmarks(req).await
+    }
+    async fn get_block(&self, req: GetBlockReq) -> Result<GetBlockResp> {
+        (**self).get_block(req).await
+    }
+    async fn get_protocol(&self) -> Result<GetProtocolResp> {
+        (**self).get_protocol().await
+    }
+    async fn submit_benchmark(&self, req: SubmitBenchmarkReq) -> Result<SubmitBenchmarkResp> {
+        (**self).submit_benchmark(req).await
+    }
+    async fn submit_benchmark_chunked(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+    ) -> Result<SubmitBenchmarkResp> {
+        (**self).submit_benchmark_chunked(req, chunk_size).await
+    }
+    async fn submit_benchmark_signed(
+        &self,
+        req: SubmitBenchmarkReq,
+        chunk_size: usize,
+        signer: &dyn Signer,
+    ) -> Result<SubmitBenchmarkResp> {
+        (**self)
+            .submit_benchmark_signed(req, chunk_size, signer)
+            .await
+    }
+    async fn submit_benchmarks_batch(&self, req: SubmitBatchReq) -> Result<SubmitBatchResp> {
+        (**self).submit_benchmarks_batch(req).await
+    }
+    async fn submit_proof(&self, req: SubmitProofReq) -> Result<SubmitProofResp> {
+        (**self).submit_proof(req).await
+    }
+    async fn probe_endpoints(&self) {
+        (**self).probe_endpoints().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn request_error(status: u16, body: &str) -> Error {
+        anyhow!(format!(
+            "Request error (status: {}, body: {})",
+            status, body
+        ))
+    }
+
+    #[test]
+    fn test_probe_closes_the_breaker_of_a_recovered_endpoint() {
+        let api = Api::with_endpoints(
+            vec!["http://a".to_string(), "http://b".to_string()],
+            FailoverStrategy::PrimaryWithFallback,
+            String::new(),
+        )
+        .with_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 1,
+            probe_interval_ms: 0,
+            success_threshold: 1,
+        });
+        api.record_outcome::<()>(0, &Err(anyhow!("connection refused")));
+        let probed = Mutex::new(Vec::new());
+        futures::executor::block_on(api.probe_endpoints_with(Uuid::new_v4(), |url| {
+            probed.lock().unwrap().push(url);
+            async {
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: String::new(),
+                })
+            }
+        }));
+        // the healthy endpoint is left alone
+        assert_eq!(*probed.lock().unwrap(), vec!["http://a/v1/health"]);
+        let endpoints = api.endpoints.lock().unwrap();
+        assert_eq!(endpoints[0].circuit_breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_benchmark_id_from_response_body() {
+        let e = with_benchmark_id(
+            request_error(400, r#"{"benchmark_id":"server_id","error":"bad"}"#),
+            Some("client_id".to_string()),
+        );
+        assert_eq!(error_benchmark_id(&e), Some("server_id"));
+    }
+
+    #[test]
+    fn test_benchmark_id_falls_back_to_client() {
+        let e = with_benchmark_id(
+            request_error(500, "Internal Server Error"),
+            Some("client_id".to_string()),
+        );
+        assert_eq!(error_benchmark_id(&e), Some("client_id"));
+        let e = with_benchmark_id(anyhow!("connection refused"), None);
+        assert_eq!(error_benchmark_id(&e), None);
+    }
+
+    #[test]
+    fn test_multipart_parts() {
+        use tig_structs::core::{BenchmarkSettings, SolutionData, SolutionMetaData};
+        let req = SubmitBenchmarkReq::builder()
+            .settings(BenchmarkSettings {
+                player_id: "player".to_string(),
+                block_id: "block".to_string(),
+                challenge_id: "c001".to_string(),
+                algorithm_id: "c001_a001".to_string(),
+                difficulty: vec![50, 300],
+            })
+            .solutions_meta_data(
+                (0..5)
+                    .map(|nonce| SolutionMetaData {
+                        nonce,
+                        solution_signature: nonce as u32,
+                    })
+                    .collect(),
+            )
+            .solution_data(SolutionData {
+                nonce: 0,
+                runtime_signature: 0,
+                fuel_consumed: 0,
+                solution: Default::default(),
+            })
+            .idempotency_key(Uuid::nil())
+            .build()
+            .unwrap();
+        let parts: Vec<String> = multipart_parts(Arc::new(req), 2, "b".to_string())
+            .map(|p| String::from_utf8(p).unwrap())
+            .collect();
+        assert_eq!(parts.len(), 7);
+        assert!(parts[2].contains(&format!("\"{}\"", Uuid::nil())));
+        let chunks: Vec<&String> = parts
+            .iter()
+            .filter(|p| p.contains("name=\"solutions_meta_data\""))
+            .collect();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[2].contains(r#"[{"nonce":4,"solution_signature":4}]"#));
+        assert!(parts[6].contains("name=\"solution_data\""));
+        assert!(parts[6].ends_with("--b--\r\n"));
+    }
+
+    #[test]
+    fn test_builder_requires_every_field() {
+        use tig_structs::core::{BenchmarkSettings, SolutionData, SolutionMetaData};
+        let solution_data = SolutionData {
+            nonce: 0,
+            runtime_signature: 0,
+            fuel_consumed: 0,
+            solution: Default::default(),
+        };
+        let result = SubmitBenchmarkReq::builder()
+            .solutions_meta_data(Vec::new())
+            .solution_data(solution_data.clone())
+            .build();
+        assert_eq!(result, Err(BuilderError::EmptyMetadata));
+        let result = SubmitBenchmarkReq::builder()
+            .solution_data(solution_data.clone())
+            .build();
+        assert_eq!(
+            result,
+            Err(BuilderError::MissingField("solutions_meta_data"))
+        );
+        let result = SubmitBenchmarkReq::builder()
+            .settings(BenchmarkSettings {
+                player_id: "player".to_string(),
+                block_id: "block".to_string(),
+                challenge_id: "c001".to_string(),
+                algorithm_id: "c001_a001".to_string(),
+                difficulty: vec![50, 300],
+            })
+            .solutions_meta_data(vec![SolutionMetaData {
+                nonce: 0,
+                solution_signature: 0,
+            }])
+            .solution_data(solution_data)
+            .build();
+        assert_eq!(result, Err(BuilderError::MissingField("idempotency_key")));
+    }
+
+    #[test]
+    fn test_request_error_still_downcasts() {
+        let e = with_benchmark_id(
+            request_error(503, "unavailable"),
+            Some("client_id".to_string()),
+        );
+        assert!(is_server_failure(&e));
+        assert!(e
+            .downcast_ref::<String>()
+            .is_some_and(|err_str| err_str.contains("status: 503")));
+    }
+
+    #[test]
+    fn test_status_error() {
+        let e =
+            anyhow!("Request error (status: 429, retry-after: 5, body: rate limited)".to_string());
+        let status_error = StatusError::parse(&e).unwrap();
+        assert_eq!(status_error.retry_after.as_deref(), Some("5"));
+        assert_eq!(
+            status_error.to_string(),
+            "API error 429: rate limited, retry after 5s"
+        );
+        let e = with_benchmark_id(request_error(500, "oops, again"), None);
+        assert_eq!(
+            StatusError::parse(&e).unwrap().to_string(),
+            "API error 500: oops, again"
+        );
+        assert_eq!(StatusError::parse(&anyhow!("connection refused")), None);
+    }
+
+    #[test]
+    fn test_trace_id() {
+        let trace_id = Uuid::new_v4();
+        let resp = |echoed: Option<String>| HttpResponse {
+            status: 200,
+            headers: echoed
+                .map(|v| vec![(TRACE_ID_HEADER.to_string(), v)])
+                .unwrap_or_default(),
+            body: "{}".to_string(),
+        };
+        assert_eq!(check_trace_id(trace_id, resp(None)), "{}");
+        assert_eq!(
+            check_trace_id(trace_id, resp(Some(trace_id.to_string()))),
+            "{}"
+        );
+        // a 2xx has been handled, so a different trace id must not turn it into a retried error
+        assert_eq!(
+            check_trace_id(trace_id, resp(Some(Uuid::new_v4().to_string()))),
+            "{}"
+        );
+        let e = with_benchmark_id(
+            request_error(500, "oops").context(TraceContext { trace_id }),
+            Some("client_id".to_string()),
+        );
+        assert_eq!(error_trace_id(&e), Some(trace_id));
+        assert_eq!(error_benchmark_id(&e), Some("client_id"));
+        assert_eq!(StatusError::parse(&e).unwrap().status, 500);
+    }
+
+    #[test]
+    fn test_verified_pubkey() {
+        let signature = Ed25519Signer::from_bytes(&[7u8; 32]).sign(b"req");
+        let public_key = hex::encode(&signature.public_key);
+        let resp = |echoed: Option<&str>| HttpResponse {
+            status: 200,
+            headers: echoed
+                .map(|v| vec![(VERIFIED_PUBKEY_HEADER.to_string(), v.to_string())])
+                .unwrap_or_default(),
+            body: "{}".to_string(),
+        };
+        assert!(check_verified_pubkey(None, &resp(None)).is_ok());
+        assert!(check_verified_pubkey(Some(&signature), &resp(Some(&public_key))).is_ok());
+        for echoed in [None, Some("00".repeat(32).as_str())] {
+            let e = check_verified_pubkey(Some(&signature), &resp(echoed)).unwrap_err();
+            assert!(matches!(
+                e.downcast_ref::<ApiError>(),
+                Some(ApiError::PubkeyMismatch { sent, .. }) if *sent == public_key
+            ));
+        }
+    }
+
+    #[test]
+    fn test_response_too_large() {
+        let e = with_response_too_large(
+            BodyTooLargeError {
+                limit: 10,
+                received_so_far: 16,
+            }
+            .into(),
+        );
+        assert!(matches!(
+            e.downcast_ref::<ApiError>(),
+            Some(ApiError::ResponseTooLarge {
+                limit: 10,
+                received_so_far: 16
+            })
+        ));
+        let e = with_response_too_large(request_error(500, "oops"));
+        assert_eq!(StatusError::parse(&e).unwrap().status, 500);
+    }
+
+    #[test]
+    fn test_invalid_response_keeps_source_and_body() {
+        let e = dejsonify_response::<u32>("<html>Bad Gateway</html>").unwrap_err();
+        assert!(e.to_string().starts_with("Failed to dejsonify: "));
+        assert!(e
+            .to_string()
+            .ends_with("Response body: '<html>Bad Gateway</html>'"));
+        assert!(
+            std::error::Error::source(e.downcast_ref::<ApiError>().unwrap())
+                .unwrap()
+                .is::<serde_json::Error>()
+        );
+    }
+
+    fn raw_body(body: &str) -> String {
+        match dejsonify_response::<u32>(body)
+            .unwrap_err()
+            .downcast::<ApiError>()
+        {
+            Ok(ApiError::DeserializationFailed { raw_body, .. }) => raw_body,
+            other => panic!("expected DeserializationFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_raw_body_is_truncated() {
+        assert_eq!(raw_body(&"x".repeat(10000)).len(), error::MAX_RAW_BODY_LEN);
+        // never splits a character
+        let body = format!("x{}", "é".repeat(5000));
+        assert_eq!(raw_body(&body), body[..error::MAX_RAW_BODY_LEN - 1]);
+    }
+
+    #[test]
+    fn test_raw_body_redacts_sensitive_fields() {
+        assert_eq!(
+            raw_body(r#"{"api_key": "abc", "player_id": "key", "secret":"a\"b", "x" 1"#),
+            r#"{"api_key": "<redacted>", "player_id": "key", "secret":"<redacted>", "x" 1"#
+        );
+        assert_eq!(
+            raw_body(r#"{"token": "unterminated"#),
+            r#"{"token": "<redacted>""#
+        );
     }
 }
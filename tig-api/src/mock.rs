@@ -0,0 +1,217 @@
+use super::*;
+use anyhow::anyhow;
+use futures::channel::oneshot;
+use std::collections::VecDeque;
+
+struct QueuedResponse {
+    resp: std::result::Result<String, String>,
+    // the call waits on this before returning `resp`
+    hold: Option<oneshot::Receiver<()>>,
+}
+
+// records every call and replays responses queued per method, in order. a call with no
+// response queued fails, so a test only has to configure the calls it expects
+#[derive(Default)]
+pub struct MockBenchmarkApi {
+    calls: Mutex<Vec<(&'static str, String)>>,
+    responses: Mutex<HashMap<&'static str, VecDeque<QueuedResponse>>>,
+}
+
+impl MockBenchmarkApi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_response<T: Serialize>(&self, method: &'static str, resp: &T) -> &Self {
+        self.queue(method, Ok(jsonify(resp)))
+    }
+
+    // errors are returned as strings so they can be classified the same way as those from
+    // tig-utils, e.g. "Request error (status: 503, body: ...)"
+    pub fn push_error(&self, method: &'static str, error: impl Into<String>) -> &Self {
+        self.queue(method, Err(error.into()))
+    }
+
+    // the call that gets `resp` only returns once the returned sender is used or dropped, so a
+    // test can keep that call in flight while it makes others
+    pub fn push_held_response<T: Serialize>(
+        &self,
+        method: &'static str,
+        resp: &T,
+    ) -> oneshot::Sender<()> {
+        let (release, hold) = oneshot::channel();
+        self.push_back(
+            method,
+            QueuedResponse {
+                resp: Ok(jsonify(resp)),
+                hold: Some(hold),
+            },
+        );
+        release
+    }
+
+    fn queue(&self, method: &'static str, resp: std::result::Result<String, String>) -> &Self {
+        self.push_back(method, QueuedResponse { resp, hold: None })
+    }
+
+    fn push_back(&self, method: &'static str, resp: QueuedResponse) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(method)
+            .or_default()
+            .push_back(resp);
+        self
+    }
+
+    // forgets every call and queued response, for tests that share one mock
+    pub fn reset(&self) {
+        self.calls.lock().unwrap().clear();
+        self.responses.lock().unwrap().clear();
+    }
+
+    // the jsonified requests for `method`, oldest first
+    pub fn calls(&self, method: &str) -> Vec<String> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(m, _)| *m == method)
+            .map(|(_, req)| req.clone())
+            .collect()
+    }
+
+    pub fn assert_called_n_times(&self, method: &str, n: usize) {
+        let num_calls = self.calls(method).len();
+        assert_eq!(
+            num_calls, n,
+            "Expected {} to be called {} times, but it was called {} times",
+            method, n, num_calls
+        );
+    }
+
+    async fn respond<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        req: &Req,
+    ) -> Result<Resp> {
+        self.calls.lock().unwrap().push((method, jsonify(req)));
+        let queued = self
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(method)
+            .and_then(|r| r.pop_front())
+            .ok_or_else(|| anyhow!("MockBenchmarkApi has no response queued for {}", method))?;
+        if let Some(hold) = queued.hold {
+            // a dropped sender releases the call too
+            let _ = hold.await;
+        }
+        match queued.resp {
+            Ok(resp) => dejsonify_response::<Resp>(&resp),
+            Err(e) => Err(anyhow!(e)),
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "request-js"), async_trait)]
+#[cfg_attr(feature = "request-js", async_trait(?Send))]
+impl BenchmarkApi for MockBenchmarkApi {
+    async fn get_challenges(&self, req: GetChallengesReq) -> Result<GetChallengesResp> {
+        self.respond("get_challenges", &req).await
+    }
+    async fn get_algorithms(&self, req: GetAlgorithmsReq) -> Result<GetAlgorithmsResp> {
+        self.respond("get_algorithms", &req).await
+    }
+    async fn get_players(&self, req: GetPlayersReq) -> Result<GetPlayersResp> {
+        self.respond("get_players", &req).await
+    }
+    async fn get_benchmarks(&self, req: GetBenchmarksReq) -> Result<GetBenchmarksResp> {
+        self.respond("get_benchmarks", &req).await
+    }
+    async fn get_block(&self, req: GetBlockReq) -> Result<GetBlockResp> {
+        self.respond("get_block", &req).await
+    }
+    async fn get_protocol(&self) -> Result<GetProtocolResp> {
+        self.respond("get_protocol", &()).await
+    }
+    async fn submit_benchmark(&self, req: SubmitBenchmarkReq) -> Result<SubmitBenchmarkResp> {
+        self.respond("submit_benchmark", &req).await
+    }
+    async fn submit_benchmark_signed(
+        &self,
+        req: SubmitBenchmarkReq,
+        _chunk_size: usize,
+        _signer: &dyn Signer,
+    ) -> Result<SubmitBenchmarkResp> {
+        self.respond("submit_benchmark_signed", &req).await
+    }
+    async fn submit_benchmarks_batch(&self, req: SubmitBatchReq) -> Result<SubmitBatchResp> {
+        self.respond("submit_benchmarks_batch", &req).await
+    }
+    async fn submit_proof(&self, req: SubmitProofReq) -> Result<SubmitProofResp> {
+        self.respond("submit_proof", &req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn submit_proof_req() -> SubmitProofReq {
+        SubmitProofReq {
+            benchmark_id: "benchmark".to_string(),
+            solutions_data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_replays_responses_in_order() {
+        let mock = MockBenchmarkApi::new();
+        mock.push_error("submit_proof", "Request error (status: 503, body: busy)")
+            .push_response("submit_proof", &SubmitProofResp { verified: Ok(()) });
+        let first = block_on(mock.submit_proof(submit_proof_req()));
+        assert_eq!(
+            first
+                .unwrap_err()
+                .downcast_ref::<String>()
+                .map(String::as_str),
+            Some("Request error (status: 503, body: busy)")
+        );
+        let second = block_on(mock.submit_proof(submit_proof_req())).unwrap();
+        assert_eq!(second.verified, Ok(()));
+        mock.assert_called_n_times("submit_proof", 2);
+        mock.assert_called_n_times("submit_benchmark", 0);
+    }
+
+    #[test]
+    fn test_held_response_waits_for_release() {
+        let mock = MockBenchmarkApi::new();
+        let release =
+            mock.push_held_response("submit_proof", &SubmitProofResp { verified: Ok(()) });
+        block_on(async {
+            let mut call = Box::pin(mock.submit_proof(submit_proof_req()));
+            assert!(futures::poll!(&mut call).is_pending());
+            release.send(()).unwrap();
+            assert_eq!(call.await.unwrap().verified, Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_reset_forgets_calls_and_responses() {
+        let mock = MockBenchmarkApi::new();
+        mock.push_response("submit_proof", &SubmitProofResp { verified: Ok(()) });
+        let _ = block_on(mock.get_latest_block());
+        mock.reset();
+        mock.assert_called_n_times("get_block", 0);
+        assert!(block_on(mock.submit_proof(submit_proof_req())).is_err());
+    }
+
+    #[test]
+    fn test_fails_without_queued_response() {
+        let mock = MockBenchmarkApi::new();
+        assert!(block_on(mock.get_latest_block()).is_err());
+        mock.assert_called_n_times("get_block", 1);
+    }
+}
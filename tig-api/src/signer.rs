@@ -0,0 +1,108 @@
+use super::ApiError;
+use anyhow::Result;
+use ed25519_dalek::{Signer as _, SigningKey};
+
+pub const SIGNATURE_HEADER: &str = "x-signature";
+pub const PUBLIC_KEY_HEADER: &str = "x-public-key";
+// servers that accept a signed request echo the public key they verified it with
+pub const VERIFIED_PUBKEY_HEADER: &str = "x-verified-pubkey";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub public_key: Vec<u8>,
+    pub bytes: Vec<u8>,
+}
+
+impl Signature {
+    // sent hex encoded in the `x-signature` and `x-public-key` headers
+    pub fn headers(&self) -> Vec<(String, String)> {
+        vec![
+            (SIGNATURE_HEADER.to_string(), hex::encode(&self.bytes)),
+            (PUBLIC_KEY_HEADER.to_string(), hex::encode(&self.public_key)),
+        ]
+    }
+}
+
+// the identity a benchmark is submitted as. signers are compared by public key
+pub trait Signer: Send + Sync + std::fmt::Debug {
+    fn public_key(&self) -> Vec<u8>;
+    fn sign(&self, payload: &[u8]) -> Signature;
+}
+
+impl PartialEq for dyn Signer {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_key() == other.public_key()
+    }
+}
+
+pub struct Ed25519Signer {
+    signing_key: SigningKey,
+}
+
+impl Ed25519Signer {
+    pub fn from_bytes(secret_key: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret_key),
+        }
+    }
+
+    // 64 hex characters, surrounding whitespace ignored
+    pub fn from_hex(secret_key: &str) -> Result<Self> {
+        let secret_key: [u8; 32] = hex::decode(secret_key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(ApiError::InvalidSigningKey)?;
+        Ok(Self::from_bytes(&secret_key))
+    }
+}
+
+impl Signer for Ed25519Signer {
+    fn public_key(&self) -> Vec<u8> {
+        self.signing_key.verifying_key().to_bytes().to_vec()
+    }
+
+    fn sign(&self, payload: &[u8]) -> Signature {
+        Signature {
+            public_key: self.public_key(),
+            bytes: self.signing_key.sign(payload).to_bytes().to_vec(),
+        }
+    }
+}
+
+// never prints the secret key
+impl std::fmt::Debug for Ed25519Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ed25519Signer")
+            .field("public_key", &hex::encode(self.public_key()))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Verifier, VerifyingKey};
+
+    #[test]
+    fn test_ed25519_signature_verifies() {
+        let signer = Ed25519Signer::from_bytes(&[7u8; 32]);
+        let signature = signer.sign(b"payload");
+        let verifying_key =
+            VerifyingKey::from_bytes(&signature.public_key.clone().try_into().unwrap()).unwrap();
+        let sig = ed25519_dalek::Signature::from_slice(&signature.bytes).unwrap();
+        assert!(verifying_key.verify(b"payload", &sig).is_ok());
+        assert!(verifying_key.verify(b"tampered", &sig).is_err());
+    }
+
+    #[test]
+    fn test_from_hex() {
+        let signer = Ed25519Signer::from_hex(&format!("{}\n", "07".repeat(32))).unwrap();
+        assert_eq!(
+            signer.public_key(),
+            Ed25519Signer::from_bytes(&[7u8; 32]).public_key()
+        );
+        assert!(Ed25519Signer::from_hex("07").is_err());
+        assert!(Ed25519Signer::from_hex(&"zz".repeat(32)).is_err());
+        assert!(!format!("{:?}", signer).contains(&"07".repeat(32)));
+    }
+}
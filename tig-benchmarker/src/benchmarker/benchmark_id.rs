@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, convert::Infallible, fmt, str::FromStr};
+
+// id of a benchmark, as assigned by the api. serialized as a plain string, so the snapshot,
+// checkpoints and jobs sent to slaves are unchanged
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[serde(transparent)]
+pub struct BenchmarkId(String);
+
+impl BenchmarkId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for BenchmarkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for BenchmarkId {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl AsRef<str> for BenchmarkId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// hashes and orders the same as the inner string, so maps keyed by id can be looked up by &str
+impl Borrow<str> for BenchmarkId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for BenchmarkId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&str> for BenchmarkId {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<BenchmarkId> for String {
+    fn from(id: BenchmarkId) -> Self {
+        id.0
+    }
+}
+
+impl PartialEq<str> for BenchmarkId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for BenchmarkId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for BenchmarkId {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_serializes_as_string() {
+        let id: BenchmarkId = "abc".parse().unwrap();
+        assert_eq!(serde_json::to_string(&id).unwrap(), r#""abc""#);
+        assert_eq!(serde_json::from_str::<BenchmarkId>(r#""abc""#).unwrap(), id);
+        assert_eq!(id.to_string(), "abc");
+    }
+
+    #[test]
+    fn test_lookup_by_str() {
+        let mut map = HashMap::new();
+        map.insert(BenchmarkId::new("abc"), 1);
+        assert_eq!(map.get("abc"), Some(&1));
+        assert!(BenchmarkId::new("a") < BenchmarkId::new("b"));
+    }
+}
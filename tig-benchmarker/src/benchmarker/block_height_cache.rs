@@ -0,0 +1,115 @@
+use super::{query_data::query_latest_block_from, Result};
+use std::time::{Duration, Instant};
+use tig_api::BenchmarkApi;
+use tig_structs::core::Block;
+use tokio::sync::RwLock;
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(1);
+
+// the latest block, reused for `ttl` so that fast retries do not each query the api for a
+// height that has not changed
+#[derive(Debug)]
+pub struct BlockHeightCache {
+    ttl: Duration,
+    entry: RwLock<Option<(Block, Instant)>>,
+}
+
+impl Default for BlockHeightCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl BlockHeightCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: RwLock::new(None),
+        }
+    }
+
+    pub async fn get(&self, api: &dyn BenchmarkApi) -> Result<Block> {
+        if let Some(block) = self.fresh(&*self.entry.read().await) {
+            return Ok(block);
+        }
+        // held across the query, so concurrent misses wait for one query instead of each
+        // sending their own
+        let mut entry = self.entry.write().await;
+        if let Some(block) = self.fresh(&entry) {
+            return Ok(block);
+        }
+        let block = query_latest_block_from(api).await?;
+        *entry = Some((block.clone(), Instant::now()));
+        Ok(block)
+    }
+
+    // the next `get` queries the api
+    pub async fn reset(&self) {
+        *self.entry.write().await = None;
+    }
+
+    fn fresh(&self, entry: &Option<(Block, Instant)>) -> Option<Block> {
+        entry
+            .as_ref()
+            .filter(|(_, fetched_at)| fetched_at.elapsed() < self.ttl)
+            .map(|(block, _)| block.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tig_api::{mock::MockBenchmarkApi, GetBlockResp};
+    use tig_structs::core::BlockDetails;
+
+    fn block(height: u32) -> GetBlockResp {
+        GetBlockResp {
+            block: Some(Block {
+                id: format!("block{}", height),
+                details: BlockDetails {
+                    prev_block_id: String::new(),
+                    height,
+                    round: 0,
+                    eth_block_num: None,
+                },
+                data: None,
+                config: None,
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hit_within_ttl() {
+        let api = MockBenchmarkApi::new();
+        api.push_response("get_block", &block(100))
+            .push_response("get_block", &block(101));
+        let cache = BlockHeightCache::new(Duration::from_secs(60));
+        assert_eq!(cache.get(&api).await.unwrap().details.height, 100);
+        assert_eq!(cache.get(&api).await.unwrap().details.height, 100);
+        api.assert_called_n_times("get_block", 1);
+        cache.reset().await;
+        assert_eq!(cache.get(&api).await.unwrap().details.height, 101);
+        api.assert_called_n_times("get_block", 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entry_is_refreshed() {
+        let api = MockBenchmarkApi::new();
+        api.push_response("get_block", &block(100))
+            .push_response("get_block", &block(101));
+        let cache = BlockHeightCache::new(Duration::ZERO);
+        assert_eq!(cache.get(&api).await.unwrap().details.height, 100);
+        assert_eq!(cache.get(&api).await.unwrap().details.height, 101);
+        api.assert_called_n_times("get_block", 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_query_is_not_cached() {
+        let api = MockBenchmarkApi::new();
+        api.push_error("get_block", "Request error (status: 503, body: busy)")
+            .push_response("get_block", &block(100));
+        let cache = BlockHeightCache::default();
+        assert!(cache.get(&api).await.is_err());
+        assert_eq!(cache.get(&api).await.unwrap().details.height, 100);
+    }
+}
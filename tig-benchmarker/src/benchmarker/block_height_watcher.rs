@@ -0,0 +1,197 @@
+#[cfg(feature = "standalone")]
+use super::{api, block_height_cache::BlockHeightCache};
+use super::{
+    job_runner, query_data::query_latest_block, submission_config, utils::jitter_rng,
+    BenchmarkerError, Result, RetryPolicy,
+};
+use crate::future_utils::{sleep, spawn, time};
+use once_cell::sync::OnceCell;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tracing::warn;
+
+static WATCHER: OnceCell<BlockHeightWatcher> = OnceCell::new();
+
+// the api has no push channel for new blocks, so the watcher polls in the background and
+// callers read the cached height instead of making a round-trip per retry
+#[derive(Debug, Clone)]
+pub struct BlockHeightWatcher {
+    height: Arc<AtomicU64>,
+    updated_at_ms: Arc<AtomicU64>,
+    // answers callers that find the height stale, so their retries share one query
+    #[cfg(feature = "standalone")]
+    cache: Arc<BlockHeightCache>,
+}
+
+pub fn block_height_watcher() -> &'static BlockHeightWatcher {
+    WATCHER.get_or_init(|| BlockHeightWatcher {
+        height: Arc::new(AtomicU64::new(0)),
+        updated_at_ms: Arc::new(AtomicU64::new(0)),
+        #[cfg(feature = "standalone")]
+        cache: Arc::new(BlockHeightCache::new(std::time::Duration::from_millis(
+            submission_config().block_cache_ttl_ms,
+        ))),
+    })
+}
+
+impl BlockHeightWatcher {
+    pub fn height(&self) -> Arc<AtomicU64> {
+        self.height.clone()
+    }
+
+    // None if the watcher has not seen a block within `stale_after_ms`
+    pub fn fresh_height(&self, stale_after_ms: u64) -> Option<u32> {
+        let updated_at_ms = self.updated_at_ms.load(Ordering::SeqCst);
+        if updated_at_ms == 0 || time().saturating_sub(updated_at_ms) >= stale_after_ms {
+            None
+        } else {
+            Some(self.height.load(Ordering::SeqCst) as u32)
+        }
+    }
+
+    // a reorg can roll the height back, so the latest observation wins
+    fn update(&self, height: u32) {
+        self.height.store(height as u64, Ordering::SeqCst);
+        self.updated_at_ms.store(time(), Ordering::SeqCst);
+    }
+}
+
+pub fn start() {
+    static STARTED: OnceCell<()> = OnceCell::new();
+    if STARTED.set(()).is_ok() {
+        spawn(run());
+    }
+}
+
+async fn run() {
+    let config = submission_config();
+    // failed polls back off like submission retries, but never poll faster than the interval
+    let backoff = RetryPolicy {
+        base_delay_ms: config.block_poll_interval_ms,
+        max_delay_ms: config.retry_max_delay_ms.max(config.block_poll_interval_ms),
+        min_jitter_ms: 0,
+        max_jitter_ms: 0,
+    };
+    let mut failures = 0u32;
+    loop {
+        let delay_ms = match query_latest_block().await {
+            Ok(block) => {
+                failures = 0;
+                block_height_watcher().update(block.details.height);
+                job_runner::cancel_expired(block.details.height).await;
+                config.block_poll_interval_ms
+            }
+            Err(e) => {
+                failures += 1;
+                let delay_ms = backoff.delay_ms(failures + 1, &mut jitter_rng());
+                warn!(
+                    "Block height watcher failed to query latest block ({}). Retrying in {} ms",
+                    e, delay_ms
+                );
+                delay_ms
+            }
+        };
+        sleep(delay_ms.min(u32::MAX as u64) as u32).await;
+    }
+}
+
+// falls back to an http round-trip when the watcher's height is stale
+pub async fn current_height() -> Result<u32> {
+    let watcher = block_height_watcher();
+    match watcher.fresh_height(submission_config().block_stale_timeout_ms) {
+        Some(height) => Ok(height),
+        None => {
+            #[cfg(feature = "standalone")]
+            let height = watcher.cache.get(api()).await?.details.height;
+            #[cfg(feature = "browser")]
+            let height = query_latest_block().await?.details.height;
+            watcher.update(height);
+            Ok(height)
+        }
+    }
+}
+
+// `captured_height` is the height when a submission started. a lower height since then means
+// the chain reorganised, and the submission's view of its block can no longer be trusted
+pub fn check_block_height(
+    captured_height: u32,
+    current_height: u32,
+    block_started: u32,
+    lifespan_period: u32,
+) -> Result<()> {
+    if current_height < captured_height {
+        warn!(
+            "Block height rolled back from {} to {}. Chain reorganised",
+            captured_height, current_height
+        );
+        return Err(BenchmarkerError::BlockReorg {
+            captured_height,
+            current_height,
+        });
+    }
+    if current_height >= block_started + lifespan_period {
+        return Err(BenchmarkerError::BlockHeightStale {
+            block_started,
+            current_height,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // stands in for the api, returning one height per retry attempt
+    struct MockBlockSource {
+        heights: Vec<u32>,
+    }
+
+    impl MockBlockSource {
+        fn run(&self, block_started: u32, lifespan_period: u32) -> Result<usize> {
+            let captured_height = self.heights[0];
+            for height in self.heights.iter() {
+                check_block_height(captured_height, *height, block_started, lifespan_period)?;
+            }
+            Ok(self.heights.len())
+        }
+    }
+
+    #[test]
+    fn test_advancing_height_is_accepted() {
+        let source = MockBlockSource {
+            heights: vec![100, 100, 101, 102],
+        };
+        assert_eq!(source.run(95, 10).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_decrementing_height_is_a_reorg() {
+        let source = MockBlockSource {
+            heights: vec![100, 101, 99, 100],
+        };
+        assert!(matches!(
+            source.run(95, 10),
+            Err(BenchmarkerError::BlockReorg {
+                captured_height: 100,
+                current_height: 99,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_finalised_block_is_stale() {
+        let source = MockBlockSource {
+            heights: vec![100, 104, 105],
+        };
+        assert!(matches!(
+            source.run(95, 10),
+            Err(BenchmarkerError::BlockHeightStale {
+                block_started: 95,
+                current_height: 105,
+            })
+        ));
+    }
+}
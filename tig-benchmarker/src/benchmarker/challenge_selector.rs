@@ -0,0 +1,132 @@
+use super::QueryData;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChallengeConfig {
+    pub challenge_id: String,
+    pub challenge_name: String,
+    // relative reward for benchmarking this challenge. the protocol has no per-challenge fee,
+    // so this is how far the player's share of qualifiers on it lags behind their best one
+    pub fee: f64,
+}
+
+// narrows down the challenges in the algorithm selection. a job's challenge is then sampled
+// from the result, weighted by fee
+pub trait ChallengeSelector: Send + Sync {
+    fn select(&self, available: &[ChallengeConfig], query_data: &QueryData)
+        -> Vec<ChallengeConfig>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AllChallenges;
+
+impl ChallengeSelector for AllChallenges {
+    fn select(&self, available: &[ChallengeConfig], _: &QueryData) -> Vec<ChallengeConfig> {
+        available.to_vec()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TopNByFee(pub usize);
+
+impl ChallengeSelector for TopNByFee {
+    fn select(&self, available: &[ChallengeConfig], _: &QueryData) -> Vec<ChallengeConfig> {
+        let mut selected = available.to_vec();
+        selected.sort_by(|a, b| b.fee.total_cmp(&a.fee));
+        selected.truncate(self.0);
+        selected
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeSelectionStrategy {
+    #[default]
+    AllChallenges,
+    TopNByFee(usize),
+}
+
+impl ChallengeSelectionStrategy {
+    pub fn build(&self) -> Box<dyn ChallengeSelector> {
+        match self {
+            ChallengeSelectionStrategy::AllChallenges => Box::new(AllChallenges),
+            ChallengeSelectionStrategy::TopNByFee(n) => Box::new(TopNByFee(*n)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tig_structs::core::{Block, BlockDetails};
+
+    fn query_data() -> QueryData {
+        QueryData {
+            latest_block: Block {
+                id: String::new(),
+                details: BlockDetails {
+                    prev_block_id: String::new(),
+                    height: 0,
+                    round: 0,
+                    eth_block_num: None,
+                },
+                data: None,
+                config: None,
+            },
+            player_data: None,
+            challenges: Vec::new(),
+            download_urls: HashMap::new(),
+            algorithms_by_challenge: HashMap::new(),
+            benchmarks: HashMap::new(),
+            proofs: HashMap::new(),
+            frauds: HashMap::new(),
+            solutions_data: HashMap::new(),
+            recency: Default::default(),
+        }
+    }
+
+    fn available() -> Vec<ChallengeConfig> {
+        [0.3, 0.9, 0.1, 0.5, 0.7, 0.2]
+            .into_iter()
+            .enumerate()
+            .map(|(i, fee)| ChallengeConfig {
+                challenge_id: format!("c00{}", i + 1),
+                challenge_name: format!("challenge_{}", i + 1),
+                fee,
+            })
+            .collect()
+    }
+
+    fn ids(selected: Vec<ChallengeConfig>) -> Vec<String> {
+        selected.into_iter().map(|c| c.challenge_id).collect()
+    }
+
+    #[test]
+    fn test_all_challenges() {
+        let selected = AllChallenges.select(&available(), &query_data());
+        assert_eq!(selected, available());
+    }
+
+    #[test]
+    fn test_top_n_by_fee() {
+        let selected = TopNByFee(2).select(&available(), &query_data());
+        assert_eq!(ids(selected), vec!["c002", "c005"]);
+    }
+
+    #[test]
+    fn test_top_n_by_fee_larger_than_available() {
+        let selected = TopNByFee(10).select(&available(), &query_data());
+        assert_eq!(selected.len(), 6);
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        let strategy: ChallengeSelectionStrategy =
+            serde_json::from_str(r#"{"top_n_by_fee": 2}"#).unwrap();
+        assert_eq!(strategy, ChallengeSelectionStrategy::TopNByFee(2));
+        let strategy: ChallengeSelectionStrategy =
+            serde_json::from_str(r#""all_challenges""#).unwrap();
+        assert_eq!(strategy, ChallengeSelectionStrategy::AllChallenges);
+    }
+}
@@ -0,0 +1,173 @@
+use super::{BenchmarkerError, CompressedSolutionData, Job, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tig_structs::core::SolutionMetaData;
+use tracing::warn;
+
+pub const CHECKPOINT_EXTENSION: &str = "ckpt";
+
+// progress of a benchmark that is still generating solutions
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub job: Job,
+    pub block_started: u32,
+    /// next nonce of each worker, i.e. where generation resumes
+    pub next_nonces: Vec<u64>,
+    pub solutions_meta_data: Vec<SolutionMetaData>,
+    // kept alongside the meta data so a resumed benchmark can still submit its proof
+    pub solutions_data: CompressedSolutionData,
+}
+
+pub fn checkpoint_path(dir: &Path, benchmark_id: &str) -> PathBuf {
+    dir.join(benchmark_id).with_extension(CHECKPOINT_EXTENSION)
+}
+
+// every checkpoint in `dir`. unreadable files are skipped rather than failing the rest
+pub fn load_all(dir: &Path) -> Result<Vec<Checkpoint>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut checkpoints = Vec::new();
+    for entry in fs::read_dir(dir).map_err(BenchmarkerError::CheckpointIo)? {
+        let path = entry.map_err(BenchmarkerError::CheckpointIo)?.path();
+        if path.extension().is_some_and(|e| e == CHECKPOINT_EXTENSION) {
+            match load(&path) {
+                Ok(checkpoint) => checkpoints.push(checkpoint),
+                Err(e) => warn!("Ignoring checkpoint {:?}: {}", path, e),
+            }
+        }
+    }
+    Ok(checkpoints)
+}
+
+pub fn load(path: &Path) -> Result<Checkpoint> {
+    let data = fs::read(path).map_err(BenchmarkerError::CheckpointIo)?;
+    serde_json::from_slice(&data).map_err(BenchmarkerError::CheckpointSerde)
+}
+
+// unlinking is atomic, so a crash leaves either the whole checkpoint or none of it
+pub fn remove(dir: &Path, benchmark_id: &str) -> Result<()> {
+    match fs::remove_file(checkpoint_path(dir, benchmark_id)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(BenchmarkerError::CheckpointIo(e)),
+    }
+}
+
+// passed to the solution generation loop. writes `<benchmark_id>.ckpt` every `interval`
+// solutions
+pub struct CheckpointWriter {
+    path: PathBuf,
+    interval: u32,
+    last_written: u32,
+}
+
+impl CheckpointWriter {
+    pub fn new(dir: &Path, benchmark_id: &str, interval: u32) -> Self {
+        Self {
+            path: checkpoint_path(dir, benchmark_id),
+            interval: interval.max(1),
+            last_written: 0,
+        }
+    }
+
+    // a writer for a resumed benchmark does not rewrite what its checkpoint already holds
+    pub fn resumed(mut self, num_solutions: u32) -> Self {
+        self.last_written = num_solutions;
+        self
+    }
+
+    pub fn is_due(&self, num_solutions: u32) -> bool {
+        num_solutions >= self.last_written.saturating_add(self.interval)
+    }
+
+    // writes to a sibling .tmp file then renames it over the checkpoint, like the snapshot
+    pub fn write(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        let data = serde_json::to_vec(checkpoint).map_err(BenchmarkerError::CheckpointSerde)?;
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp_path).map_err(BenchmarkerError::CheckpointIo)?;
+        file.write_all(&data)
+            .map_err(BenchmarkerError::CheckpointIo)?;
+        file.sync_all().map_err(BenchmarkerError::CheckpointIo)?;
+        fs::rename(&tmp_path, &self.path).map_err(BenchmarkerError::CheckpointIo)?;
+        self.last_written = checkpoint.solutions_meta_data.len() as u32;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::future_utils::time;
+    use tig_structs::{config::WasmVMConfig, core::BenchmarkSettings};
+    use tig_utils::CompressionConfig;
+
+    fn checkpoint(benchmark_id: &str, num_solutions: u64) -> Checkpoint {
+        Checkpoint {
+            job: Job {
+                download_url: String::new(),
+                benchmark_id: benchmark_id.into(),
+                settings: BenchmarkSettings {
+                    player_id: String::new(),
+                    block_id: String::new(),
+                    challenge_id: "c001".to_string(),
+                    algorithm_id: String::new(),
+                    difficulty: vec![50, 300],
+                },
+                solution_signature_threshold: u32::MAX,
+                sampled_nonces: None,
+                wasm_vm_config: WasmVMConfig {
+                    max_memory: 0,
+                    max_fuel: 0,
+                },
+                signer: None,
+            },
+            block_started: 100,
+            next_nonces: vec![42],
+            solutions_meta_data: (0..num_solutions)
+                .map(|nonce| SolutionMetaData {
+                    nonce,
+                    solution_signature: 1,
+                })
+                .collect(),
+            solutions_data: CompressedSolutionData::new(CompressionConfig::default()),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tig-checkpoint-{}-{}", name, time()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_writes_every_interval() {
+        let dir = temp_dir("interval");
+        let mut writer = CheckpointWriter::new(&dir, "abc", 10);
+        assert!(!writer.is_due(9));
+        assert!(writer.is_due(10));
+        writer.write(&checkpoint("abc", 12)).unwrap();
+        assert!(!writer.is_due(21));
+        assert!(writer.is_due(22));
+        assert!(dir.join("abc.ckpt").is_file());
+        assert!(!dir.join("abc.tmp").exists());
+    }
+
+    #[test]
+    fn test_load_all_and_remove() {
+        let dir = temp_dir("load");
+        CheckpointWriter::new(&dir, "abc", 1)
+            .write(&checkpoint("abc", 3))
+            .unwrap();
+        fs::write(dir.join("corrupt.ckpt"), "{").unwrap();
+        fs::write(dir.join("unrelated.json"), "{}").unwrap();
+        assert_eq!(load_all(&dir).unwrap(), vec![checkpoint("abc", 3)]);
+        remove(&dir, "abc").unwrap();
+        remove(&dir, "abc").unwrap();
+        assert!(load_all(&dir).unwrap().is_empty());
+    }
+}
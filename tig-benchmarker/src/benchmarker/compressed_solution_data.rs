@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tig_structs::core::SolutionData;
+use tig_utils::{decompress, dejsonify, jsonify, CompressionConfig};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Entry {
+    nonce: u64,
+    #[serde(with = "base64_bytes")]
+    data: Vec<u8>,
+}
+
+// solutions data of a benchmark, compressed per entry on insertion so reading one entry
+// (e.g. the solution_data sent with a benchmark) does not decompress the rest
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompressedSolutionData {
+    config: CompressionConfig,
+    entries: Vec<Entry>,
+}
+
+impl CompressedSolutionData {
+    pub fn new(config: CompressionConfig) -> Self {
+        Self {
+            config,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, solution_data: &SolutionData) {
+        self.entries.push(Entry {
+            nonce: solution_data.nonce,
+            data: self
+                .config
+                .compress(jsonify(solution_data).as_bytes())
+                .expect("compression config is validated at startup"),
+        });
+    }
+
+    pub fn extend<'a>(&mut self, solutions_data: impl IntoIterator<Item = &'a SolutionData>) {
+        for d in solutions_data {
+            self.push(d);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // total size of the stored entries, excluding nonces
+    pub fn stored_bytes(&self) -> usize {
+        self.entries.iter().map(|e| e.data.len()).sum()
+    }
+
+    pub fn nonces(&self) -> impl Iterator<Item = u64> + '_ {
+        self.entries.iter().map(|e| e.nonce)
+    }
+
+    pub fn retain_nonces(&mut self, nonces: &HashSet<u64>) {
+        self.entries.retain(|e| nonces.contains(&e.nonce));
+    }
+
+    pub fn first(&self) -> Option<SolutionData> {
+        self.entries.first().map(|e| self.decompress(e))
+    }
+
+    // decompresses one entry at a time
+    pub fn iter(&self) -> impl Iterator<Item = SolutionData> + '_ {
+        self.entries.iter().map(|e| self.decompress(e))
+    }
+
+    pub fn into_vec(self) -> Vec<SolutionData> {
+        self.iter().collect()
+    }
+
+    fn decompress(&self, entry: &Entry) -> SolutionData {
+        let data = decompress(self.config.algorithm, &entry.data)
+            .expect("entries are compressed by this wrapper");
+        dejsonify(std::str::from_utf8(&data).expect("entries are jsonified"))
+            .expect("entries are jsonified SolutionData")
+    }
+}
+
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        STANDARD.decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tig_structs::core::Solution;
+    use tig_utils::CompressionAlgorithm;
+
+    // shaped like a satisfiability solution: one boolean per variable
+    fn solutions_data(num_solutions: u64, num_variables: usize) -> Vec<SolutionData> {
+        (0..num_solutions)
+            .map(|nonce| {
+                let variables: Vec<bool> = (0..num_variables)
+                    .map(|i| (i as u64 * 7 + nonce) % 3 == 0)
+                    .collect();
+                let mut solution = Solution::new();
+                solution.insert("variables".to_string(), json!(variables));
+                SolutionData {
+                    nonce,
+                    runtime_signature: 1234567 + nonce as u32,
+                    fuel_consumed: 987654321 + nonce,
+                    solution,
+                }
+            })
+            .collect()
+    }
+
+    fn raw_bytes(solutions_data: &[SolutionData]) -> usize {
+        solutions_data.iter().map(|d| jsonify(d).len()).sum()
+    }
+
+    fn compressed(algorithm: CompressionAlgorithm, level: u32) -> CompressedSolutionData {
+        CompressedSolutionData::new(CompressionConfig { algorithm, level })
+    }
+
+    #[test]
+    fn test_round_trips_through_snapshot() {
+        let solutions_data = solutions_data(5, 100);
+        let mut data = compressed(CompressionAlgorithm::Gzip, 6);
+        data.extend(solutions_data.iter());
+        let restored: CompressedSolutionData =
+            serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+        assert_eq!(restored, data);
+        assert_eq!(restored.first(), solutions_data.first().cloned());
+        assert_eq!(restored.into_vec(), solutions_data);
+    }
+
+    #[test]
+    fn test_retain_nonces() {
+        let mut data = compressed(CompressionAlgorithm::None, 0);
+        data.extend(solutions_data(5, 10).iter());
+        data.retain_nonces(&HashSet::from([1, 3]));
+        assert_eq!(data.nonces().collect::<Vec<u64>>(), vec![1, 3]);
+        assert_eq!(data.first().unwrap().nonce, 1);
+    }
+
+    // run with --nocapture to see the savings
+    #[test]
+    fn test_memory_savings() {
+        let solutions_data = solutions_data(200, 5000);
+        let raw = raw_bytes(&solutions_data);
+        let mut algorithms = vec![(CompressionAlgorithm::Gzip, 6)];
+        if cfg!(feature = "zstd") {
+            algorithms.push((CompressionAlgorithm::Zstd, 3));
+        }
+        for (algorithm, level) in algorithms {
+            let mut data = compressed(algorithm, level);
+            data.extend(solutions_data.iter());
+            let stored = data.stored_bytes();
+            println!(
+                "{:?} level {}: {} bytes -> {} bytes ({:.1}x)",
+                algorithm,
+                level,
+                raw,
+                stored,
+                raw as f64 / stored as f64
+            );
+            assert!(stored * 4 < raw);
+        }
+    }
+}
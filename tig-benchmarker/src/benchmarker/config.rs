@@ -0,0 +1,420 @@
+use super::{
+    BenchmarkerError, ChallengeSelectionStrategy, OverflowPolicy, Result, RetryPolicy,
+    SolutionSelector, SortStrategy,
+};
+use serde::{Deserialize, Serialize};
+pub use tig_api::ApiConfig;
+use tig_api::{CompressionAlgorithm, CompressionConfig};
+
+// every section and field is optional. missing ones take the values from the `Default` impls
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct BenchmarkerConfig {
+    pub api: ApiConfig,
+    pub submission: SubmissionConfig,
+    pub scheduler: SchedulerConfig,
+    pub metrics: MetricsConfig,
+    pub storage: StorageConfig,
+    pub selection: SelectionConfig,
+    pub history: HistoryConfig,
+    pub resources: ResourcesConfig,
+    pub workers: WorkersConfig,
+}
+
+impl BenchmarkerConfig {
+    #[cfg(feature = "standalone")]
+    pub fn from_toml_file(path: &std::path::Path) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| BenchmarkerError::ConfigLoadFailed {
+                path: path.to_path_buf(),
+                error: e.to_string(),
+            })?;
+        toml::from_str(&contents).map_err(|e| BenchmarkerError::ConfigLoadFailed {
+            path: path.to_path_buf(),
+            error: e.to_string(),
+        })
+    }
+
+    // reports every problem at once rather than one per restart
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        if self.api.endpoints.is_empty() {
+            errors.push("api.endpoints must contain at least one url".to_string());
+        }
+        for endpoint in self.api.endpoints.iter() {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                errors.push(format!(
+                    "api.endpoints entry '{}' must start with http:// or https://",
+                    endpoint
+                ));
+            }
+        }
+        if let Some(path) = &self.api.tls_ca_bundle {
+            if !path.is_file() {
+                errors.push(format!(
+                    "api.tls_ca_bundle '{}' does not exist",
+                    path.display()
+                ));
+            }
+        }
+        if self.api.connect_timeout_ms == Some(0) {
+            errors.push("api.connect_timeout_ms must be greater than 0".to_string());
+        }
+        if self.api.request_timeout_ms == Some(0) {
+            errors.push("api.request_timeout_ms must be greater than 0".to_string());
+        }
+        let submission = &self.submission;
+        if submission.max_retries == 0 {
+            errors.push("submission.max_retries must be at least 1".to_string());
+        }
+        if let Some(path) = &submission.signing_key_path {
+            if !path.is_file() {
+                errors.push(format!(
+                    "submission.signing_key_path '{}' does not exist",
+                    path.display()
+                ));
+            }
+        }
+        if submission.retry_base_delay_ms > submission.retry_max_delay_ms {
+            errors.push(format!(
+                "submission.retry_base_delay_ms ({}) must not exceed submission.retry_max_delay_ms ({})",
+                submission.retry_base_delay_ms, submission.retry_max_delay_ms
+            ));
+        }
+        if submission.retry_min_jitter_ms > submission.retry_max_jitter_ms {
+            errors.push(format!(
+                "submission.retry_min_jitter_ms ({}) must not exceed submission.retry_max_jitter_ms ({})",
+                submission.retry_min_jitter_ms, submission.retry_max_jitter_ms
+            ));
+        }
+        if submission.submission_timeout_ms == 0 {
+            errors.push("submission.submission_timeout_ms must be greater than 0".to_string());
+        }
+        if !(0.0..=1.0).contains(&submission.verify_sample_fraction) {
+            errors.push(format!(
+                "submission.verify_sample_fraction ({}) must be between 0 and 1",
+                submission.verify_sample_fraction
+            ));
+        }
+        if submission.block_poll_interval_ms == 0 {
+            errors.push("submission.block_poll_interval_ms must be greater than 0".to_string());
+        }
+        if self.scheduler.concurrency == 0 {
+            errors.push("scheduler.concurrency must be at least 1".to_string());
+        }
+        if self.scheduler.poll_interval_ms == 0 {
+            errors.push("scheduler.poll_interval_ms must be greater than 0".to_string());
+        }
+        if self.scheduler.queue_capacity == 0 {
+            errors.push("scheduler.queue_capacity must be at least 1".to_string());
+        }
+        if self.scheduler.max_batch_size == 0 {
+            errors.push("scheduler.max_batch_size must be at least 1".to_string());
+        }
+        if self.selection.strategy == ChallengeSelectionStrategy::TopNByFee(0) {
+            errors.push("selection.strategy top_n_by_fee must select at least 1".to_string());
+        }
+        if self.metrics.enabled && self.metrics.port == 0 {
+            errors.push("metrics.port must be set when metrics.enabled is true".to_string());
+        }
+        if !cfg!(feature = "zstd")
+            && self.storage.solutions_data_compression.algorithm == CompressionAlgorithm::Zstd
+        {
+            errors.push(
+                "storage.solutions_data_compression uses Zstd but the benchmarker was built without feature `zstd`"
+                    .to_string(),
+            );
+        }
+        if let Some(path) = &self.workers.binary {
+            if !path.is_file() {
+                errors.push(format!(
+                    "workers.binary '{}' does not exist",
+                    path.display()
+                ));
+            }
+        }
+        let resources = &self.resources;
+        if resources.enabled && resources.poll_interval_ms == 0 {
+            errors.push("resources.poll_interval_ms must be greater than 0".to_string());
+        }
+        if !(0.0..=100.0).contains(&resources.max_cpu_percent) {
+            errors.push(format!(
+                "resources.max_cpu_percent ({}) must be between 0 and 100",
+                resources.max_cpu_percent
+            ));
+        }
+        if self.storage.checkpoint_dir.is_some() && self.storage.checkpoint_interval == 0 {
+            errors.push("storage.checkpoint_interval must be at least 1".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(BenchmarkerError::InvalidConfig { errors })
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// sqlite file recording the outcome of every submitted benchmark. standalone only.
+    /// nothing is recorded when unset
+    pub path: Option<std::path::PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// serve prometheus metrics at /metrics
+    pub enabled: bool,
+    pub port: u16,
+    /// submissions whose phase timings are kept for `tig-benchmarker timings`
+    pub timing_log_len: usize,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9100,
+            timing_log_len: 100,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ResourcesConfig {
+    /// stop starting submissions while the machine is short of memory or cpu, e.g. because
+    /// algorithm workers run on it too. standalone only
+    pub enabled: bool,
+    /// how often free memory and cpu usage are sampled
+    pub poll_interval_ms: u64,
+    /// submissions are paused while less memory than this is available
+    pub min_free_memory_mb: u64,
+    /// submissions are paused while cpu usage across all cores is above this, from 0 to 100
+    pub max_cpu_percent: f32,
+}
+
+impl Default for ResourcesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_interval_ms: 1000,
+            min_free_memory_mb: 512,
+            max_cpu_percent: 95.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct WorkersConfig {
+    /// path to a tig-worker binary. when set, each nonce is computed in a subprocess running it
+    /// instead of in-process, so a crashing algorithm cannot take the benchmarker down with it.
+    /// standalone only
+    pub binary: Option<std::path::PathBuf>,
+    /// a worker whose resident memory exceeds this is killed and restarted. linux only
+    pub max_memory_mb: Option<u64>,
+    /// restarts per nonce before it is given up on
+    pub max_restarts: u32,
+}
+
+impl Default for WorkersConfig {
+    fn default() -> Self {
+        Self {
+            binary: None,
+            max_memory_mb: None,
+            max_restarts: 3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SelectionConfig {
+    /// `"all_challenges"` or `{ top_n_by_fee = n }`. defaults to all challenges in the
+    /// algorithm selection
+    pub strategy: ChallengeSelectionStrategy,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// how solutions data is held in memory and in the snapshot until its proof is submitted.
+    /// off by default
+    pub solutions_data_compression: CompressionConfig,
+    /// cap on benchmarks and proofs held in memory. the least recently used ones that were
+    /// already submitted are evicted first. 0 means no cap
+    pub max_entries: usize,
+    /// directory for `<benchmark_id>.ckpt` files of benchmarks still generating solutions.
+    /// a restart resumes from them. nothing is checkpointed when unset
+    pub checkpoint_dir: Option<std::path::PathBuf>,
+    /// solutions generated between checkpoints
+    pub checkpoint_interval: u32,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            solutions_data_compression: CompressionConfig::default(),
+            max_entries: 0,
+            checkpoint_dir: None,
+            checkpoint_interval: 1000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// submissions in flight at the same time
+    pub concurrency: usize,
+    /// how often the job runner checks for newly queued submissions
+    pub poll_interval_ms: u32,
+    /// how long a shutdown waits for in-flight submissions before saving them as pending
+    pub drain_timeout_ms: u64,
+    /// submissions waiting to be started before newly computed ones apply `overflow_policy`
+    pub queue_capacity: usize,
+    /// `Block` pauses benchmarking until there is room. `DropOldest` discards the oldest
+    /// queued benchmark. proofs are never dropped
+    pub overflow_policy: OverflowPolicy,
+    /// benchmarks that finished at the same block height are submitted together, up to this
+    /// many per request. 1 submits each on its own
+    pub max_batch_size: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            poll_interval_ms: 200,
+            drain_timeout_ms: 30000,
+            queue_capacity: 32,
+            overflow_policy: OverflowPolicy::Block,
+            max_batch_size: 8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SubmissionConfig {
+    /// attempts per submission, including the first
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    pub retry_min_jitter_ms: u64,
+    pub retry_max_jitter_ms: u64,
+    pub fraud_is_fatal: bool,
+    /// how often the block height watcher polls for the latest block
+    pub block_poll_interval_ms: u64,
+    /// how old the watcher's polled height may be before callers query the api directly. 0
+    /// queries every time
+    pub block_stale_timeout_ms: u64,
+    /// how long the latest block queried on a stale height is reused for. 0 queries every time
+    pub block_cache_ttl_ms: u64,
+    /// how often to check for a new block after the api reports high transaction volume
+    pub high_volume_poll_interval_ms: u32,
+    /// build and verify benchmark submissions but never send them
+    pub dry_run: bool,
+    /// an attempt that gets no response within this time is abandoned and retried
+    pub submission_timeout_ms: u64,
+    /// solutions_meta_data entries per multipart part. 0 sends the benchmark in a single body,
+    /// as do servers without multipart support
+    pub chunk_size: usize,
+    /// share of a benchmark's solutions regenerated and checked against its
+    /// solutions_meta_data before it is submitted, from 0 (off) to 1
+    pub verify_sample_fraction: f64,
+    /// seeds which solutions are sampled, so a failing sample can be reproduced
+    pub verify_sample_seed: u64,
+    /// order of solutions_meta_data in a submitted benchmark: `by_hash`, `by_index` or
+    /// `as_generated`
+    pub sort_strategy: SortStrategy,
+    /// which solution's data is sent with a benchmark: `first` or `last` once sorted,
+    /// `highest_score` (least fuel consumed) or `{ random = <seed> }`
+    pub solution_selector: SolutionSelector,
+    /// file holding a hex encoded ed25519 secret key. benchmarks are submitted signed with it
+    /// unless their job carries a signer of its own
+    pub signing_key_path: Option<std::path::PathBuf>,
+}
+
+impl Default for SubmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            retry_base_delay_ms: 2000,
+            retry_max_delay_ms: 30000,
+            retry_min_jitter_ms: 0,
+            retry_max_jitter_ms: 1000,
+            fraud_is_fatal: true,
+            block_poll_interval_ms: 5000,
+            block_stale_timeout_ms: 30000,
+            block_cache_ttl_ms: 1000,
+            high_volume_poll_interval_ms: 5000,
+            dry_run: false,
+            submission_timeout_ms: 30000,
+            chunk_size: 0,
+            verify_sample_fraction: 0.0,
+            verify_sample_seed: 0,
+            sort_strategy: SortStrategy::ByHash,
+            solution_selector: SolutionSelector::First,
+            signing_key_path: None,
+        }
+    }
+}
+
+impl SubmissionConfig {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            base_delay_ms: self.retry_base_delay_ms,
+            max_delay_ms: self.retry_max_delay_ms,
+            min_jitter_ms: self.retry_min_jitter_ms,
+            max_jitter_ms: self.retry_max_jitter_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BenchmarkerConfig {
+        BenchmarkerConfig {
+            api: ApiConfig {
+                endpoints: vec!["https://mainnet-api.tig.foundation".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        assert!(config().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_every_error() {
+        let mut config = config();
+        config.api.endpoints = vec!["mainnet-api.tig.foundation".to_string()];
+        config.submission.max_retries = 0;
+        config.scheduler.concurrency = 0;
+        match config.validate() {
+            Err(BenchmarkerError::InvalidConfig { errors }) => assert_eq!(errors.len(), 3),
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_api_client_settings() {
+        let mut config = config();
+        config.api.tls_ca_bundle = Some("/nonexistent/ca.pem".into());
+        config.api.request_timeout_ms = Some(0);
+        config.api.connect_timeout_ms = Some(5000);
+        match config.validate() {
+            Err(BenchmarkerError::InvalidConfig { errors }) => assert_eq!(errors.len(), 2),
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+    }
+}
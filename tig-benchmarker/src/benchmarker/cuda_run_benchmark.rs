@@ -9,6 +9,7 @@ use std::sync::Arc;
 use tig_algorithms::{c001, c002, c003, c004, CudaKernel};
 use tig_challenges::ChallengeTrait;
 use tig_worker::{compute_solution, verify_solution, SolutionData};
+use tracing::{info, warn};
 
 static PTX_CACHE: OnceCell<Mutex<HashMap<String, Ptx>>> = OnceCell::new();
 
@@ -18,7 +19,7 @@ pub async fn get_or_compile_cuda(
     dev: &Arc<CudaDevice>,
 ) -> HashMap<&'static str, CudaFunction> {
     if kernel.is_none() {
-        println!("No CUDA optimisations available for '{}'", key);
+        warn!("No CUDA optimisations available for '{}'", key);
         return HashMap::new();
     }
     let kernel = kernel.as_ref().unwrap();
@@ -26,14 +27,14 @@ pub async fn get_or_compile_cuda(
     let mut ptx_cache = ptx_cache.lock().await;
     if !ptx_cache.contains_key(key) {
         let start = std::time::Instant::now();
-        println!("Compiling CUDA kernels for '{}'", key);
+        info!("Compiling CUDA kernels for '{}'", key);
         ptx_cache.insert(
             key.clone(),
             compile_ptx(kernel.src)
                 .map_err(|e| format!("Cuda Kernel failed to compile\nError: {}", e))
                 .unwrap_or_else(|err_msg| panic!("{}", err_msg)),
         );
-        println!(
+        info!(
             "CUDA kernels for '{}' compiled in {}ms",
             key,
             start.elapsed().as_millis()
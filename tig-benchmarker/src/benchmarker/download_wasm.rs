@@ -1,4 +1,4 @@
-use super::{Job, Result};
+use super::{BenchmarkerError, Job, Result};
 use crate::future_utils::Mutex;
 use once_cell::sync::OnceCell;
 use std::collections::HashMap;
@@ -16,7 +16,10 @@ pub async fn execute(job: &Job) -> Result<Vec<u8>> {
     } else {
         let wasm = get::<Vec<u8>>(&job.download_url, None)
             .await
-            .map_err(|e| format!("Failed to download wasm from {}: {:?}", job.download_url, e))?;
+            .map_err(|error| BenchmarkerError::DownloadFailed {
+                url: job.download_url.clone(),
+                error,
+            })?;
         (*cache).insert(job.settings.algorithm_id.clone(), wasm.clone());
         Ok(wasm)
     }
@@ -0,0 +1,296 @@
+use super::{verify::VerificationError, BenchmarkId};
+use rand::distributions::WeightedError;
+use tig_api::Uuid;
+
+#[derive(Debug)]
+pub enum BenchmarkerError {
+    ApiError {
+        error: tig_api::Error,
+        trace_id: Option<Uuid>,
+    },
+    BlockHeightStale {
+        block_started: u32,
+        current_height: u32,
+    },
+    BlockReorg {
+        captured_height: u32,
+        current_height: u32,
+    },
+    Cancelled,
+    CheckpointIo(std::io::Error),
+    CheckpointSerde(serde_json::Error),
+    ConfigLoadFailed {
+        path: std::path::PathBuf,
+        error: String,
+    },
+    DownloadFailed {
+        url: String,
+        error: anyhow::Error,
+    },
+    EmptyAlgorithmSelection,
+    FraudDetected(String),
+    HistoryStoreFailed {
+        error: String,
+    },
+    InvalidAlgorithmSelection {
+        algorithm_name: String,
+    },
+    InvalidChallengeSelection {
+        challenge_name: String,
+    },
+    InvalidChallengeWeights(WeightedError),
+    InvalidConfig {
+        errors: Vec<String>,
+    },
+    InvalidSubmission(tig_api::BuilderError),
+    JoinFailed(String),
+    LocalVerificationFailed(VerificationError),
+    MaxRetriesExceeded {
+        attempts: u32,
+        last_error: tig_api::Error,
+        trace_id: Option<Uuid>,
+    },
+    MissingData {
+        what: &'static str,
+    },
+    MissingDownloadUrl {
+        algorithm_id: String,
+    },
+    MissingSampledNonces {
+        benchmark_id: BenchmarkId,
+    },
+    MissingSolutionsForSampledNonces {
+        nonces: Vec<u64>,
+    },
+    NoChallengesSelected,
+    PayloadTooLarge {
+        num_solutions: usize,
+    },
+    ProtocolVersionMismatch {
+        server: u16,
+        client: u16,
+    },
+    RecomputeFailed {
+        benchmark_id: BenchmarkId,
+    },
+    ShuttingDown,
+    SnapshotIo(std::io::Error),
+    SnapshotSerde(serde_json::Error),
+    SnapshotVersionMismatch {
+        found: u32,
+        expected: u32,
+    },
+    StateNotFound {
+        what: &'static str,
+        id: String,
+    },
+    SubmissionAborted {
+        error: tig_api::Error,
+        trace_id: Option<Uuid>,
+    },
+    SubmissionTimeout {
+        attempts: u32,
+        timeout_ms: u64,
+    },
+    WorkerIo(std::io::Error),
+}
+
+impl std::fmt::Display for BenchmarkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BenchmarkerError::ApiError { error, trace_id } => {
+                write!(f, "API error{}: {:?}", trace_id_suffix(trace_id), error)
+            }
+            BenchmarkerError::BlockHeightStale {
+                block_started,
+                current_height,
+            } => write!(
+                f,
+                "Benchmark started at block '{}' is stale at current height '{}'",
+                block_started, current_height
+            ),
+            BenchmarkerError::BlockReorg {
+                captured_height,
+                current_height,
+            } => write!(
+                f,
+                "Block height rolled back from '{}' to '{}' during submission",
+                captured_height, current_height
+            ),
+            BenchmarkerError::Cancelled => write!(f, "Cancelled"),
+            BenchmarkerError::CheckpointIo(e) => write!(f, "Checkpoint I/O error: {}", e),
+            BenchmarkerError::CheckpointSerde(e) => {
+                write!(f, "Failed to (de)serialize checkpoint: {}", e)
+            }
+            BenchmarkerError::ConfigLoadFailed { path, error } => {
+                write!(f, "Failed to load config from {}: {}", path.display(), error)
+            }
+            BenchmarkerError::DownloadFailed { url, error } => {
+                write!(f, "Failed to download wasm from {}: {:?}", url, error)
+            }
+            BenchmarkerError::EmptyAlgorithmSelection => {
+                write!(f, "Your <algorithm_selection>.json is empty")
+            }
+            BenchmarkerError::FraudDetected(reason) => {
+                write!(f, "Flagged as fraud: {}", reason)
+            }
+            BenchmarkerError::HistoryStoreFailed { error } => {
+                write!(f, "History store error: {}", error)
+            }
+            BenchmarkerError::InvalidAlgorithmSelection { algorithm_name } => write!(
+                f,
+                "Your <algorithm_selection>.json contains a non-existent algorithm '{}'",
+                algorithm_name
+            ),
+            BenchmarkerError::InvalidChallengeSelection { challenge_name } => write!(
+                f,
+                "Your <algorithm_selection>.json contains a non-existent challenge '{}'",
+                challenge_name
+            ),
+            BenchmarkerError::InvalidChallengeWeights(e) => {
+                write!(f, "Failed to create WeightedIndex: {}", e)
+            }
+            BenchmarkerError::InvalidConfig { errors } => {
+                write!(f, "Invalid config:\n  {}", errors.join("\n  "))
+            }
+            BenchmarkerError::InvalidSubmission(e) => {
+                write!(f, "Failed to build submission: {}", e)
+            }
+            BenchmarkerError::JoinFailed(e) => write!(f, "Failed to join futures: {}", e),
+            BenchmarkerError::LocalVerificationFailed(e) => {
+                write!(f, "Local proof verification failed: {}", e)
+            }
+            BenchmarkerError::MaxRetriesExceeded {
+                attempts,
+                last_error,
+                trace_id,
+            } => write!(
+                f,
+                "Failed to submit after {} attempts{}: {:?}",
+                attempts,
+                trace_id_suffix(trace_id),
+                last_error
+            ),
+            BenchmarkerError::MissingData { what } => {
+                write!(f, "Expecting {} to exist", what)
+            }
+            BenchmarkerError::MissingDownloadUrl { algorithm_id } => {
+                write!(f, "Algorithm {} does not have wasm download_url", algorithm_id)
+            }
+            BenchmarkerError::MissingSampledNonces { benchmark_id } => write!(
+                f,
+                "Expecting benchmark '{}' to have sampled_nonces",
+                benchmark_id
+            ),
+            BenchmarkerError::MissingSolutionsForSampledNonces { nonces } => {
+                write!(f, "No solutions for sampled nonces: '{:?}'", nonces)
+            }
+            BenchmarkerError::NoChallengesSelected => {
+                write!(f, "Challenge selector did not select any challenges")
+            }
+            BenchmarkerError::PayloadTooLarge { num_solutions } => write!(
+                f,
+                "Submission is too large for the server even with solutions_meta_data truncated to {} entries",
+                num_solutions
+            ),
+            BenchmarkerError::ProtocolVersionMismatch { server, client } => write!(
+                f,
+                "Server speaks protocol version '{}' but this benchmarker speaks '{}'. Please upgrade",
+                server, client
+            ),
+            BenchmarkerError::RecomputeFailed { benchmark_id } => {
+                write!(f, "Failed to recompute solutions for {}", benchmark_id)
+            }
+            BenchmarkerError::ShuttingDown => write!(f, "Cancelled due to shutdown"),
+            BenchmarkerError::SnapshotIo(e) => write!(f, "Snapshot I/O error: {}", e),
+            BenchmarkerError::SnapshotSerde(e) => {
+                write!(f, "Failed to (de)serialize snapshot: {}", e)
+            }
+            BenchmarkerError::SnapshotVersionMismatch { found, expected } => write!(
+                f,
+                "Snapshot schema version '{}' is not supported. Expected: '{}'",
+                found, expected
+            ),
+            BenchmarkerError::StateNotFound { what, id } => {
+                write!(f, "Expecting {} '{}' to exist in state", what, id)
+            }
+            BenchmarkerError::SubmissionAborted { error, trace_id } => write!(
+                f,
+                "Submission aborted{}: {:?}",
+                trace_id_suffix(trace_id),
+                error
+            ),
+            BenchmarkerError::SubmissionTimeout {
+                attempts,
+                timeout_ms,
+            } => write!(
+                f,
+                "Submission timed out after {} attempts of {} ms",
+                attempts, timeout_ms
+            ),
+            BenchmarkerError::WorkerIo(e) => write!(f, "Worker I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BenchmarkerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BenchmarkerError::ApiError { error: e, .. }
+            | BenchmarkerError::MaxRetriesExceeded { last_error: e, .. }
+            | BenchmarkerError::SubmissionAborted { error: e, .. }
+            | BenchmarkerError::DownloadFailed { error: e, .. } => Some(e.as_ref()),
+            BenchmarkerError::CheckpointIo(e) => Some(e),
+            BenchmarkerError::CheckpointSerde(e) => Some(e),
+            BenchmarkerError::InvalidChallengeWeights(e) => Some(e),
+            BenchmarkerError::InvalidSubmission(e) => Some(e),
+            BenchmarkerError::LocalVerificationFailed(e) => Some(e),
+            BenchmarkerError::SnapshotIo(e) => Some(e),
+            BenchmarkerError::SnapshotSerde(e) => Some(e),
+            BenchmarkerError::WorkerIo(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl BenchmarkerError {
+    // keeps the trace id of the failed request, so it can be looked up in the server's logs
+    pub fn api(error: tig_api::Error) -> Self {
+        BenchmarkerError::ApiError {
+            trace_id: tig_api::error_trace_id(&error),
+            error,
+        }
+    }
+
+    // a request the server rejected in a way retrying cannot fix
+    pub fn aborted(error: tig_api::Error) -> Self {
+        BenchmarkerError::SubmissionAborted {
+            trace_id: tig_api::error_trace_id(&error),
+            error,
+        }
+    }
+
+    pub fn trace_id(&self) -> Option<Uuid> {
+        match self {
+            BenchmarkerError::ApiError { trace_id, .. }
+            | BenchmarkerError::MaxRetriesExceeded { trace_id, .. }
+            | BenchmarkerError::SubmissionAborted { trace_id, .. } => *trace_id,
+            _ => None,
+        }
+    }
+}
+
+impl From<tig_api::Error> for BenchmarkerError {
+    fn from(error: tig_api::Error) -> Self {
+        BenchmarkerError::api(error)
+    }
+}
+
+fn trace_id_suffix(trace_id: &Option<Uuid>) -> String {
+    match trace_id {
+        Some(trace_id) => format!(" (trace id '{}')", trace_id),
+        None => String::new(),
+    }
+}
+
+pub type Result<T> = std::result::Result<T, BenchmarkerError>;
@@ -0,0 +1,79 @@
+use super::{BenchmarkerError, Job, QueryDataDiff};
+use std::sync::Arc;
+#[cfg(feature = "standalone")]
+use {once_cell::sync::OnceCell, tokio::sync::broadcast};
+
+// subscribers that fall this far behind miss the oldest events, see `RecvError::Lagged`
+#[cfg(feature = "standalone")]
+pub const EVENT_CAPACITY: usize = 256;
+
+#[cfg(feature = "standalone")]
+static EVENTS: OnceCell<broadcast::Sender<BenchmarkerEvent>> = OnceCell::new();
+
+// lifecycle events for dashboards and notifiers. the error is shared as broadcast hands every
+// subscriber its own clone
+#[derive(Debug, Clone)]
+pub enum BenchmarkerEvent {
+    JobStarted {
+        job: Job,
+    },
+    SubmissionSucceeded {
+        benchmark_id: String,
+        reward: Option<u64>,
+    },
+    SubmissionFailed {
+        benchmark_id: String,
+        error: Arc<BenchmarkerError>,
+    },
+    BlockAdvanced {
+        height: u64,
+    },
+    StateDiff(QueryDataDiff),
+}
+
+#[cfg(feature = "standalone")]
+fn sender() -> &'static broadcast::Sender<BenchmarkerEvent> {
+    EVENTS.get_or_init(|| broadcast::channel(EVENT_CAPACITY).0)
+}
+
+// receives every event emitted after this call
+#[cfg(feature = "standalone")]
+pub fn subscribe_events() -> broadcast::Receiver<BenchmarkerEvent> {
+    sender().subscribe()
+}
+
+// does nothing without subscribers, or in the browser
+pub fn emit(event: BenchmarkerEvent) {
+    #[cfg(feature = "standalone")]
+    let _ = sender().send(event);
+    #[cfg(not(feature = "standalone"))]
+    let _ = event;
+}
+
+#[cfg(all(test, feature = "standalone"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_receive_events() {
+        emit(BenchmarkerEvent::BlockAdvanced { height: 1 });
+        let mut first = subscribe_events();
+        let mut second = subscribe_events();
+        emit(BenchmarkerEvent::SubmissionFailed {
+            benchmark_id: "abc".to_string(),
+            error: Arc::new(BenchmarkerError::Cancelled),
+        });
+        for receiver in [&mut first, &mut second] {
+            match receiver.recv().await.unwrap() {
+                BenchmarkerEvent::SubmissionFailed {
+                    benchmark_id,
+                    error,
+                } => {
+                    assert_eq!(benchmark_id, "abc");
+                    assert!(matches!(*error, BenchmarkerError::Cancelled));
+                }
+                other => panic!("expected SubmissionFailed, got {:?}", other),
+            }
+        }
+    }
+}
@@ -1,34 +1,39 @@
-use super::{state, QueryData, Result};
+use super::{state, BenchmarkId, BenchmarkerError, QueryData, Result};
 use std::collections::HashSet;
 use tig_worker::SolutionData;
 
-pub async fn execute() -> Result<Option<(String, Vec<SolutionData>)>> {
+pub async fn execute() -> Result<Option<(BenchmarkId, Vec<SolutionData>)>> {
+    let query_data = &mut state().lock().await.query_data;
     let QueryData {
-        proofs,
+        solutions_data,
         benchmarks,
         frauds,
         ..
-    } = &mut state().lock().await.query_data;
-    for (benchmark_id, proof) in proofs.iter_mut() {
-        if proof.solutions_data.is_none() || frauds.contains_key(benchmark_id) {
-            continue;
-        }
-        if let Some(state) = &benchmarks[benchmark_id].state {
-            let sampled_nonces: HashSet<u64> =
-                state.sampled_nonces.clone().unwrap().into_iter().collect();
-            let mut solutions_data = proof.solutions_data.take().unwrap();
-            solutions_data.retain(|x| sampled_nonces.contains(&x.nonce));
-            let extracted_nonces: HashSet<u64> = solutions_data.iter().map(|x| x.nonce).collect();
-            if extracted_nonces != sampled_nonces {
-                return Err(format!(
-                    "No solutions for sampled nonces: '{:?}'",
-                    sampled_nonces
-                        .difference(&extracted_nonces)
-                        .collect::<Vec<_>>()
-                ));
-            }
-            return Ok(Some((benchmark_id.clone(), solutions_data)));
-        }
+    } = &mut *query_data;
+    let benchmark_id = solutions_data.keys().find(|benchmark_id| {
+        !frauds.contains_key(*benchmark_id) && benchmarks[*benchmark_id].state.is_some()
+    });
+    let Some(benchmark_id) = benchmark_id.cloned() else {
+        return Ok(None);
+    };
+    let sampled_nonces: HashSet<u64> = benchmarks[&benchmark_id]
+        .state()
+        .sampled_nonces
+        .clone()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let mut data = solutions_data.remove(&benchmark_id).unwrap();
+    data.retain_nonces(&sampled_nonces);
+    let extracted_nonces: HashSet<u64> = data.nonces().collect();
+    if extracted_nonces != sampled_nonces {
+        return Err(BenchmarkerError::MissingSolutionsForSampledNonces {
+            nonces: sampled_nonces
+                .difference(&extracted_nonces)
+                .cloned()
+                .collect(),
+        });
     }
-    Ok(None)
+    query_data.touch(&benchmark_id);
+    Ok(Some((benchmark_id, data.into_vec())))
 }
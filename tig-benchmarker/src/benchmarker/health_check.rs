@@ -0,0 +1,18 @@
+use super::{api, config, shutdown::shutdown_signal};
+use crate::future_utils::{sleep, spawn};
+use once_cell::sync::OnceCell;
+
+// re-probes unhealthy endpoints in the background every `api.health_check_interval_ms`, so a
+// recovered endpoint is back in rotation before a request has to fall through to it
+pub fn start() {
+    static STARTED: OnceCell<()> = OnceCell::new();
+    let interval_ms = config().api.health_check_interval_ms;
+    if interval_ms > 0 && STARTED.set(()).is_ok() {
+        spawn(async move {
+            while !shutdown_signal().is_triggered() {
+                sleep(interval_ms.min(u32::MAX as u64) as u32).await;
+                api().probe_endpoints().await;
+            }
+        });
+    }
+}
@@ -0,0 +1,191 @@
+use super::{BenchmarkerError, Result};
+use rusqlite::{params, Connection};
+use std::{path::Path, sync::Mutex};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub benchmark_id: String,
+    /// unix time in milliseconds
+    pub submitted_at: u64,
+    pub block_height: u32,
+    pub verified: bool,
+    pub fraud_reason: Option<String>,
+    /// not known at submission time. left for whoever later learns it
+    pub reward: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistorySummary {
+    pub num_entries: u64,
+    pub num_verified: u64,
+    pub total_reward: u64,
+}
+
+impl HistorySummary {
+    pub fn success_rate(&self) -> f64 {
+        if self.num_entries == 0 {
+            0.0
+        } else {
+            self.num_verified as f64 / self.num_entries as f64
+        }
+    }
+}
+
+// one row per submitted benchmark. a resubmission replaces the earlier row
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+}
+
+fn history_error(e: rusqlite::Error) -> BenchmarkerError {
+    BenchmarkerError::HistoryStoreFailed {
+        error: e.to_string(),
+    }
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::init(Connection::open(path).map_err(history_error)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(history_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS history (
+                benchmark_id TEXT PRIMARY KEY,
+                submitted_at INTEGER NOT NULL,
+                block_height INTEGER NOT NULL,
+                verified INTEGER NOT NULL,
+                fraud_reason TEXT,
+                reward INTEGER
+            )",
+            [],
+        )
+        .map_err(history_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn record(&self, entry: &HistoryEntry) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO history
+                    (benchmark_id, submitted_at, block_height, verified, fraud_reason, reward)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    entry.benchmark_id,
+                    entry.submitted_at as i64,
+                    entry.block_height,
+                    entry.verified,
+                    entry.fraud_reason,
+                    entry.reward.map(|r| r as i64),
+                ],
+            )
+            .map_err(history_error)?;
+        Ok(())
+    }
+
+    // most recent first
+    pub fn last(&self, n: usize) -> Result<Vec<HistoryEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT benchmark_id, submitted_at, block_height, verified, fraud_reason, reward
+                    FROM history ORDER BY submitted_at DESC LIMIT ?1",
+            )
+            .map_err(history_error)?;
+        let entries = stmt
+            .query_map(params![n as i64], |row| {
+                Ok(HistoryEntry {
+                    benchmark_id: row.get(0)?,
+                    submitted_at: row.get::<_, i64>(1)? as u64,
+                    block_height: row.get(2)?,
+                    verified: row.get(3)?,
+                    fraud_reason: row.get(4)?,
+                    reward: row.get::<_, Option<i64>>(5)?.map(|r| r as u64),
+                })
+            })
+            .map_err(history_error)?
+            .collect::<rusqlite::Result<Vec<HistoryEntry>>>()
+            .map_err(history_error)?;
+        Ok(entries)
+    }
+
+    pub fn summary(&self) -> Result<HistorySummary> {
+        let conn = self.conn.lock().unwrap();
+        let (num_entries, num_verified, total_reward) = conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(verified), 0), COALESCE(SUM(reward), 0) FROM history",
+                [],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)),
+            )
+            .map_err(history_error)?;
+        Ok(HistorySummary {
+            num_entries: num_entries as u64,
+            num_verified: num_verified as u64,
+            total_reward: total_reward as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(benchmark_id: &str, submitted_at: u64, fraud_reason: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            benchmark_id: benchmark_id.to_string(),
+            submitted_at,
+            block_height: 100,
+            verified: fraud_reason.is_none(),
+            fraud_reason: fraud_reason.map(|r| r.to_string()),
+            reward: None,
+        }
+    }
+
+    #[test]
+    fn test_last_is_most_recent_first() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        store.record(&entry("a", 1, None)).unwrap();
+        store
+            .record(&entry("b", 3, Some("invalid solution")))
+            .unwrap();
+        store.record(&entry("c", 2, None)).unwrap();
+        let ids: Vec<String> = store
+            .last(2)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.benchmark_id)
+            .collect();
+        assert_eq!(ids, vec!["b", "c"]);
+        assert_eq!(
+            store.last(1).unwrap()[0].fraud_reason.as_deref(),
+            Some("invalid solution")
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        assert_eq!(store.summary().unwrap().success_rate(), 0.0);
+        store.record(&entry("a", 1, None)).unwrap();
+        store
+            .record(&entry("b", 2, Some("invalid solution")))
+            .unwrap();
+        store
+            .record(&HistoryEntry {
+                reward: Some(250),
+                ..entry("a", 1, None)
+            })
+            .unwrap();
+        let summary = store.summary().unwrap();
+        assert_eq!(summary.num_entries, 2);
+        assert_eq!(summary.total_reward, 250);
+        assert_eq!(summary.success_rate(), 0.5);
+    }
+}
@@ -0,0 +1,762 @@
+use super::{
+    block_height_watcher::check_block_height,
+    config, events, evict_completed, setup_job,
+    shutdown::{shutdown_signal, CancellationToken},
+    state, submission_config, submit_benchmark, submit_benchmarks_batch, submit_proof,
+    update_status, BenchmarkId, BenchmarkerError, BenchmarkerEvent, Job, QueryData, Result, State,
+    SubmissionOutcome,
+};
+use crate::future_utils::{sleep, spawn, time, BoxFuture};
+use futures::{
+    future::select,
+    stream::{FuturesUnordered, StreamExt},
+};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use tig_structs::core::SolutionMetaData;
+use tig_worker::SolutionData;
+use tracing::warn;
+
+static STARTED: OnceCell<()> = OnceCell::new();
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static STARTED_SUBMISSIONS: OnceCell<Mutex<HashMap<BenchmarkId, StartedSubmission>>> =
+    OnceCell::new();
+
+// a submission from when it starts until it finishes
+struct StartedSubmission {
+    token: CancellationToken,
+    submission: Submission,
+}
+
+fn started_submissions() -> &'static Mutex<HashMap<BenchmarkId, StartedSubmission>> {
+    STARTED_SUBMISSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// the latest block height when each queued benchmark finished computing. benchmarks restored
+// from a snapshot have none and are never batched
+static FINISHED_AT: OnceCell<Mutex<HashMap<BenchmarkId, u32>>> = OnceCell::new();
+
+fn finished_at() -> &'static Mutex<HashMap<BenchmarkId, u32>> {
+    FINISHED_AT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// ids of the submissions that have started and not yet finished
+pub fn in_flight_ids() -> Vec<BenchmarkId> {
+    started_submissions()
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect()
+}
+
+// cancels the in-flight submission for `benchmark_id` at its next retry. returns false if there
+// is none, e.g. because it has not started yet
+pub fn cancel_job(benchmark_id: &str) -> bool {
+    match started_submissions().lock().unwrap().get(benchmark_id) {
+        Some(started) => {
+            started.token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+// puts the submissions still in flight back in the queue, for a shutdown that cannot wait for
+// them to finish. the solutions_meta_data a benchmark submission took out of the state is only
+// returned when it finishes, so it is rebuilt from the benchmark's solutions data
+pub fn requeue_in_flight(state: &mut State) -> usize {
+    let in_flight: Vec<Submission> = started_submissions()
+        .lock()
+        .unwrap()
+        .values()
+        .map(|s| s.submission.clone())
+        .collect();
+    for submission in in_flight.iter() {
+        if let Submission::Benchmark(job) = submission {
+            let query_data = &mut state.query_data;
+            let taken = query_data
+                .benchmarks
+                .get(&job.benchmark_id)
+                .is_some_and(|b| b.solutions_meta_data.is_none());
+            let Some(solutions_data) = query_data.solutions_data.get(&job.benchmark_id) else {
+                continue;
+            };
+            if taken {
+                let solutions_meta_data =
+                    solutions_data.iter().map(SolutionMetaData::from).collect();
+                submit_benchmark::restore_submission(query_data, job, solutions_meta_data);
+            }
+        }
+    }
+    let num_requeued = in_flight.len();
+    for submission in in_flight {
+        push(state, submission);
+    }
+    num_requeued
+}
+
+// cancels every in-flight benchmark submission whose window has closed at `height`, as each
+// further attempt would only be rejected as out of window
+pub async fn cancel_expired(height: u32) {
+    let state = state().lock().await;
+    let lifespan_period = state
+        .query_data
+        .latest_block
+        .config()
+        .benchmark_submissions
+        .lifespan_period;
+    for (benchmark_id, started) in started_submissions().lock().unwrap().iter() {
+        // a proof can be submitted after its benchmark's window has closed
+        if !matches!(started.submission, Submission::Benchmark(_)) {
+            continue;
+        }
+        let Some(benchmark) = state.query_data.benchmarks.get(benchmark_id) else {
+            continue;
+        };
+        let block_started = benchmark.details.block_started;
+        if let Err(e) = check_block_height(height, height, block_started, lifespan_period) {
+            warn!("Cancelling submission of {}: {}", benchmark_id, e);
+            started.token.cancel();
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Submission {
+    Benchmark(Job),
+    Proof {
+        benchmark_id: BenchmarkId,
+        solutions_data: Vec<SolutionData>,
+    },
+}
+
+impl Submission {
+    pub fn benchmark_id(&self) -> &BenchmarkId {
+        match self {
+            Submission::Benchmark(job) => &job.benchmark_id,
+            Submission::Proof { benchmark_id, .. } => benchmark_id,
+        }
+    }
+}
+
+pub async fn queue(submission: Submission) {
+    push(&mut *state().lock().await, submission);
+}
+
+fn push(state: &mut State, submission: Submission) {
+    let State {
+        pending_submissions,
+        job_scheduler,
+        query_data,
+        ..
+    } = &mut *state;
+    match submission {
+        Submission::Benchmark(job) => job_scheduler.push(job, query_data),
+        _ => pending_submissions.push_back(submission),
+    }
+    state
+        .metrics
+        .set_pending_jobs(state.pending_submissions.len() + state.job_scheduler.len());
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    Block,
+    DropOldest,
+}
+
+// queues a newly computed submission once the queue is below `scheduler.queue_capacity`, so
+// benchmarking cannot run arbitrarily far ahead of submitting. `queue` itself is unbounded
+// as requeued and restored submissions must never be lost
+pub async fn enqueue(submission: Submission) {
+    let mut submission = submission;
+    loop {
+        let scheduler_config = &config().scheduler;
+        // nothing is started during shutdown, so waiting for room would never end
+        let capacity = if shutdown_signal().is_triggered() {
+            usize::MAX
+        } else {
+            scheduler_config.queue_capacity
+        };
+        let pushed = push_bounded(
+            &mut *state().lock().await,
+            submission,
+            capacity,
+            scheduler_config.overflow_policy,
+        );
+        match pushed {
+            Ok(Some(dropped)) => {
+                update_status(&format!(
+                    "Submission queue full. Dropped benchmark {}",
+                    dropped.benchmark_id
+                ))
+                .await;
+                return;
+            }
+            Ok(None) => return,
+            Err(full) => submission = full,
+        }
+        sleep(scheduler_config.poll_interval_ms).await;
+    }
+}
+
+// pushes `submission` if fewer than `capacity` are queued. `DropOldest` makes room by dropping
+// the oldest queued benchmark, which is returned. otherwise a full queue hands it back
+fn push_bounded(
+    state: &mut State,
+    submission: Submission,
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> std::result::Result<Option<Job>, Submission> {
+    let num_pending = state.pending_submissions.len() + state.job_scheduler.len();
+    let has_room = num_pending < capacity;
+    let dropped = match policy {
+        OverflowPolicy::DropOldest if !has_room => state.job_scheduler.pop_oldest(),
+        _ => None,
+    };
+    if !has_room && dropped.is_none() {
+        return Err(submission);
+    }
+    if let Submission::Benchmark(job) = &submission {
+        let height = state.query_data.latest_block.details.height;
+        finished_at()
+            .lock()
+            .unwrap()
+            .insert(job.benchmark_id.clone(), height);
+    }
+    if let Some(job) = &dropped {
+        finished_at().lock().unwrap().remove(&job.benchmark_id);
+        let QueryData {
+            benchmarks,
+            proofs,
+            solutions_data,
+            ..
+        } = &mut state.query_data;
+        benchmarks.remove(&job.benchmark_id);
+        proofs.remove(&job.benchmark_id);
+        solutions_data.remove(&job.benchmark_id);
+        state.metrics.record_dropped_job();
+    }
+    push(state, submission);
+    Ok(dropped)
+}
+
+// proofs go first as a late proof delays when its benchmark becomes active
+async fn next_submission() -> Option<Submission> {
+    let mut state = state().lock().await;
+    let next = match state.pending_submissions.pop_front() {
+        Some(submission) => Some(submission),
+        None => {
+            let State {
+                job_scheduler,
+                query_data,
+                ..
+            } = &mut *state;
+            job_scheduler.pop(query_data).map(Submission::Benchmark)
+        }
+    };
+    state
+        .metrics
+        .set_pending_jobs(state.pending_submissions.len() + state.job_scheduler.len());
+    next
+}
+
+// submissions that have not been started yet, in the order they would be started
+pub fn pending(state: &State) -> Vec<Submission> {
+    state
+        .pending_submissions
+        .iter()
+        .cloned()
+        .chain(
+            state
+                .job_scheduler
+                .iter()
+                .cloned()
+                .map(Submission::Benchmark),
+        )
+        .collect()
+}
+
+// waits for in-flight submissions to finish. returns false if `timeout_ms` expired first
+pub async fn drain(timeout_ms: u64) -> bool {
+    let deadline = time() + timeout_ms;
+    while IN_FLIGHT.load(Ordering::SeqCst) > 0 {
+        if time() >= deadline {
+            return false;
+        }
+        sleep(config().scheduler.poll_interval_ms).await;
+    }
+    true
+}
+
+pub fn start() {
+    if STARTED.set(()).is_ok() {
+        spawn(run());
+    }
+}
+
+// drives up to `concurrency` submissions at a time. the state lock is only taken to pop the
+// queue and to record results, never across a submission's network calls
+async fn run() {
+    let mut in_flight: FuturesUnordered<BoxFuture<()>> = FuturesUnordered::new();
+    loop {
+        let scheduler_config = &config().scheduler;
+        let concurrency = scheduler_config.concurrency.max(1);
+        while !shutdown_signal().is_triggered()
+            && in_flight.len() < concurrency
+            && permits_submission()
+        {
+            match next_submission().await {
+                Some(Submission::Benchmark(job)) => {
+                    in_flight.push(Box::pin(process_batch(take_batch(job).await)))
+                }
+                Some(submission) => in_flight.push(Box::pin(process(submission))),
+                None => break,
+            }
+        }
+        IN_FLIGHT.store(in_flight.len(), Ordering::SeqCst);
+        if in_flight.is_empty() {
+            sleep(scheduler_config.poll_interval_ms).await;
+        } else {
+            // wake up periodically so newly queued submissions can start while others are in flight
+            select(
+                in_flight.next(),
+                Box::pin(sleep(scheduler_config.poll_interval_ms)),
+            )
+            .await;
+        }
+        IN_FLIGHT.store(in_flight.len(), Ordering::SeqCst);
+    }
+}
+
+// false while `resources` thresholds are exceeded
+#[cfg(feature = "standalone")]
+fn permits_submission() -> bool {
+    super::resource_guard::permits_submission()
+}
+
+#[cfg(not(feature = "standalone"))]
+fn permits_submission() -> bool {
+    true
+}
+
+// `job` along with the queued benchmarks that finished at the same block height, up to
+// `scheduler.max_batch_size`
+async fn take_batch(job: Job) -> Vec<Job> {
+    let max_batch_size = config().scheduler.max_batch_size;
+    let height = finished_at().lock().unwrap().remove(&job.benchmark_id);
+    let mut batch = vec![job];
+    let Some(height) = height else {
+        return batch;
+    };
+    if max_batch_size <= 1 || submission_config().dry_run {
+        return batch;
+    }
+    let mut state = state().lock().await;
+    let mut finished = finished_at().lock().unwrap();
+    let same_height = state.job_scheduler.take_where(max_batch_size - 1, |j| {
+        finished.get(&j.benchmark_id) == Some(&height)
+    });
+    for job in same_height {
+        finished.remove(&job.benchmark_id);
+        batch.push(job);
+    }
+    state
+        .metrics
+        .set_pending_jobs(state.pending_submissions.len() + state.job_scheduler.len());
+    batch
+}
+
+// a single benchmark goes through `process`. each benchmark in a batch keeps its own token, so
+// cancelling one leaves the rest of the batch to be submitted
+async fn process_batch(mut jobs: Vec<Job>) {
+    if jobs.len() == 1 {
+        return process(Submission::Benchmark(jobs.remove(0))).await;
+    }
+    let members: Vec<(Job, CancellationToken)> = {
+        let mut started = started_submissions().lock().unwrap();
+        jobs.iter()
+            .map(|job| {
+                let token = CancellationToken::default();
+                started.insert(
+                    job.benchmark_id.clone(),
+                    StartedSubmission {
+                        token: token.clone(),
+                        submission: Submission::Benchmark(job.clone()),
+                    },
+                );
+                (job.clone(), token)
+            })
+            .collect()
+    };
+    let results = submit_benchmarks_batch::execute(members, submission_config()).await;
+    {
+        let mut started = started_submissions().lock().unwrap();
+        for job in jobs.iter() {
+            started.remove(&job.benchmark_id);
+        }
+    }
+    match results {
+        Ok(results) => {
+            for (job, result) in results {
+                let result = match result {
+                    Ok(outcome) => record_outcome(&job, outcome).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    record_failure(Submission::Benchmark(job), Arc::new(e)).await;
+                }
+            }
+        }
+        Err(e) => {
+            let e = Arc::new(e);
+            for job in jobs {
+                record_failure(Submission::Benchmark(job), e.clone()).await;
+            }
+        }
+    }
+}
+
+async fn process(submission: Submission) {
+    let benchmark_id = submission.benchmark_id().clone();
+    let token = CancellationToken::default();
+    started_submissions().lock().unwrap().insert(
+        benchmark_id.clone(),
+        StartedSubmission {
+            token: token.clone(),
+            submission: submission.clone(),
+        },
+    );
+    let result = match submission.clone() {
+        Submission::Benchmark(job) => submit_benchmark(job, &token).await,
+        Submission::Proof {
+            benchmark_id,
+            solutions_data,
+        } => submit_proof(benchmark_id, solutions_data, &token).await,
+    };
+    started_submissions().lock().unwrap().remove(&benchmark_id);
+    if let Err(e) = result {
+        record_failure(submission, Arc::new(e)).await;
+    }
+}
+
+// a submission that failed because of a shutdown is queued again, to be saved as pending
+async fn record_failure(submission: Submission, e: Arc<BenchmarkerError>) {
+    if let BenchmarkerError::ShuttingDown = *e {
+        return queue(submission).await;
+    }
+    let benchmark_id = submission.benchmark_id().clone();
+    update_status(&format!("Error submitting {}: {}", benchmark_id, e)).await;
+    events::emit(BenchmarkerEvent::SubmissionFailed {
+        benchmark_id: benchmark_id.to_string(),
+        error: e.clone(),
+    });
+    let mut state = state().lock().await;
+    state.submission_errors.insert(benchmark_id, e.to_string());
+}
+
+async fn submit_benchmark(job: Job, token: &CancellationToken) -> Result<()> {
+    let outcome = submit_benchmark::execute(&job, submission_config(), token).await?;
+    let benchmark_id = outcome.benchmark_id().to_string();
+    if benchmark_id == submit_benchmark::DRY_RUN_BENCHMARK_ID {
+        update_status(&format!(
+            "Dry run. Benchmark {} not submitted",
+            job.benchmark_id
+        ))
+        .await;
+        return Ok(());
+    }
+    record_outcome(&job, outcome).await
+}
+
+// reports an accepted (or fraud flagged) benchmark and renames it to the id the server gave it
+async fn record_outcome(job: &Job, outcome: SubmissionOutcome) -> Result<()> {
+    let benchmark_id = outcome.benchmark_id().to_string();
+    setup_job::remove_checkpoint(job.benchmark_id.as_str());
+    let status = match &outcome {
+        SubmissionOutcome::Accepted {
+            reward: Some(reward),
+            ..
+        } => format!(
+            "Success. Benchmark {} submitted, reward {}",
+            benchmark_id, reward
+        ),
+        SubmissionOutcome::Accepted { .. } => {
+            format!("Success. Benchmark {} submitted", benchmark_id)
+        }
+        SubmissionOutcome::Fraud { reason, .. } => {
+            format!(
+                "Benchmark {} submitted but flagged as fraud: {}",
+                benchmark_id, reason
+            )
+        }
+    };
+    update_status(&status).await;
+    events::emit(match &outcome {
+        SubmissionOutcome::Accepted { reward, .. } => BenchmarkerEvent::SubmissionSucceeded {
+            benchmark_id: benchmark_id.clone(),
+            reward: *reward,
+        },
+        SubmissionOutcome::Fraud { reason, .. } => BenchmarkerEvent::SubmissionFailed {
+            benchmark_id: benchmark_id.clone(),
+            error: Arc::new(BenchmarkerError::FraudDetected(reason.clone())),
+        },
+    });
+    let mut state = state().lock().await;
+    let QueryData {
+        benchmarks,
+        proofs,
+        solutions_data,
+        ..
+    } = &mut (*state).query_data;
+    // the submission stands even if part of its state was evicted meanwhile, so whatever is left
+    // is renamed
+    let new_id = BenchmarkId::from(benchmark_id.clone());
+    match benchmarks.remove(&job.benchmark_id) {
+        Some(mut benchmark) => {
+            benchmark.id = benchmark_id.clone();
+            benchmarks.insert(new_id.clone(), benchmark);
+        }
+        None => warn!("No benchmark {} to rename", job.benchmark_id),
+    }
+    match proofs.remove(&job.benchmark_id) {
+        Some(mut proof) => {
+            proof.benchmark_id = benchmark_id.clone();
+            proofs.insert(new_id.clone(), proof);
+        }
+        None => warn!("No proof of benchmark {} to rename", job.benchmark_id),
+    }
+    match solutions_data.remove(&job.benchmark_id) {
+        Some(data) => {
+            solutions_data.insert(new_id.clone(), data);
+        }
+        None => warn!(
+            "No solutions data of benchmark {} to rename",
+            job.benchmark_id
+        ),
+    }
+    state.query_data.touch(&new_id);
+    // the local id is registered by `submit_benchmark::execute`. eviction looks up this one
+    state.submitted.insert(new_id);
+    Ok(())
+}
+
+async fn submit_proof(
+    benchmark_id: BenchmarkId,
+    solutions_data: Vec<SolutionData>,
+    token: &CancellationToken,
+) -> Result<()> {
+    submit_proof::execute(
+        benchmark_id.clone(),
+        solutions_data,
+        submission_config(),
+        token,
+    )
+    .await?;
+    update_status(&format!("Success. Proof {} submitted", benchmark_id)).await;
+    events::emit(BenchmarkerEvent::SubmissionSucceeded {
+        benchmark_id: benchmark_id.to_string(),
+        reward: None,
+    });
+    evict_completed(&mut *state().lock().await);
+    Ok(())
+}
+
+#[cfg(all(test, feature = "standalone"))]
+mod tests {
+    use super::*;
+    use crate::benchmarker::test_utils::{self, LIFESPAN_PERIOD};
+
+    fn start_submission(job: Job) -> CancellationToken {
+        let token = CancellationToken::default();
+        started_submissions().lock().unwrap().insert(
+            job.benchmark_id.clone(),
+            StartedSubmission {
+                token: token.clone(),
+                submission: Submission::Benchmark(job),
+            },
+        );
+        token
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_a_job_that_never_started_keeps_no_token() {
+        let _ctx = test_utils::setup().await;
+        assert!(!cancel_job("never-started"));
+        assert!(!in_flight_ids().contains(&BenchmarkId::from("never-started")));
+    }
+
+    #[tokio::test]
+    async fn test_cancels_submissions_whose_window_closed() {
+        let _ctx = test_utils::setup().await;
+        let expired = start_submission(test_utils::add_benchmark("expired", &[0], 100).await);
+        let open = start_submission(test_utils::add_benchmark("open", &[0], 105).await);
+        cancel_expired(100 + LIFESPAN_PERIOD).await;
+        assert!(expired.is_cancelled());
+        assert!(!open.is_cancelled());
+        started_submissions().lock().unwrap().clear();
+    }
+
+    fn queued_ids(state: &State) -> Vec<BenchmarkId> {
+        let mut ids: Vec<BenchmarkId> = pending(state)
+            .iter()
+            .map(|s| s.benchmark_id().clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_hands_back_the_submission_under_block() {
+        let _ctx = test_utils::setup().await;
+        let a = test_utils::add_benchmark("a", &[0], 100).await;
+        let b = test_utils::add_benchmark("b", &[0], 100).await;
+        let mut state = state().lock().await;
+        let policy = OverflowPolicy::Block;
+        assert!(matches!(
+            push_bounded(&mut state, Submission::Benchmark(a), 1, policy),
+            Ok(None)
+        ));
+        assert!(matches!(
+            push_bounded(&mut state, Submission::Benchmark(b), 1, policy),
+            Err(Submission::Benchmark(job)) if job.benchmark_id.as_str() == "b"
+        ));
+        assert_eq!(queued_ids(&state), vec![BenchmarkId::from("a")]);
+        assert!(state.query_data.benchmarks.contains_key("a"));
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_the_oldest_benchmark_under_drop_oldest() {
+        let _ctx = test_utils::setup().await;
+        let mut jobs = Vec::new();
+        for id in ["a", "b", "c"] {
+            jobs.push(test_utils::add_benchmark(id, &[0], 100).await);
+        }
+        let mut state = state().lock().await;
+        let policy = OverflowPolicy::DropOldest;
+        for job in jobs {
+            let is_c = job.benchmark_id.as_str() == "c";
+            let dropped = push_bounded(&mut state, Submission::Benchmark(job), 2, policy)
+                .unwrap_or_else(|_| panic!("DropOldest should make room"));
+            assert_eq!(
+                dropped.map(|job| job.benchmark_id),
+                is_c.then(|| BenchmarkId::from("a"))
+            );
+        }
+        assert_eq!(
+            queued_ids(&state),
+            vec![BenchmarkId::from("b"), BenchmarkId::from("c")]
+        );
+        assert!(!state.query_data.benchmarks.contains_key("a"));
+        assert!(!state.query_data.solutions_data.contains_key("a"));
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_waits_for_room_under_block() {
+        let _ctx = test_utils::setup().await;
+        let capacity = config().scheduler.queue_capacity;
+        for i in 0..capacity {
+            queue(Submission::Benchmark(
+                test_utils::add_benchmark(&format!("queued{}", i), &[0], 100).await,
+            ))
+            .await;
+        }
+        let job = test_utils::add_benchmark("waiting", &[0], 100).await;
+        let waiting = tokio::spawn(enqueue(Submission::Benchmark(job)));
+        sleep(5 * config().scheduler.poll_interval_ms).await;
+        assert!(!waiting.is_finished());
+        assert!(next_submission().await.is_some());
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiting)
+            .await
+            .expect("enqueue should finish once there is room")
+            .unwrap();
+        assert!(queued_ids(&*state().lock().await).contains(&BenchmarkId::from("waiting")));
+    }
+
+    #[tokio::test]
+    async fn test_batch_takes_benchmarks_finished_at_the_same_height() {
+        let _ctx = test_utils::setup().await;
+        let first = test_utils::add_benchmark("first", &[0], 100).await;
+        let same = test_utils::add_benchmark("same", &[0], 100).await;
+        let later = test_utils::add_benchmark("later", &[0], 100).await;
+        {
+            let mut state = state().lock().await;
+            let policy = OverflowPolicy::Block;
+            state.query_data.latest_block = test_utils::block(100);
+            assert!(push_bounded(&mut state, Submission::Benchmark(same), 10, policy).is_ok());
+            state.query_data.latest_block = test_utils::block(101);
+            assert!(push_bounded(&mut state, Submission::Benchmark(later), 10, policy).is_ok());
+        }
+        finished_at()
+            .lock()
+            .unwrap()
+            .insert(first.benchmark_id.clone(), 100);
+        let batch: Vec<BenchmarkId> = take_batch(first)
+            .await
+            .into_iter()
+            .map(|job| job.benchmark_id)
+            .collect();
+        assert_eq!(batch, vec!["first", "same"]);
+        assert_eq!(
+            queued_ids(&*state().lock().await),
+            vec![BenchmarkId::from("later")]
+        );
+        finished_at().lock().unwrap().clear();
+    }
+
+    #[tokio::test]
+    async fn test_outcome_renames_what_is_left_of_an_evicted_benchmark() {
+        let _ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("local", &[0], 100).await;
+        state().lock().await.query_data.proofs.remove("local");
+        let outcome = SubmissionOutcome::Accepted {
+            benchmark_id: "server".to_string(),
+            reward: None,
+        };
+        record_outcome(&job, outcome).await.unwrap();
+        let state = state().lock().await;
+        assert_eq!(state.query_data.benchmarks["server"].id, "server");
+        assert!(state.query_data.solutions_data.contains_key("server"));
+        assert!(!state.query_data.proofs.contains_key("server"));
+        assert!(state.submitted.contains("server"));
+    }
+
+    #[tokio::test]
+    async fn test_requeued_in_flight_submission_is_pending_with_its_meta_data() {
+        let _ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("in-flight", &[0, 1, 2], 100).await;
+        let mut state = state().lock().await;
+        // taken out of the state by prepare_submission
+        state
+            .query_data
+            .benchmarks
+            .get_mut(&job.benchmark_id)
+            .unwrap()
+            .solutions_meta_data = None;
+        start_submission(job);
+        assert_eq!(requeue_in_flight(&mut state), 1);
+        started_submissions().lock().unwrap().clear();
+        let pending_ids: Vec<BenchmarkId> = pending(&state)
+            .iter()
+            .map(|s| s.benchmark_id().clone())
+            .collect();
+        assert_eq!(pending_ids, vec![BenchmarkId::from("in-flight")]);
+        let mut nonces: Vec<u64> = state.query_data.benchmarks["in-flight"]
+            .solutions_meta_data
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|d| d.nonce)
+            .collect();
+        nonces.sort();
+        assert_eq!(nonces, vec![0, 1, 2]);
+    }
+}
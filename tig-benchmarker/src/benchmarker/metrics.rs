@@ -0,0 +1,136 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttemptOutcome {
+    Success,
+    Fraud,
+    Error,
+}
+
+impl AttemptOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AttemptOutcome::Success => "success",
+            AttemptOutcome::Fraud => "fraud",
+            AttemptOutcome::Error => "error",
+        }
+    }
+}
+
+// each handle owns its own registry so independent instances do not collide
+pub struct MetricsHandle {
+    registry: Registry,
+    submit_attempts: IntCounterVec,
+    submit_duration: Histogram,
+    pending_jobs: IntGauge,
+    dropped_jobs: IntCounter,
+}
+
+impl MetricsHandle {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let submit_attempts = IntCounterVec::new(
+            Opts::new(
+                "tig_submit_attempts_total",
+                "Benchmark submission attempts by outcome",
+            ),
+            &["outcome"],
+        )
+        .unwrap();
+        let submit_duration = Histogram::with_opts(HistogramOpts::new(
+            "tig_submit_duration_seconds",
+            "Duration of benchmark submission attempts",
+        ))
+        .unwrap();
+        let pending_jobs =
+            IntGauge::new("tig_pending_jobs", "Submissions waiting to be started").unwrap();
+        let dropped_jobs = IntCounter::new(
+            "tig_dropped_jobs_total",
+            "Benchmarks dropped because the submission queue was full",
+        )
+        .unwrap();
+        registry
+            .register(Box::new(submit_attempts.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(submit_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(pending_jobs.clone())).unwrap();
+        registry.register(Box::new(dropped_jobs.clone())).unwrap();
+        Self {
+            registry,
+            submit_attempts,
+            submit_duration,
+            pending_jobs,
+            dropped_jobs,
+        }
+    }
+
+    pub fn record_attempt(&self, outcome: AttemptOutcome, duration_ms: u64) {
+        self.submit_attempts
+            .with_label_values(&[outcome.as_str()])
+            .inc();
+        self.submit_duration.observe(duration_ms as f64 / 1000.0);
+    }
+
+    pub fn set_pending_jobs(&self, num_pending: usize) {
+        self.pending_jobs.set(num_pending as i64);
+    }
+
+    pub fn record_dropped_job(&self) {
+        self.dropped_jobs.inc();
+    }
+
+    // prometheus text exposition format
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+impl Default for MetricsHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for MetricsHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsHandle").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_includes_recorded_metrics() {
+        let metrics = MetricsHandle::new();
+        metrics.record_attempt(AttemptOutcome::Success, 1500);
+        metrics.record_attempt(AttemptOutcome::Error, 500);
+        metrics.record_attempt(AttemptOutcome::Error, 500);
+        metrics.set_pending_jobs(3);
+        metrics.record_dropped_job();
+        let encoded = metrics.encode();
+        assert!(encoded.contains("tig_submit_attempts_total{outcome=\"success\"} 1"));
+        assert!(encoded.contains("tig_submit_attempts_total{outcome=\"error\"} 2"));
+        assert!(encoded.contains("tig_submit_duration_seconds_count 3"));
+        assert!(encoded.contains("tig_pending_jobs 3"));
+        assert!(encoded.contains("tig_dropped_jobs_total 1"));
+    }
+
+    #[test]
+    fn test_handles_are_independent() {
+        let a = MetricsHandle::new();
+        let b = MetricsHandle::new();
+        a.set_pending_jobs(5);
+        assert!(b.encode().contains("tig_pending_jobs 0"));
+    }
+}
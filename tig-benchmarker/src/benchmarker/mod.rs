@@ -1,11 +1,43 @@
+mod benchmark_id;
+#[cfg(feature = "standalone")]
+mod block_height_cache;
+mod block_height_watcher;
+mod challenge_selector;
+mod checkpoint;
+mod compressed_solution_data;
+mod config;
 mod difficulty_sampler;
 pub mod download_wasm;
+mod error;
+mod events;
 mod find_proof_to_submit;
+mod health_check;
+#[cfg(feature = "standalone")]
+mod history;
+mod job_runner;
+mod metrics;
 mod query_data;
+mod query_data_diff;
+#[cfg(feature = "standalone")]
+mod resource_guard;
+mod sample_verifier;
+mod scheduler;
 mod setup_job;
+mod shutdown;
+mod submission_outcome;
 mod submit_benchmark;
+mod submit_benchmarks_batch;
 mod submit_proof;
+mod submitted_registry;
+#[cfg(all(test, feature = "standalone"))]
+mod test_utils;
+mod timing;
 mod utils;
+#[cfg(feature = "standalone")]
+mod validate;
+mod verify;
+#[cfg(feature = "standalone")]
+mod worker_manager;
 
 #[cfg(not(feature = "cuda"))]
 pub mod run_benchmark;
@@ -13,29 +45,71 @@ pub mod run_benchmark;
 #[path = "cuda_run_benchmark.rs"]
 pub mod run_benchmark;
 
-use crate::future_utils::{sleep, spawn, time, Mutex};
+use crate::future_utils::{sleep, spawn, spawn_blocking, time, Mutex};
+pub use benchmark_id::BenchmarkId;
+pub use challenge_selector::{
+    AllChallenges, ChallengeConfig, ChallengeSelectionStrategy, ChallengeSelector, TopNByFee,
+};
+pub use checkpoint::{Checkpoint, CheckpointWriter};
+pub use compressed_solution_data::CompressedSolutionData;
+pub use config::*;
 use difficulty_sampler::DifficultySampler;
+pub use error::*;
+#[cfg(feature = "standalone")]
+pub use events::subscribe_events;
+pub use events::BenchmarkerEvent;
+#[cfg(feature = "standalone")]
+pub use history::{HistoryEntry, HistoryStore, HistorySummary};
+use job_runner::Submission;
+pub use job_runner::{cancel_job, OverflowPolicy};
+use linked_hash_map::LinkedHashMap;
+pub use metrics::{AttemptOutcome, MetricsHandle};
 use once_cell::sync::OnceCell;
+pub use query_data_diff::QueryDataDiff;
+#[cfg(feature = "standalone")]
+pub use resource_guard::{ResourceGuard, ResourceProbe, ResourceUsage, SystemProbe};
+pub use sample_verifier::SampleVerifier;
+pub use scheduler::{default_priority, JobScheduler, PrioritizedJob, PriorityFn};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
-use tig_api::Api;
+pub use shutdown::{shutdown_signal, CancellationToken, ShutdownSignal};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+    sync::Arc,
+};
+pub use submission_outcome::SubmissionOutcome;
+pub use submit_benchmark::{SolutionSelector, SortStrategy};
+pub use submitted_registry::SubmittedRegistry;
+use tig_api::{ApiClientBuilder, BenchmarkApi, Ed25519Signer, Signer};
 use tig_structs::{
     config::{MinMaxDifficulty, WasmVMConfig},
     core::*,
 };
+pub use timing::{SubmissionTiming, TimingLog};
+use tracing::{debug, info, warn};
+pub use utils::RetryPolicy;
+#[cfg(feature = "standalone")]
+pub use validate::{validate, Check, ValidationReport};
+pub use verify::VerificationError;
+#[cfg(feature = "standalone")]
+pub use worker_manager::{WorkerManager, WorkerManagerConfig, WorkerResult, WorkerTask};
 
-pub type Result<T> = std::result::Result<T, String>;
-
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct QueryData {
     pub latest_block: Block,
     pub player_data: Option<PlayerBlockData>,
     pub challenges: Vec<Challenge>,
     pub download_urls: HashMap<String, String>,
     pub algorithms_by_challenge: HashMap<String, Vec<Algorithm>>,
-    pub benchmarks: HashMap<String, Benchmark>,
-    pub proofs: HashMap<String, Proof>,
-    pub frauds: HashMap<String, Fraud>,
+    pub benchmarks: HashMap<BenchmarkId, Benchmark>,
+    pub proofs: HashMap<BenchmarkId, Proof>,
+    pub frauds: HashMap<BenchmarkId, Fraud>,
+    // solutions data of proofs computed locally, held here instead of in `proofs`
+    #[serde(default)]
+    pub solutions_data: HashMap<BenchmarkId, CompressedSolutionData>,
+    // benchmark ids from least to most recently used, for `storage.max_entries`
+    #[serde(skip)]
+    pub recency: LinkedHashMap<BenchmarkId, ()>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -65,11 +139,73 @@ impl Timer {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Job {
     pub download_url: String,
-    pub benchmark_id: String,
+    pub benchmark_id: BenchmarkId,
     pub settings: BenchmarkSettings,
     pub solution_signature_threshold: u32,
     pub sampled_nonces: Option<Vec<u64>>,
     pub wasm_vm_config: WasmVMConfig,
+    // identity the benchmark is submitted as. never sent to slaves or persisted, so a job
+    // loaded from disk falls back to `signer()`
+    #[serde(skip)]
+    pub signer: Option<Arc<dyn Signer>>,
+}
+
+// none of a job's fields are floats, so the derived PartialEq is an equivalence
+impl Eq for Job {}
+
+// jobs with the same id can still differ, e.g. a benchmark and the proof job that follows it
+impl std::hash::Hash for Job {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&self.benchmark_id, state);
+    }
+}
+
+// by benchmark id first, then by every other field so unequal jobs never compare equal
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Job {
+    #[allow(clippy::type_complexity)]
+    fn sort_key(
+        &self,
+    ) -> (
+        &BenchmarkId,
+        &str,
+        &str,
+        &str,
+        &str,
+        &str,
+        &[i32],
+        u32,
+        Option<&[u64]>,
+        u64,
+        u64,
+        Option<Vec<u8>>,
+    ) {
+        (
+            &self.benchmark_id,
+            &self.download_url,
+            &self.settings.player_id,
+            &self.settings.block_id,
+            &self.settings.challenge_id,
+            &self.settings.algorithm_id,
+            &self.settings.difficulty,
+            self.solution_signature_threshold,
+            self.sampled_nonces.as_deref(),
+            self.wasm_vm_config.max_memory,
+            self.wasm_vm_config.max_fuel,
+            self.signer.as_ref().map(|s| s.public_key()),
+        )
+    }
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -97,6 +233,9 @@ impl NonceIterator {
     pub fn attempts(&self) -> u64 {
         self.attempts
     }
+    pub fn current(&self) -> u64 {
+        self.current
+    }
     pub fn is_empty(&self) -> bool {
         self.nonces.as_ref().is_some_and(|x| x.is_empty()) || self.current == u64::MAX
     }
@@ -140,23 +279,123 @@ pub struct State {
     pub query_data: QueryData,
     pub selected_algorithms: HashMap<String, String>,
     pub job: Option<Job>,
-    pub submission_errors: HashMap<String, String>,
+    pub submission_errors: HashMap<BenchmarkId, String>,
+    // benchmark submissions are ordered by `job_scheduler`, everything else is FIFO
+    pub pending_submissions: VecDeque<Submission>,
+    pub job_scheduler: JobScheduler,
+    pub submitted: SubmittedRegistry,
+    #[serde(skip_serializing)]
+    pub metrics: Arc<MetricsHandle>,
+    #[serde(skip_serializing)]
+    pub timings: Arc<TimingLog>,
     #[serde(skip_serializing)]
     pub difficulty_samplers: HashMap<String, DifficultySampler>,
+    // found in `storage.checkpoint_dir` at startup and not yet resumed
+    #[serde(skip_serializing)]
+    pub checkpoints: Vec<Checkpoint>,
 }
 
 static STATE: OnceCell<Mutex<State>> = OnceCell::new();
-static API: OnceCell<Api> = OnceCell::new();
+static API: OnceCell<Box<dyn BenchmarkApi>> = OnceCell::new();
 static PLAYER_ID: OnceCell<String> = OnceCell::new();
+static CONFIG: OnceCell<BenchmarkerConfig> = OnceCell::new();
+static SNAPSHOT_PATH: OnceCell<Option<PathBuf>> = OnceCell::new();
+static SERVER_PROTOCOL_VERSION: OnceCell<Option<u16>> = OnceCell::new();
+static CHALLENGE_SELECTOR: OnceCell<Box<dyn ChallengeSelector>> = OnceCell::new();
+static SIGNER: OnceCell<Option<Arc<dyn Signer>>> = OnceCell::new();
+static SNAPSHOT_WRITING: OnceCell<Mutex<()>> = OnceCell::new();
+#[cfg(feature = "standalone")]
+static HISTORY: OnceCell<Option<HistoryStore>> = OnceCell::new();
+
+pub fn api() -> &'static dyn BenchmarkApi {
+    API.get().expect("API should be initialised").as_ref()
+}
+
+pub fn challenge_selector() -> &'static dyn ChallengeSelector {
+    CHALLENGE_SELECTOR
+        .get()
+        .expect("CHALLENGE_SELECTOR should be initialised")
+        .as_ref()
+}
+
+fn install_challenge_selector(
+    strategy: &ChallengeSelectionStrategy,
+    selector: Option<Box<dyn ChallengeSelector>>,
+) {
+    CHALLENGE_SELECTOR.get_or_init(|| selector.unwrap_or_else(|| strategy.build()));
+}
+
+// installs `api` in place of the one `setup` would create, which is how `test_utils` puts a
+// `MockBenchmarkApi` behind `api()`. returns false if an api is already installed
+#[cfg(all(test, feature = "standalone"))]
+pub fn set_api(api: Box<dyn BenchmarkApi>) -> bool {
+    API.set(api).is_ok()
+}
 
-pub fn api() -> &'static Api {
-    API.get().expect("API should be initialised")
+// the signer of jobs that do not carry their own. None until `setup`, or if
+// `submission.signing_key_path` is unset
+pub fn signer() -> Option<Arc<dyn Signer>> {
+    SIGNER.get().cloned().flatten()
+}
+
+// records a benchmark the server responded to. does nothing without a [history] path
+pub fn record_history(resp: &tig_api::SubmitBenchmarkResp, block_height: u32) {
+    #[cfg(feature = "standalone")]
+    if let Some(history) = HISTORY.get().and_then(|h| h.as_ref()) {
+        let entry = HistoryEntry {
+            benchmark_id: resp.benchmark_id.clone(),
+            submitted_at: time(),
+            block_height,
+            verified: resp.verified.is_ok(),
+            fraud_reason: resp.verified.clone().err(),
+            reward: resp.reward,
+        };
+        if let Err(e) = history.record(&entry) {
+            warn!("Failed to record history for {}: {}", resp.benchmark_id, e);
+        }
+    }
+    #[cfg(not(feature = "standalone"))]
+    let _ = (resp, block_height);
 }
 
 pub fn player_id() -> &'static String {
     PLAYER_ID.get().expect("PLAYER_ID should be initialised")
 }
 
+pub fn config() -> &'static BenchmarkerConfig {
+    CONFIG.get().expect("CONFIG should be initialised")
+}
+
+pub fn submission_config() -> &'static SubmissionConfig {
+    &config().submission
+}
+
+pub fn snapshot_path() -> Option<&'static PathBuf> {
+    SNAPSHOT_PATH
+        .get()
+        .expect("SNAPSHOT_PATH should be initialised")
+        .as_ref()
+}
+
+// None until negotiated in `setup`, or if the server predates protocol versioning
+pub fn server_protocol_version() -> Option<u16> {
+    SERVER_PROTOCOL_VERSION.get().copied().flatten()
+}
+
+async fn negotiate_protocol_version() -> Option<u16> {
+    match api().get_protocol().await {
+        Ok(resp) => {
+            info!("Server speaks protocol version {}", resp.protocol_version);
+            Some(resp.protocol_version)
+        }
+        Err(e) if utils::is_unsupported_endpoint(&e) => None,
+        Err(e) => {
+            warn!("Failed to query server protocol version: {:?}", e);
+            None
+        }
+    }
+}
+
 pub fn state() -> &'static Mutex<State> {
     STATE.get().expect("STATE should be initialised")
 }
@@ -165,7 +404,7 @@ async fn update_status(status: &str) {
     let mut state = state().lock().await;
     if let Status::Running(_) = state.status {
         state.status = Status::Running(status.to_string());
-        println!("{}", status);
+        info!("{}", status);
         #[cfg(feature = "browser")]
         web_sys::console::log_1(&status.to_string().into());
     }
@@ -185,9 +424,10 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
         let state = (*state()).lock().await;
         state.query_data.latest_block.id != new_query_data.latest_block.id
     } {
-        {
+        let height = new_query_data.latest_block.details.height;
+        let diff = {
             let mut state = (*state()).lock().await;
-            let block_started_cutoff = new_query_data.latest_block.details.height.saturating_sub(
+            let block_started_cutoff = height.saturating_sub(
                 new_query_data
                     .latest_block
                     .config()
@@ -206,14 +446,27 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
             latest_frauds.retain(|id, _| latest_benchmarks.contains_key(id));
             latest_frauds.extend(new_query_data.frauds.drain());
 
+            let mut latest_solutions_data = std::mem::take(&mut state.query_data.solutions_data);
+            latest_solutions_data.retain(|id, _| latest_benchmarks.contains_key(id));
+            let recency = std::mem::take(&mut state.query_data.recency);
+
             (*state)
                 .submission_errors
                 .retain(|id, _| latest_benchmarks.contains_key(id));
             new_query_data.benchmarks = latest_benchmarks;
             new_query_data.proofs = latest_proofs;
             new_query_data.frauds = latest_frauds;
-            (*state).query_data = new_query_data;
-        }
+            new_query_data.solutions_data = latest_solutions_data;
+            new_query_data.recency = recency;
+            let before = std::mem::replace(&mut (*state).query_data, new_query_data);
+            evict_completed(&mut state);
+            query_data_diff::diff(&before, &state.query_data)
+        };
+        debug!(?diff, "Query data changed");
+        events::emit(BenchmarkerEvent::BlockAdvanced {
+            height: height as u64,
+        });
+        events::emit(BenchmarkerEvent::StateDiff(diff));
 
         update_status("Updating difficulty sampler with query data").await;
         {
@@ -238,13 +491,12 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
     update_status("Finding proof to submit").await;
     match find_proof_to_submit::execute().await? {
         Some((benchmark_id, solutions_data)) => {
-            update_status(&format!("Submitting proof for {}", benchmark_id)).await;
-            if let Err(e) = submit_proof::execute(benchmark_id.clone(), solutions_data).await {
-                let mut state = state().lock().await;
-                state.submission_errors.insert(benchmark_id, e.clone());
-                return Err(e);
-            }
-            update_status(&format!("Success. Proof {} submitted", benchmark_id)).await;
+            update_status(&format!("Queueing proof for {}", benchmark_id)).await;
+            job_runner::enqueue(Submission::Proof {
+                benchmark_id,
+                solutions_data,
+            })
+            .await;
         }
         None => {
             update_status("No proof to submit").await;
@@ -252,7 +504,7 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
     }
     // creates a benchmark & proof with job.benchmark_id
     update_status("Selecting settings to benchmark").await;
-    setup_job::execute().await?;
+    let resume_nonces = setup_job::execute().await?;
     let job = {
         let state = state().lock().await;
         state.job.clone().unwrap()
@@ -267,11 +519,15 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
     let wasm = download_wasm::execute(&job).await?;
 
     // variables that are shared by workers
-    let nonce_iters = match &job.sampled_nonces {
-        Some(nonces) => vec![Arc::new(Mutex::new(NonceIterator::from_vec(
+    let nonce_iters = match (&job.sampled_nonces, resume_nonces) {
+        (Some(nonces), _) => vec![Arc::new(Mutex::new(NonceIterator::from_vec(
             nonces.clone(),
         )))],
-        None => (0..num_workers)
+        (None, Some(next_nonces)) => next_nonces
+            .into_iter()
+            .map(|x| Arc::new(Mutex::new(NonceIterator::from_u64(x))))
+            .collect(),
+        (None, None) => (0..num_workers)
             .into_iter()
             .map(|x| {
                 Arc::new(Mutex::new(NonceIterator::from_u64(
@@ -282,15 +538,49 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
     };
     let solutions_data = Arc::new(Mutex::new(Vec::<SolutionData>::new()));
     let solutions_count = Arc::new(Mutex::new(0u32));
+    // recomputed solutions are already on the server, so only new benchmarks are checkpointed
+    let mut checkpoint_writer = match (&config().storage.checkpoint_dir, &job.sampled_nonces) {
+        (Some(dir), None) => {
+            let num_solutions = state()
+                .lock()
+                .await
+                .query_data
+                .benchmarks
+                .get(&job.benchmark_id)
+                .map_or(0, |b| b.details.num_solutions);
+            Some(
+                CheckpointWriter::new(
+                    dir,
+                    job.benchmark_id.as_str(),
+                    config().storage.checkpoint_interval,
+                )
+                .resumed(num_solutions),
+            )
+        }
+        _ => None,
+    };
     update_status("Starting benchmark").await;
-    run_benchmark::execute(
-        nonce_iters.iter().cloned().collect(),
+    events::emit(BenchmarkerEvent::JobStarted { job: job.clone() });
+    #[cfg(feature = "standalone")]
+    let workers = worker_manager::start(
         &job,
         &wasm,
+        nonce_iters.iter().cloned().collect(),
         solutions_data.clone(),
         solutions_count.clone(),
-    )
-    .await;
+    )?;
+    #[cfg(not(feature = "standalone"))]
+    let workers: Option<()> = None;
+    if workers.is_none() {
+        run_benchmark::execute(
+            nonce_iters.iter().cloned().collect(),
+            &job,
+            &wasm,
+            solutions_data.clone(),
+            solutions_count.clone(),
+        )
+        .await;
+    }
     {
         let mut state = state().lock().await;
         (*state).timer = Some(Timer::new(ms_per_benchmark as u64));
@@ -312,6 +602,11 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
                 num_solutions, num_attempts
             ))
             .await;
+            if let Some(writer) = checkpoint_writer.as_mut() {
+                if writer.is_due(num_solutions) {
+                    write_checkpoint(writer, &job, &nonce_iters).await;
+                }
+            }
             let State {
                 status,
                 timer: time_left,
@@ -329,6 +624,10 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
     for nonce_iter in nonce_iters {
         (*(*nonce_iter).lock().await).empty();
     }
+    #[cfg(feature = "standalone")]
+    if let Some(workers) = workers {
+        workers.shutdown().await;
+    }
 
     // transfers solutions computed by workers to benchmark state
     let num_solutions =
@@ -336,17 +635,10 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
     if let Some(sampled_nonces) = job.sampled_nonces.as_ref() {
         if num_solutions != sampled_nonces.len() as u32 {
             let mut state = (*state()).lock().await;
-            (*state)
-                .query_data
-                .proofs
-                .get_mut(&job.benchmark_id)
-                .unwrap()
-                .solutions_data
-                .take();
-            return Err(format!(
-                "Failed to recompute solutions for {}",
-                job.benchmark_id
-            ));
+            (*state).query_data.solutions_data.remove(&job.benchmark_id);
+            return Err(BenchmarkerError::RecomputeFailed {
+                benchmark_id: job.benchmark_id.clone(),
+            });
         } else {
             update_status(&format!(
                 "Finished. Recompute solutions for {}",
@@ -368,42 +660,57 @@ async fn run_once(num_workers: u32, ms_per_benchmark: u32) -> Result<()> {
         }
 
         if num_solutions == 0 {
+            setup_job::remove_checkpoint(job.benchmark_id.as_str());
             update_status("Finished. No solutions to submit").await;
         } else {
-            update_status(&format!("Finished. Submitting {} solutions", num_solutions,)).await;
-            let benchmark_id = match submit_benchmark::execute(&job).await {
-                Ok(benchmark_id) => benchmark_id,
-                Err(e) => {
-                    let mut state = (*state()).lock().await;
-                    state
-                        .submission_errors
-                        .insert(job.benchmark_id.clone(), e.clone());
-                    return Err(e);
-                }
-            };
-            update_status(&format!("Success. Benchmark {} submitted", benchmark_id)).await;
-            let mut state = (*state()).lock().await;
-            let QueryData {
-                benchmarks, proofs, ..
-            } = &mut (*state).query_data;
-            let mut benchmark = benchmarks.remove(&job.benchmark_id).unwrap();
-            let mut proof = proofs.remove(&job.benchmark_id).unwrap();
-            benchmark.id = benchmark_id.clone();
-            proof.benchmark_id = benchmark_id.clone();
-            benchmarks.insert(benchmark_id.clone(), benchmark);
-            proofs.insert(benchmark_id.clone(), proof);
+            update_status(&format!("Finished. Queueing {} solutions", num_solutions,)).await;
+            job_runner::enqueue(Submission::Benchmark(job)).await;
         }
     }
     Ok(())
 }
 
-pub async fn drain_solutions(benchmark_id: &String, solutions_data: &mut Vec<SolutionData>) -> u32 {
+// a failed checkpoint only costs progress on a crash, so the benchmark carries on
+async fn write_checkpoint(
+    writer: &mut CheckpointWriter,
+    job: &Job,
+    nonce_iters: &[Arc<Mutex<NonceIterator>>],
+) {
+    let mut next_nonces = Vec::with_capacity(nonce_iters.len());
+    for nonce_iter in nonce_iters {
+        next_nonces.push((*nonce_iter).lock().await.current());
+    }
+    let state = state().lock().await;
+    let (Some(benchmark), Some(solutions_data)) = (
+        state.query_data.benchmarks.get(&job.benchmark_id),
+        state.query_data.solutions_data.get(&job.benchmark_id),
+    ) else {
+        return;
+    };
+    let checkpoint = Checkpoint {
+        job: job.clone(),
+        block_started: benchmark.details.block_started,
+        next_nonces,
+        solutions_meta_data: benchmark.solutions_meta_data.clone().unwrap_or_default(),
+        solutions_data: solutions_data.clone(),
+    };
+    if let Err(e) = writer.write(&checkpoint) {
+        warn!("Failed to checkpoint {}: {}", job.benchmark_id, e);
+    }
+}
+
+pub async fn drain_solutions(
+    benchmark_id: &BenchmarkId,
+    solutions_data: &mut Vec<SolutionData>,
+) -> u32 {
     let mut state = (*state()).lock().await;
     let QueryData {
-        benchmarks, proofs, ..
+        benchmarks,
+        solutions_data: stored_solutions_data,
+        ..
     } = &mut (*state).query_data;
     if let Some(benchmark) = benchmarks.get_mut(benchmark_id) {
-        let proof = proofs.get_mut(benchmark_id).unwrap();
+        let to_update = stored_solutions_data.get_mut(benchmark_id).unwrap();
         if let Some(x) = benchmark.solutions_meta_data.as_mut() {
             x.extend(
                 solutions_data
@@ -412,13 +719,38 @@ pub async fn drain_solutions(benchmark_id: &String, solutions_data: &mut Vec<Sol
             );
             benchmark.details.num_solutions = x.len() as u32;
         }
-        let to_update = proof.solutions_data.as_mut().unwrap();
-        to_update.extend(solutions_data.drain(..));
+        to_update.extend(solutions_data.iter());
+        solutions_data.clear();
         to_update.len() as u32
     } else {
         0
     }
 }
+
+// applies `storage.max_entries`. benchmarks that are being computed, are waiting to be
+// submitted or still have a proof to compute are never evicted
+fn evict_completed(state: &mut State) -> Vec<BenchmarkId> {
+    let max_entries = config().storage.max_entries;
+    if max_entries == 0 {
+        return Vec::new();
+    }
+    let mut in_use: HashSet<BenchmarkId> = job_runner::pending(state)
+        .iter()
+        .map(|s| s.benchmark_id().clone())
+        .chain(job_runner::in_flight_ids())
+        .chain(state.query_data.solutions_data.keys().cloned())
+        .collect();
+    if let Some(job) = &state.job {
+        in_use.insert(job.benchmark_id.clone());
+    }
+    let evicted = state
+        .query_data
+        .evict_completed(max_entries, &state.submitted, &in_use);
+    if !evicted.is_empty() {
+        info!("Evicted {} completed benchmarks from memory", evicted.len());
+    }
+    evicted
+}
 pub async fn start(num_workers: u32, ms_per_benchmark: u32) {
     {
         let mut state = (*state()).lock().await;
@@ -427,6 +759,11 @@ pub async fn start(num_workers: u32, ms_per_benchmark: u32) {
         }
         state.status = Status::Starting;
     }
+    job_runner::start();
+    block_height_watcher::start();
+    health_check::start();
+    #[cfg(feature = "standalone")]
+    resource_guard::start(&config().resources);
     spawn(async move {
         {
             let mut state = (*state()).lock().await;
@@ -440,9 +777,12 @@ pub async fn start(num_workers: u32, ms_per_benchmark: u32) {
                 }
             }
             if let Err(e) = run_once(num_workers, ms_per_benchmark).await {
-                update_status(&format!("Error: {:?}", e)).await;
+                update_status(&format!("Error: {}", e)).await;
                 sleep(5000).await;
             }
+            if let Err(e) = save_snapshot().await {
+                update_status(&format!("Error: {}", e)).await;
+            }
         }
     });
 }
@@ -462,10 +802,127 @@ pub async fn select_algorithm(challenge_name: String, algorithm_name: String) {
         .insert(challenge_name, algorithm_name);
 }
 
-pub async fn setup(api_url: String, api_key: String, player_id: String) {
-    API.get_or_init(|| Api::new(api_url, api_key));
+// the state is only locked while the snapshot is serialised. saves are taken one at a time, so
+// an older snapshot cannot be written over a newer one
+pub async fn save_snapshot() -> Result<()> {
+    if let Some(path) = snapshot_path() {
+        let _writing = SNAPSHOT_WRITING.get_or_init(|| Mutex::new(())).lock().await;
+        let data = {
+            let state = state().lock().await;
+            state
+                .query_data
+                .serialize_snapshot(&job_runner::pending(&state), &state.submitted)?
+        };
+        spawn_blocking(move || query_data::write_snapshot(path, &data)).await?;
+    }
+    Ok(())
+}
+
+// stops new submissions from starting, waits up to `drain_timeout_ms` for in-flight ones and
+// saves the snapshot. anything not yet submitted, including submissions still in flight when
+// the drain times out, is kept in the snapshot as pending
+pub async fn shutdown() -> Result<()> {
+    shutdown_signal().trigger();
+    stop().await;
+    info!("Shutting down. Waiting for in-flight submissions to finish");
+    if !job_runner::drain(config().scheduler.drain_timeout_ms).await {
+        let num_requeued = job_runner::requeue_in_flight(&mut *state().lock().await);
+        warn!(
+            "Drain timeout expired. Saving {} in-flight submissions as pending",
+            num_requeued
+        );
+    }
+    if snapshot_path().is_none() {
+        let num_pending = job_runner::pending(&*state().lock().await).len();
+        if num_pending > 0 {
+            warn!(
+                "No snapshot path set. Dropping {} pending submissions",
+                num_pending
+            );
+        }
+    }
+    save_snapshot().await
+}
+
+// `challenge_selector` replaces the selector built from `selection.strategy`, for selection
+// rules the config cannot express
+pub async fn setup(
+    config: BenchmarkerConfig,
+    api_key: String,
+    player_id: String,
+    snapshot_path: Option<PathBuf>,
+    challenge_selector: Option<Box<dyn ChallengeSelector>>,
+) {
+    // the browser has no config file, so this is the only check its config gets
+    if let Err(e) = config.validate() {
+        panic!("Invalid config: {}", e);
+    }
+    API.get_or_init(|| {
+        Box::new(
+            ApiClientBuilder::from_config(&config.api)
+                .with_api_key(api_key)
+                .build()
+                .expect("Failed to build api client"),
+        )
+    });
     PLAYER_ID.get_or_init(|| player_id);
-    let query_data = query_data::execute().await.expect("Failed to query data");
+    install_challenge_selector(&config.selection.strategy, challenge_selector);
+    SIGNER.get_or_init(|| {
+        config.submission.signing_key_path.as_ref().map(|path| {
+            let secret_key = std::fs::read_to_string(path).expect("Failed to read signing key");
+            let signer = Ed25519Signer::from_hex(&secret_key).expect("Failed to load signing key");
+            Arc::new(signer) as Arc<dyn Signer>
+        })
+    });
+    #[cfg(feature = "standalone")]
+    HISTORY.get_or_init(|| {
+        config
+            .history
+            .path
+            .as_ref()
+            .and_then(|path| match HistoryStore::open(path) {
+                Ok(history) => Some(history),
+                Err(e) => {
+                    warn!("Not recording history to {:?}: {}", path, e);
+                    None
+                }
+            })
+    });
+    CONFIG.get_or_init(|| config);
+    SNAPSHOT_PATH.get_or_init(|| snapshot_path);
+    let server_protocol_version = negotiate_protocol_version().await;
+    SERVER_PROTOCOL_VERSION.get_or_init(|| server_protocol_version);
+    let mut query_data = query_data::execute().await.expect("Failed to query data");
+    let mut pending_submissions = Vec::new();
+    let mut submitted = SubmittedRegistry::default();
+    if let Some(path) = self::snapshot_path() {
+        match QueryData::load_from_disk(path) {
+            Ok(Some((saved, saved_pending, saved_submitted))) => {
+                info!("Resuming from snapshot {:?}", path);
+                query_data.restore_from(saved);
+                pending_submissions = saved_pending;
+                submitted = saved_submitted;
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Ignoring snapshot {:?}: {}", path, e),
+        }
+    }
+    let mut checkpoints = Vec::new();
+    if let Some(dir) = self::config().storage.checkpoint_dir.as_ref() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            warn!("Failed to create checkpoint dir {:?}: {}", dir, e);
+        }
+        match checkpoint::load_all(dir) {
+            // benchmarks that finished generating are resumed as pending submissions instead
+            Ok(loaded) => checkpoints.extend(loaded.into_iter().filter(|c| {
+                !submitted.contains(c.job.benchmark_id.as_str())
+                    && !pending_submissions
+                        .iter()
+                        .any(|s| *s.benchmark_id() == c.job.benchmark_id)
+            })),
+            Err(e) => warn!("Ignoring checkpoints in {:?}: {}", dir, e),
+        }
+    }
     let mut difficulty_samplers = HashMap::new();
     for challenge in query_data.challenges.iter() {
         let difficulty_sampler = difficulty_samplers
@@ -484,6 +941,60 @@ pub async fn setup(api_url: String, api_key: String, player_id: String) {
             selected_algorithms: HashMap::new(),
             job: None,
             submission_errors: HashMap::new(),
+            pending_submissions: VecDeque::new(),
+            job_scheduler: JobScheduler::default(),
+            submitted,
+            metrics: Arc::new(MetricsHandle::new()),
+            timings: Arc::new(TimingLog::new(config().metrics.timing_log_len)),
+            checkpoints,
         })
     });
+    for submission in pending_submissions {
+        let benchmark_exists = {
+            let state = state().lock().await;
+            state
+                .query_data
+                .benchmarks
+                .contains_key(submission.benchmark_id())
+        };
+        if benchmark_exists {
+            job_runner::queue(submission).await;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "standalone"))]
+mod tests {
+    use super::*;
+
+    struct LastChallenge;
+
+    impl ChallengeSelector for LastChallenge {
+        fn select(&self, available: &[ChallengeConfig], _: &QueryData) -> Vec<ChallengeConfig> {
+            available.last().cloned().into_iter().collect()
+        }
+    }
+
+    fn challenge(challenge_id: &str) -> ChallengeConfig {
+        ChallengeConfig {
+            challenge_id: challenge_id.to_string(),
+            challenge_name: challenge_id.to_string(),
+            fee: 1.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_given_challenge_selector_replaces_the_strategy() {
+        let _ctx = test_utils::setup().await;
+        install_challenge_selector(
+            &ChallengeSelectionStrategy::AllChallenges,
+            Some(Box::new(LastChallenge)),
+        );
+        let available = vec![challenge("c001"), challenge("c002")];
+        let query_data = &state().lock().await.query_data;
+        assert_eq!(
+            challenge_selector().select(&available, query_data),
+            vec![challenge("c002")]
+        );
+    }
 }
@@ -1,16 +1,204 @@
-use super::{api, player_id, QueryData, Result};
+use super::{
+    api, job_runner::Submission, player_id, BenchmarkId, BenchmarkerError, CompressedSolutionData,
+    QueryData, Result, SubmittedRegistry,
+};
 use crate::future_utils::{join, Mutex};
+use linked_hash_map::LinkedHashMap;
 use once_cell::sync::OnceCell;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
 use tig_api::*;
 use tig_structs::core::*;
 
 static CACHE: OnceCell<Mutex<HashMap<String, QueryData>>> = OnceCell::new();
 
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    schema_version: u32,
+}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    schema_version: u32,
+    query_data: &'a QueryData,
+    pending_submissions: &'a [Submission],
+    submitted: &'a SubmittedRegistry,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    query_data: QueryData,
+    #[serde(default)]
+    pending_submissions: Vec<Submission>,
+    #[serde(default)]
+    submitted: SubmittedRegistry,
+}
+
+// writes to a sibling .tmp file then renames it over the snapshot, so an interrupted save
+// leaves the previous snapshot intact. the parent directory is synced too, as the rename is
+// not durable until it is
+pub fn write_snapshot(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut file = File::create(&tmp_path).map_err(BenchmarkerError::SnapshotIo)?;
+    file.write_all(data).map_err(BenchmarkerError::SnapshotIo)?;
+    file.sync_all().map_err(BenchmarkerError::SnapshotIo)?;
+    fs::rename(&tmp_path, path).map_err(BenchmarkerError::SnapshotIo)?;
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(BenchmarkerError::SnapshotIo)?;
+    }
+    Ok(())
+}
+
+impl QueryData {
+    // the in-memory half of a save, done while the state is locked. `write_snapshot` does the
+    // rest without it
+    pub fn serialize_snapshot(
+        &self,
+        pending_submissions: &[Submission],
+        submitted: &SubmittedRegistry,
+    ) -> Result<Vec<u8>> {
+        serde_json::to_vec(&SnapshotRef {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            query_data: self,
+            pending_submissions,
+            submitted,
+        })
+        .map_err(BenchmarkerError::SnapshotSerde)
+    }
+
+    pub fn load_from_disk(
+        path: &Path,
+    ) -> Result<Option<(Self, Vec<Submission>, SubmittedRegistry)>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(path).map_err(BenchmarkerError::SnapshotIo)?;
+        let SnapshotHeader { schema_version } =
+            serde_json::from_slice(&data).map_err(BenchmarkerError::SnapshotSerde)?;
+        if schema_version != SNAPSHOT_SCHEMA_VERSION {
+            return Err(BenchmarkerError::SnapshotVersionMismatch {
+                found: schema_version,
+                expected: SNAPSHOT_SCHEMA_VERSION,
+            });
+        }
+        let Snapshot {
+            query_data,
+            pending_submissions,
+            submitted,
+        } = serde_json::from_slice(&data).map_err(BenchmarkerError::SnapshotSerde)?;
+        Ok(Some((query_data, pending_submissions, submitted)))
+    }
+
+    // keeps saved benchmarks (and their proofs) whose solutions_meta_data is already populated
+    pub fn restore_from(&mut self, mut saved: QueryData) {
+        for (benchmark_id, benchmark) in saved.benchmarks.drain() {
+            if !benchmark
+                .solutions_meta_data
+                .as_ref()
+                .is_some_and(|x| !x.is_empty())
+            {
+                continue;
+            }
+            if let Some(mut proof) = saved.proofs.remove(&benchmark_id) {
+                // snapshots from before solutions data moved out of `proofs`
+                if let Some(solutions_data) = proof.solutions_data.take() {
+                    let mut data = CompressedSolutionData::new(CompressionConfig::default());
+                    data.extend(solutions_data.iter());
+                    saved
+                        .solutions_data
+                        .entry(benchmark_id.clone())
+                        .or_insert(data);
+                }
+                self.proofs.insert(benchmark_id.clone(), proof);
+            }
+            if let Some(solutions_data) = saved.solutions_data.remove(&benchmark_id) {
+                self.solutions_data
+                    .insert(benchmark_id.clone(), solutions_data);
+            }
+            self.benchmarks.insert(benchmark_id, benchmark);
+        }
+    }
+
+    // marks `benchmark_id` as the most recently used
+    pub fn touch(&mut self, benchmark_id: &BenchmarkId) {
+        if self.recency.get_refresh(benchmark_id).is_none() {
+            self.recency.insert(benchmark_id.clone(), ());
+        }
+    }
+
+    // evicts the least recently used benchmarks, along with their proofs, frauds and solutions
+    // data, until `benchmarks` and `proofs` hold at most `max_entries` between them. only ids in
+    // `submitted` are evicted and those in `in_use` are skipped. returns the evicted ids
+    pub fn evict_completed(
+        &mut self,
+        max_entries: usize,
+        submitted: &SubmittedRegistry,
+        in_use: &HashSet<BenchmarkId>,
+    ) -> Vec<BenchmarkId> {
+        // entries added without a `touch` (e.g. queried from the api) count as just used
+        let ids: HashSet<BenchmarkId> = self
+            .benchmarks
+            .keys()
+            .chain(self.proofs.keys())
+            .cloned()
+            .collect();
+        let stale: Vec<BenchmarkId> = self
+            .recency
+            .keys()
+            .filter(|id| !ids.contains(*id))
+            .cloned()
+            .collect();
+        for id in stale {
+            self.recency.remove(&id);
+        }
+        for id in ids {
+            if !self.recency.contains_key(&id) {
+                self.recency.insert(id, ());
+            }
+        }
+        let mut num_entries = self.benchmarks.len() + self.proofs.len();
+        let candidates: Vec<BenchmarkId> = self
+            .recency
+            .keys()
+            .filter(|id| submitted.contains(id.as_str()) && !in_use.contains(*id))
+            .cloned()
+            .collect();
+        let mut evicted = Vec::new();
+        for id in candidates {
+            if num_entries <= max_entries {
+                break;
+            }
+            num_entries -= self.benchmarks.remove(&id).is_some() as usize;
+            num_entries -= self.proofs.remove(&id).is_some() as usize;
+            self.frauds.remove(&id);
+            self.solutions_data.remove(&id);
+            self.recency.remove(&id);
+            evicted.push(id);
+        }
+        evicted
+    }
+}
+
 pub async fn execute() -> Result<QueryData> {
     let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
     let latest_block = query_latest_block().await?;
     let latest_block_id = latest_block.id.clone();
+    // held across the queries on purpose, so concurrent callers wait for one set of queries
+    // instead of each sending their own. nothing below locks `state()`
     let mut cache = cache.lock().await;
     if !cache.contains_key(&latest_block.id) {
         cache.clear();
@@ -20,7 +208,8 @@ pub async fn execute() -> Result<QueryData> {
             query_benchmarks(latest_block.id.clone()),
             query_challenges(latest_block.id.clone()),
         )
-        .await?;
+        .await
+        .map_err(BenchmarkerError::JoinFailed)?;
         let (algorithms_by_challenge, download_urls) = results.0?;
         let player_data = results.1?;
         let (benchmarks, proofs, frauds) = results.2?;
@@ -45,6 +234,8 @@ pub async fn execute() -> Result<QueryData> {
                 proofs,
                 frauds,
                 challenges,
+                solutions_data: HashMap::new(),
+                recency: LinkedHashMap::new(),
             },
         );
     }
@@ -52,24 +243,29 @@ pub async fn execute() -> Result<QueryData> {
 }
 
 pub async fn query_latest_block() -> Result<Block> {
-    let GetBlockResp { block, .. } = api()
+    query_latest_block_from(api()).await
+}
+
+pub async fn query_latest_block_from(api: &dyn BenchmarkApi) -> Result<Block> {
+    let GetBlockResp { block, .. } = api
         .get_block(GetBlockReq {
             id: None,
             round: None,
             height: None,
             include_data: false,
         })
-        .await
-        .map_err(|e| format!("Failed to query latest block: {:?}", e))?;
-    Ok(block.ok_or_else(|| format!("Expecting latest block to exist"))?)
+        .await?;
+    Ok(block.ok_or_else(|| BenchmarkerError::MissingData {
+        what: "latest block",
+    })?)
 }
 
 async fn query_benchmarks(
     block_id: String,
 ) -> Result<(
-    HashMap<String, Benchmark>,
-    HashMap<String, Proof>,
-    HashMap<String, Fraud>,
+    HashMap<BenchmarkId, Benchmark>,
+    HashMap<BenchmarkId, Proof>,
+    HashMap<BenchmarkId, Fraud>,
 )> {
     let GetBenchmarksResp {
         benchmarks,
@@ -81,17 +277,19 @@ async fn query_benchmarks(
             block_id: block_id.clone(),
             player_id: player_id().clone(),
         })
-        .await
-        .map_err(|e| format!("Failed to get benchmarks: {:?}", e))?;
+        .await?;
     Ok((
-        benchmarks.into_iter().map(|x| (x.id.clone(), x)).collect(),
+        benchmarks
+            .into_iter()
+            .map(|x| (x.id.clone().into(), x))
+            .collect(),
         proofs
             .into_iter()
-            .map(|x| (x.benchmark_id.clone(), x))
+            .map(|x| (x.benchmark_id.clone().into(), x))
             .collect(),
         frauds
             .into_iter()
-            .map(|x| (x.benchmark_id.clone(), x))
+            .map(|x| (x.benchmark_id.clone().into(), x))
             .collect(),
     ))
 }
@@ -102,15 +300,14 @@ async fn query_player_data(block_id: String) -> Result<Option<PlayerBlockData>>
             block_id: block_id.clone(),
             player_type: PlayerType::Benchmarker,
         })
-        .await
-        .map_err(|e| format!("Failed to query players: {:?}", e))?;
+        .await?;
     let player_id = player_id().clone();
     match players.into_iter().find(|x| x.id == player_id) {
-        Some(player) => {
-            Ok(Some(player.block_data.ok_or_else(|| {
-                format!("Expecting player to have block_data")
-            })?))
-        }
+        Some(player) => Ok(Some(player.block_data.ok_or_else(|| {
+            BenchmarkerError::MissingData {
+                what: "player block_data",
+            }
+        })?)),
         None => Ok(None),
     }
 }
@@ -120,8 +317,7 @@ async fn query_challenges(block_id: String) -> Result<Vec<Challenge>> {
         .get_challenges(GetChallengesReq {
             block_id: block_id.clone(),
         })
-        .await
-        .map_err(|e| format!("Failed to query challenges: {:?}", e))?;
+        .await?;
     Ok(challenges)
 }
 
@@ -134,8 +330,7 @@ async fn query_algorithms(
         .get_algorithms(GetAlgorithmsReq {
             block_id: block_id.clone(),
         })
-        .await
-        .map_err(|e| format!("Failed to query algorithms: {:?}", e))?;
+        .await?;
     let algorithms_by_challenge: HashMap<String, Vec<Algorithm>> =
         algorithms.into_iter().fold(HashMap::new(), |mut acc, x| {
             acc.entry(x.details.challenge_id.clone())
@@ -150,3 +345,214 @@ async fn query_algorithms(
         .collect();
     Ok((algorithms_by_challenge, download_urls))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "standalone")]
+    use crate::benchmarker::test_utils;
+    use crate::future_utils::time;
+    use std::path::PathBuf;
+
+    fn query_data() -> QueryData {
+        QueryData {
+            latest_block: Block {
+                id: String::new(),
+                details: BlockDetails {
+                    prev_block_id: String::new(),
+                    height: 0,
+                    round: 0,
+                    eth_block_num: None,
+                },
+                data: None,
+                config: None,
+            },
+            player_data: None,
+            challenges: Vec::new(),
+            download_urls: HashMap::new(),
+            algorithms_by_challenge: HashMap::new(),
+            benchmarks: HashMap::new(),
+            proofs: HashMap::new(),
+            frauds: HashMap::new(),
+            solutions_data: HashMap::new(),
+            recency: LinkedHashMap::new(),
+        }
+    }
+
+    fn add_benchmark(query_data: &mut QueryData, benchmark_id: &str) {
+        query_data.benchmarks.insert(
+            benchmark_id.into(),
+            Benchmark {
+                id: benchmark_id.to_string(),
+                settings: BenchmarkSettings {
+                    player_id: "0x0000000000000000000000000000000000000000".to_string(),
+                    block_id: "0".repeat(32),
+                    challenge_id: "c001".to_string(),
+                    algorithm_id: "c001_a001".to_string(),
+                    difficulty: vec![50, 300],
+                },
+                details: BenchmarkDetails {
+                    block_started: 0,
+                    num_solutions: 1,
+                },
+                state: None,
+                solutions_meta_data: Some(vec![]),
+                solution_data: None,
+            },
+        );
+        query_data.proofs.insert(
+            benchmark_id.into(),
+            Proof {
+                benchmark_id: benchmark_id.to_string(),
+                state: None,
+                solutions_data: None,
+            },
+        );
+        query_data.touch(&benchmark_id.into());
+    }
+
+    fn entries_size(query_data: &QueryData) -> usize {
+        query_data
+            .benchmarks
+            .values()
+            .map(|b| serde_json::to_vec(b).unwrap().len())
+            .chain(
+                query_data
+                    .proofs
+                    .values()
+                    .map(|p| serde_json::to_vec(p).unwrap().len()),
+            )
+            .sum()
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut data = query_data();
+        let mut submitted = SubmittedRegistry::default();
+        for id in ["a", "b", "c"] {
+            add_benchmark(&mut data, id);
+            submitted.insert(id.to_string());
+        }
+        data.touch(&"a".into());
+        let evicted = data.evict_completed(4, &submitted, &HashSet::new());
+        assert_eq!(evicted, vec!["b"]);
+        assert!(data.benchmarks.contains_key("a"));
+    }
+
+    #[test]
+    fn test_skips_in_use_and_unsubmitted() {
+        let mut data = query_data();
+        let mut submitted = SubmittedRegistry::default();
+        for id in ["a", "b", "c", "d"] {
+            add_benchmark(&mut data, id);
+        }
+        for id in ["a", "b", "d"] {
+            submitted.insert(id.to_string());
+        }
+        let in_use = HashSet::from([BenchmarkId::new("a")]);
+        let evicted = data.evict_completed(4, &submitted, &in_use);
+        assert_eq!(evicted, vec!["b", "d"]);
+        assert!(data.benchmarks.contains_key("a"));
+        assert!(data.benchmarks.contains_key("c"));
+    }
+
+    #[test]
+    fn test_memory_is_bounded() {
+        let max_entries = 10;
+        let mut sample = query_data();
+        add_benchmark(&mut sample, &format!("{:032x}", u64::MAX));
+        let average_entry_size_estimate = entries_size(&sample) as f64 / 2.0;
+        let mut data = query_data();
+        let mut submitted = SubmittedRegistry::default();
+        for i in 0..1000u64 {
+            let id = format!("{:032x}", i);
+            add_benchmark(&mut data, &id);
+            submitted.insert(id);
+            data.evict_completed(max_entries, &submitted, &HashSet::new());
+            assert!(data.benchmarks.len() + data.proofs.len() <= max_entries);
+            assert!(entries_size(&data) as f64 <= max_entries as f64 * average_entry_size_estimate);
+        }
+    }
+
+    fn snapshot_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tig-snapshot-{}-{}", name, time()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("snapshot.json")
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let path = snapshot_path("round-trip");
+        let mut data = query_data();
+        add_benchmark(&mut data, "a");
+        let mut submitted = SubmittedRegistry::default();
+        submitted.insert("a");
+        let pending = vec![Submission::Proof {
+            benchmark_id: "a".into(),
+            solutions_data: Vec::new(),
+        }];
+        let snapshot = data.serialize_snapshot(&pending, &submitted).unwrap();
+        write_snapshot(&path, &snapshot).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+        let (loaded, loaded_pending, loaded_submitted) =
+            QueryData::load_from_disk(&path).unwrap().unwrap();
+        assert!(loaded.benchmarks.contains_key("a"));
+        assert!(loaded.proofs.contains_key("a"));
+        assert_eq!(loaded_pending.len(), 1);
+        assert_eq!(loaded_pending[0].benchmark_id(), &BenchmarkId::from("a"));
+        assert_eq!(loaded_submitted, submitted);
+    }
+
+    #[test]
+    fn test_corrupt_snapshot_is_an_error() {
+        let path = snapshot_path("corrupt");
+        assert!(QueryData::load_from_disk(&path).unwrap().is_none());
+        fs::write(&path, "{\"schema_version\": 1, \"query_data\": {").unwrap();
+        assert!(matches!(
+            QueryData::load_from_disk(&path),
+            Err(BenchmarkerError::SnapshotSerde(_))
+        ));
+        fs::write(&path, "{\"schema_version\": 0}").unwrap();
+        assert!(matches!(
+            QueryData::load_from_disk(&path),
+            Err(BenchmarkerError::SnapshotVersionMismatch {
+                found: 0,
+                expected: SNAPSHOT_SCHEMA_VERSION,
+            })
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_latest_block() {
+        let ctx = test_utils::setup().await;
+        test_utils::push_block(&ctx.api, 7);
+        assert_eq!(query_latest_block().await.unwrap().details.height, 7);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_failed_latest_block_query_is_an_api_error() {
+        let ctx = test_utils::setup().await;
+        ctx.api
+            .push_error("get_block", "Request error (status: 503, body: busy)");
+        assert!(matches!(
+            query_latest_block().await,
+            Err(BenchmarkerError::ApiError { .. })
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_response_without_block_is_missing_data() {
+        let ctx = test_utils::setup().await;
+        ctx.api
+            .push_response("get_block", &GetBlockResp { block: None });
+        assert!(matches!(
+            query_latest_block().await,
+            Err(BenchmarkerError::MissingData {
+                what: "latest block"
+            })
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+use super::{BenchmarkId, QueryData};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// what changed in `QueryData` between two polls. ids are sorted so the changelog is stable
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct QueryDataDiff {
+    pub added_benchmarks: Vec<BenchmarkId>,
+    /// dropped for being outside the lifespan period or evicted by `storage.max_entries`
+    pub removed_benchmarks: Vec<BenchmarkId>,
+    pub new_proofs: Vec<BenchmarkId>,
+    /// benchmarks whose proof got confirmed or that got flagged as fraud
+    pub completed_jobs: Vec<BenchmarkId>,
+}
+
+impl QueryDataDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_benchmarks.is_empty()
+            && self.removed_benchmarks.is_empty()
+            && self.new_proofs.is_empty()
+            && self.completed_jobs.is_empty()
+    }
+}
+
+pub fn diff(before: &QueryData, after: &QueryData) -> QueryDataDiff {
+    let is_complete = |data: &QueryData, id: &BenchmarkId| {
+        data.frauds.contains_key(id)
+            || data.proofs.get(id).is_some_and(|p| {
+                p.state
+                    .as_ref()
+                    .is_some_and(|s| s.block_confirmed.is_some())
+            })
+    };
+    QueryDataDiff {
+        added_benchmarks: new_keys(&before.benchmarks, &after.benchmarks),
+        removed_benchmarks: new_keys(&after.benchmarks, &before.benchmarks),
+        new_proofs: new_keys(&before.proofs, &after.proofs),
+        completed_jobs: sorted(
+            after
+                .benchmarks
+                .keys()
+                .chain(after.proofs.keys())
+                .filter(|id| is_complete(after, id) && !is_complete(before, id))
+                .cloned(),
+        ),
+    }
+}
+
+// keys of `after` missing from `before`
+fn new_keys<T>(
+    before: &HashMap<BenchmarkId, T>,
+    after: &HashMap<BenchmarkId, T>,
+) -> Vec<BenchmarkId> {
+    sorted(after.keys().filter(|id| !before.contains_key(*id)).cloned())
+}
+
+fn sorted(ids: impl Iterator<Item = BenchmarkId>) -> Vec<BenchmarkId> {
+    let mut ids: Vec<BenchmarkId> = ids.collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linked_hash_map::LinkedHashMap;
+    use tig_structs::core::*;
+
+    fn query_data(benchmark_ids: &[&str]) -> QueryData {
+        let mut data = QueryData {
+            latest_block: Block {
+                id: String::new(),
+                details: BlockDetails {
+                    prev_block_id: String::new(),
+                    height: 0,
+                    round: 0,
+                    eth_block_num: None,
+                },
+                data: None,
+                config: None,
+            },
+            player_data: None,
+            challenges: Vec::new(),
+            download_urls: HashMap::new(),
+            algorithms_by_challenge: HashMap::new(),
+            benchmarks: HashMap::new(),
+            proofs: HashMap::new(),
+            frauds: HashMap::new(),
+            solutions_data: HashMap::new(),
+            recency: LinkedHashMap::new(),
+        };
+        for id in benchmark_ids {
+            data.benchmarks.insert(
+                (*id).into(),
+                Benchmark {
+                    id: id.to_string(),
+                    settings: BenchmarkSettings {
+                        player_id: String::new(),
+                        block_id: String::new(),
+                        challenge_id: "c001".to_string(),
+                        algorithm_id: "c001_a001".to_string(),
+                        difficulty: vec![50, 300],
+                    },
+                    details: BenchmarkDetails {
+                        block_started: 0,
+                        num_solutions: 1,
+                    },
+                    state: None,
+                    solutions_meta_data: None,
+                    solution_data: None,
+                },
+            );
+        }
+        data
+    }
+
+    fn add_proof(data: &mut QueryData, benchmark_id: &str, block_confirmed: Option<u32>) {
+        data.proofs.insert(
+            benchmark_id.into(),
+            Proof {
+                benchmark_id: benchmark_id.to_string(),
+                state: Some(ProofState {
+                    block_confirmed,
+                    submission_delay: None,
+                }),
+                solutions_data: None,
+            },
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut before = query_data(&["a", "b", "c"]);
+        add_proof(&mut before, "b", None);
+        let mut after = query_data(&["b", "c", "e", "d"]);
+        add_proof(&mut after, "b", Some(10));
+        add_proof(&mut after, "c", None);
+        after.frauds.insert(
+            "e".into(),
+            Fraud {
+                benchmark_id: "e".to_string(),
+                state: None,
+                allegation: None,
+            },
+        );
+        assert_eq!(
+            diff(&before, &after),
+            QueryDataDiff {
+                added_benchmarks: vec!["d".into(), "e".into()],
+                removed_benchmarks: vec!["a".into()],
+                new_proofs: vec!["c".into()],
+                completed_jobs: vec!["b".into(), "e".into()],
+            }
+        );
+        assert!(diff(&after, &after).is_empty());
+    }
+}
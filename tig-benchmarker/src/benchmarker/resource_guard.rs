@@ -0,0 +1,199 @@
+use super::ResourcesConfig;
+use crate::future_utils::{sleep, spawn};
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+use sysinfo::System;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+static GATE: OnceCell<Arc<Semaphore>> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    pub free_memory_mb: u64,
+    /// across all cores, from 0 to 100
+    pub cpu_percent: f32,
+}
+
+pub trait ResourceProbe: Send {
+    fn sample(&mut self) -> ResourceUsage;
+}
+
+// reads /proc/meminfo and /proc/stat on linux, and the platform equivalents elsewhere
+pub struct SystemProbe {
+    system: System,
+}
+
+impl SystemProbe {
+    pub fn new() -> Self {
+        let mut system = System::new();
+        // cpu usage is measured between two refreshes, so the first sample needs a baseline
+        system.refresh_cpu_usage();
+        Self { system }
+    }
+}
+
+impl Default for SystemProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceProbe for SystemProbe {
+    fn sample(&mut self) -> ResourceUsage {
+        self.system.refresh_memory();
+        self.system.refresh_cpu_usage();
+        ResourceUsage {
+            free_memory_mb: self.system.available_memory() / (1024 * 1024),
+            cpu_percent: self.system.global_cpu_info().cpu_usage(),
+        }
+    }
+}
+
+// holds the only permit of its semaphore while usage exceeds a threshold, so the job runner
+// cannot acquire one and starts no new submissions. in-flight ones carry on
+pub struct ResourceGuard {
+    config: ResourcesConfig,
+    semaphore: Arc<Semaphore>,
+    paused: bool,
+}
+
+impl ResourceGuard {
+    pub fn new(config: ResourcesConfig) -> Self {
+        Self {
+            config,
+            semaphore: Arc::new(Semaphore::new(1)),
+            paused: false,
+        }
+    }
+
+    pub fn semaphore(&self) -> Arc<Semaphore> {
+        self.semaphore.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn is_exceeded(&self, usage: &ResourceUsage) -> bool {
+        usage.free_memory_mb < self.config.min_free_memory_mb
+            || usage.cpu_percent > self.config.max_cpu_percent
+    }
+
+    pub async fn poll(&mut self, probe: &mut dyn ResourceProbe) {
+        let usage = probe.sample();
+        let exceeded = self.is_exceeded(&usage);
+        if exceeded && !self.paused {
+            self.semaphore
+                .acquire()
+                .await
+                .expect("resource guard semaphore is never closed")
+                .forget();
+            self.paused = true;
+            warn!(
+                free_memory_mb = usage.free_memory_mb,
+                cpu_percent = usage.cpu_percent,
+                "Low on resources. Pausing submissions"
+            );
+        } else if !exceeded && self.paused {
+            self.semaphore.add_permits(1);
+            self.paused = false;
+            info!(
+                free_memory_mb = usage.free_memory_mb,
+                cpu_percent = usage.cpu_percent,
+                "Resources recovered. Resuming submissions"
+            );
+        }
+    }
+
+    pub async fn run(mut self, mut probe: impl ResourceProbe) {
+        loop {
+            self.poll(&mut probe).await;
+            sleep(self.config.poll_interval_ms.min(u32::MAX as u64) as u32).await;
+        }
+    }
+}
+
+// does nothing unless `resources.enabled`
+pub fn start(config: &ResourcesConfig) {
+    if !config.enabled {
+        return;
+    }
+    let guard = ResourceGuard::new(config.clone());
+    if GATE.set(guard.semaphore()).is_ok() {
+        spawn(guard.run(SystemProbe::new()));
+    }
+}
+
+// false while the guard has paused submissions. the permit is only checked, not kept
+pub fn permits_submission() -> bool {
+    GATE.get().map_or(true, |gate| gate.try_acquire().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    struct MockProbe {
+        samples: VecDeque<ResourceUsage>,
+    }
+
+    impl ResourceProbe for MockProbe {
+        fn sample(&mut self) -> ResourceUsage {
+            self.samples.pop_front().unwrap()
+        }
+    }
+
+    fn usage(free_memory_mb: u64, cpu_percent: f32) -> ResourceUsage {
+        ResourceUsage {
+            free_memory_mb,
+            cpu_percent,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pauses_and_resumes() {
+        let mut guard = ResourceGuard::new(ResourcesConfig {
+            min_free_memory_mb: 512,
+            max_cpu_percent: 90.0,
+            ..Default::default()
+        });
+        let semaphore = guard.semaphore();
+        let mut probe = MockProbe {
+            samples: VecDeque::from(vec![
+                usage(1024, 10.0),
+                usage(256, 10.0),
+                usage(1024, 95.0),
+                usage(1024, 10.0),
+            ]),
+        };
+        let mut available = Vec::new();
+        for _ in 0..4 {
+            guard.poll(&mut probe).await;
+            available.push(semaphore.available_permits());
+        }
+        assert_eq!(available, vec![1, 0, 0, 1]);
+        assert!(!guard.is_paused());
+        assert!(semaphore.try_acquire().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_a_permit_in_use() {
+        let mut guard = ResourceGuard::new(ResourcesConfig::default());
+        let semaphore = guard.semaphore();
+        let permit = semaphore.clone().try_acquire_owned().unwrap();
+        let mut probe = MockProbe {
+            samples: VecDeque::from(vec![usage(0, 0.0)]),
+        };
+        let poll = tokio::spawn(async move {
+            guard.poll(&mut probe).await;
+            guard
+        });
+        tokio::task::yield_now().await;
+        drop(permit);
+        let guard = poll.await.unwrap();
+        assert!(guard.is_paused());
+        assert!(semaphore.try_acquire().is_err());
+    }
+}
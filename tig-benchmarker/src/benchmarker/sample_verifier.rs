@@ -0,0 +1,138 @@
+use super::VerificationError;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tig_structs::core::{SolutionData, SolutionMetaData};
+
+// regenerates a random sample of a benchmark's solutions and checks each against the
+// solution_signature stored for it, so systematic errors show up before the full submission
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleVerifier {
+    sample_fraction: f64,
+    seed: u64,
+}
+
+impl SampleVerifier {
+    pub fn new(sample_fraction: f64, seed: u64) -> Self {
+        Self {
+            sample_fraction: sample_fraction.clamp(0.0, 1.0),
+            seed,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.sample_fraction > 0.0
+    }
+
+    // at least one index whenever sampling is enabled. the same seed and length always give
+    // the same indices, in ascending order
+    pub fn sample_indices(&self, len: usize) -> Vec<usize> {
+        if !self.is_enabled() || len == 0 {
+            return Vec::new();
+        }
+        let num_samples = ((len as f64 * self.sample_fraction).ceil() as usize).clamp(1, len);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut indices: Vec<usize> = (0..len).collect();
+        // partial fisher-yates
+        for i in 0..num_samples {
+            let j = rng.gen_range(i..len);
+            indices.swap(i, j);
+        }
+        indices.truncate(num_samples);
+        indices.sort_unstable();
+        indices
+    }
+
+    // `regenerate` recomputes the solution for a nonce, returning None if none is found
+    pub fn verify<F>(
+        &self,
+        solutions_meta_data: &[SolutionMetaData],
+        mut regenerate: F,
+    ) -> Result<(), VerificationError>
+    where
+        F: FnMut(u64) -> Option<SolutionData>,
+    {
+        for idx in self.sample_indices(solutions_meta_data.len()) {
+            let expected = &solutions_meta_data[idx];
+            let matches = regenerate(expected.nonce).is_some_and(|solution_data| {
+                solution_data.nonce == expected.nonce
+                    && solution_data.calc_solution_signature() == expected.solution_signature
+            });
+            if !matches {
+                return Err(VerificationError::SampledSolutionMismatch {
+                    nonce: expected.nonce,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tig_structs::core::Solution;
+
+    fn solution_data(nonce: u64) -> SolutionData {
+        let mut solution = Solution::new();
+        solution.insert("items".to_string(), json!([nonce, nonce + 1]));
+        SolutionData {
+            nonce,
+            runtime_signature: 1234567,
+            fuel_consumed: 987654321,
+            solution,
+        }
+    }
+
+    fn solutions_meta_data(num_solutions: u64) -> Vec<SolutionMetaData> {
+        (0..num_solutions)
+            .map(|nonce| SolutionMetaData::from(solution_data(nonce)))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_is_reproducible() {
+        let verifier = SampleVerifier::new(0.05, 42);
+        let indices = verifier.sample_indices(200);
+        assert_eq!(indices.len(), 10);
+        assert_eq!(indices, verifier.sample_indices(200));
+        assert_ne!(indices, SampleVerifier::new(0.05, 43).sample_indices(200));
+        assert_eq!(SampleVerifier::new(0.05, 42).sample_indices(3).len(), 1);
+        assert!(SampleVerifier::new(0.0, 42).sample_indices(200).is_empty());
+    }
+
+    #[test]
+    fn test_verify() {
+        let meta_data = solutions_meta_data(100);
+        let verifier = SampleVerifier::new(0.1, 7);
+        let mut regenerated = Vec::new();
+        assert_eq!(
+            verifier.verify(&meta_data, |nonce| {
+                regenerated.push(nonce);
+                Some(solution_data(nonce))
+            }),
+            Ok(())
+        );
+        assert_eq!(regenerated.len(), 10);
+    }
+
+    #[test]
+    fn test_verify_detects_mismatch() {
+        let meta_data = solutions_meta_data(100);
+        let verifier = SampleVerifier::new(1.0, 7);
+        let result = verifier.verify(&meta_data, |nonce| {
+            let mut data = solution_data(nonce);
+            if nonce == 42 {
+                data.solution.insert("items".to_string(), json!([]));
+            }
+            Some(data)
+        });
+        assert_eq!(
+            result,
+            Err(VerificationError::SampledSolutionMismatch { nonce: 42 })
+        );
+        assert_eq!(
+            verifier.verify(&meta_data, |_| None),
+            Err(VerificationError::SampledSolutionMismatch { nonce: 0 })
+        );
+    }
+}
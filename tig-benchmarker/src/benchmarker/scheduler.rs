@@ -0,0 +1,283 @@
+use super::{Job, QueryData};
+use serde::{Serialize, Serializer};
+use std::{cmp::Ordering, collections::BinaryHeap, sync::Arc};
+
+pub type PriorityFn<C = QueryData> = Arc<dyn Fn(&Job, &C) -> u64 + Send + Sync>;
+
+// expected reward is approximated by the number of solutions, weighted by how many blocks the
+// benchmark has left before it can no longer be submitted
+pub fn default_priority(job: &Job, query_data: &QueryData) -> u64 {
+    let num_solutions = query_data
+        .benchmarks
+        .get(&job.benchmark_id)
+        .map(|b| b.details.num_solutions)
+        .unwrap_or(0);
+    let lifespan_period = query_data
+        .latest_block
+        .config()
+        .benchmark_submissions
+        .lifespan_period;
+    let remaining_blocks = query_data
+        .benchmarks
+        .get(&job.benchmark_id)
+        .map(|b| {
+            (b.details.block_started + lifespan_period)
+                .saturating_sub(query_data.latest_block.details.height)
+        })
+        .unwrap_or(0);
+    num_solutions as u64 * remaining_blocks as u64
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PrioritizedJob {
+    pub priority: u64,
+    seq: u64,
+    pub job: Job,
+}
+
+// highest priority first. ties are broken by insertion order
+impl Ord for PrioritizedJob {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+impl PartialOrd for PrioritizedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl PartialEq for PrioritizedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for PrioritizedJob {}
+
+#[derive(Clone)]
+pub struct JobScheduler<C = QueryData> {
+    heap: BinaryHeap<PrioritizedJob>,
+    next_seq: u64,
+    priority_fn: PriorityFn<C>,
+}
+
+impl Default for JobScheduler<QueryData> {
+    fn default() -> Self {
+        Self::new(Arc::new(default_priority))
+    }
+}
+
+impl<C> JobScheduler<C> {
+    pub fn new(priority_fn: PriorityFn<C>) -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            next_seq: 0,
+            priority_fn,
+        }
+    }
+
+    pub fn push(&mut self, job: Job, ctx: &C) {
+        let priority = (self.priority_fn)(&job, ctx);
+        self.heap.push(PrioritizedJob {
+            priority,
+            seq: self.next_seq,
+            job,
+        });
+        self.next_seq += 1;
+    }
+
+    // priorities are recomputed against `ctx` as they change over time (e.g. remaining blocks)
+    pub fn pop(&mut self, ctx: &C) -> Option<Job> {
+        let mut jobs = std::mem::take(&mut self.heap).into_vec();
+        for j in jobs.iter_mut() {
+            j.priority = (self.priority_fn)(&j.job, ctx);
+        }
+        self.heap = BinaryHeap::from(jobs);
+        self.heap.pop().map(|j| j.job)
+    }
+
+    // the job that was pushed first, regardless of priority
+    pub fn pop_oldest(&mut self) -> Option<Job> {
+        let mut jobs = std::mem::take(&mut self.heap).into_vec();
+        let oldest = (0..jobs.len()).min_by_key(|&i| jobs[i].seq);
+        let job = oldest.map(|i| jobs.swap_remove(i).job);
+        self.heap = BinaryHeap::from(jobs);
+        job
+    }
+
+    // removes up to `max` jobs matching `predicate`, highest priority first as of the last push
+    // or pop
+    pub fn take_where(&mut self, max: usize, mut predicate: impl FnMut(&Job) -> bool) -> Vec<Job> {
+        let mut taken = Vec::new();
+        let mut kept = Vec::new();
+        for j in std::mem::take(&mut self.heap)
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+        {
+            if taken.len() < max && predicate(&j.job) {
+                taken.push(j.job);
+            } else {
+                kept.push(j);
+            }
+        }
+        self.heap = BinaryHeap::from(kept);
+        taken
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    // highest priority first, as of the last push or pop
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.sorted().into_iter().map(|j| &j.job)
+    }
+
+    fn sorted(&self) -> Vec<&PrioritizedJob> {
+        let mut jobs: Vec<&PrioritizedJob> = self.heap.iter().collect();
+        jobs.sort_by(|a, b| b.cmp(a));
+        jobs
+    }
+}
+
+impl<C> std::fmt::Debug for JobScheduler<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobScheduler")
+            .field("heap", &self.heap)
+            .field("next_seq", &self.next_seq)
+            .finish()
+    }
+}
+
+impl<C> Serialize for JobScheduler<C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.sorted())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmarker::BenchmarkId;
+    use std::collections::HashMap;
+    use tig_structs::{config::WasmVMConfig, core::BenchmarkSettings};
+
+    fn job(benchmark_id: &str) -> Job {
+        Job {
+            download_url: String::new(),
+            benchmark_id: benchmark_id.into(),
+            settings: BenchmarkSettings {
+                player_id: String::new(),
+                block_id: String::new(),
+                challenge_id: String::new(),
+                algorithm_id: String::new(),
+                difficulty: vec![],
+            },
+            solution_signature_threshold: 0,
+            sampled_nonces: None,
+            wasm_vm_config: WasmVMConfig {
+                max_memory: 0,
+                max_fuel: 0,
+            },
+            signer: None,
+        }
+    }
+
+    fn scheduler() -> JobScheduler<HashMap<String, u64>> {
+        JobScheduler::new(Arc::new(|job: &Job, rewards: &HashMap<String, u64>| {
+            rewards[job.benchmark_id.as_str()]
+        }))
+    }
+
+    #[test]
+    fn test_pops_highest_priority_first() {
+        let rewards: HashMap<String, u64> = [("a", 10), ("b", 30), ("c", 20)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let mut scheduler = scheduler();
+        for id in ["a", "b", "c"] {
+            scheduler.push(job(id), &rewards);
+        }
+        assert_eq!(scheduler.len(), 3);
+        let order: Vec<BenchmarkId> = std::iter::from_fn(|| scheduler.pop(&rewards))
+            .map(|j| j.benchmark_id)
+            .collect();
+        assert_eq!(order, vec!["b", "c", "a"]);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_ties_are_fifo() {
+        let rewards: HashMap<String, u64> = [("a", 5), ("b", 5), ("c", 5)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let mut scheduler = scheduler();
+        for id in ["a", "b", "c"] {
+            scheduler.push(job(id), &rewards);
+        }
+        let order: Vec<BenchmarkId> = std::iter::from_fn(|| scheduler.pop(&rewards))
+            .map(|j| j.benchmark_id)
+            .collect();
+        assert_eq!(order, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_priorities_are_recomputed_on_pop() {
+        let mut rewards: HashMap<String, u64> = [("a", 10), ("b", 30), ("c", 20)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let mut scheduler = scheduler();
+        for id in ["a", "b", "c"] {
+            scheduler.push(job(id), &rewards);
+        }
+        rewards.insert("a".to_string(), 100);
+        assert_eq!(scheduler.pop(&rewards).unwrap().benchmark_id, "a");
+    }
+
+    #[test]
+    fn test_pop_oldest_ignores_priority() {
+        let rewards: HashMap<String, u64> = [("a", 10), ("b", 30), ("c", 20)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let mut scheduler = scheduler();
+        for id in ["a", "b", "c"] {
+            scheduler.push(job(id), &rewards);
+        }
+        assert_eq!(scheduler.pop_oldest().unwrap().benchmark_id, "a");
+        assert_eq!(scheduler.pop(&rewards).unwrap().benchmark_id, "b");
+        assert_eq!(scheduler.pop_oldest().unwrap().benchmark_id, "c");
+        assert!(scheduler.pop_oldest().is_none());
+    }
+
+    #[test]
+    fn test_take_where_keeps_the_rest_in_order() {
+        let rewards: HashMap<String, u64> = [("a", 10), ("b", 30), ("c", 20), ("d", 40)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let mut scheduler = scheduler();
+        for id in ["a", "b", "c", "d"] {
+            scheduler.push(job(id), &rewards);
+        }
+        let taken: Vec<BenchmarkId> = scheduler
+            .take_where(2, |j| j.benchmark_id != "b")
+            .into_iter()
+            .map(|j| j.benchmark_id)
+            .collect();
+        assert_eq!(taken, vec!["d", "c"]);
+        let order: Vec<BenchmarkId> = std::iter::from_fn(|| scheduler.pop(&rewards))
+            .map(|j| j.benchmark_id)
+            .collect();
+        assert_eq!(order, vec!["b", "a"]);
+    }
+}
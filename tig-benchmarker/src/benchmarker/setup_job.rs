@@ -1,4 +1,7 @@
-use super::{player_id, state, Job, QueryData, Result, State};
+use super::{
+    challenge_selector, checkpoint, config, player_id, signer, state, BenchmarkerError,
+    ChallengeConfig, Checkpoint, CompressedSolutionData, Job, QueryData, Result, State,
+};
 use crate::future_utils::time;
 use rand::{
     distributions::{Alphanumeric, DistString, WeightedIndex},
@@ -8,10 +11,14 @@ use rand::{
 use rand_distr::Distribution;
 use std::collections::HashMap;
 use tig_structs::core::*;
+use tracing::{info, warn};
 
-pub async fn execute() -> Result<()> {
+// returns the next nonce of each worker if the job was resumed from a checkpoint
+pub async fn execute() -> Result<Option<Vec<u64>>> {
     let job = if let Some(x) = find_settings_to_recompute().await? {
         x
+    } else if let Some(next_nonces) = resume_from_checkpoint().await {
+        return Ok(Some(next_nonces));
     } else {
         pick_settings_to_benchmark().await?
     };
@@ -21,13 +28,14 @@ pub async fn execute() -> Result<()> {
         latest_block,
         benchmarks,
         proofs,
+        solutions_data,
         ..
     } = &mut state.query_data;
     if job.sampled_nonces.is_none() {
         benchmarks.insert(
             job.benchmark_id.clone(),
             Benchmark {
-                id: job.benchmark_id.clone(),
+                id: job.benchmark_id.to_string(),
                 settings: job.settings.clone(),
                 details: BenchmarkDetails {
                     block_started: latest_block.details.height.clone(),
@@ -42,12 +50,93 @@ pub async fn execute() -> Result<()> {
     proofs.insert(
         job.benchmark_id.clone(),
         Proof {
-            benchmark_id: job.benchmark_id.clone(),
+            benchmark_id: job.benchmark_id.to_string(),
             state: None,
-            solutions_data: Some(Vec::new()),
+            solutions_data: None,
         },
     );
-    Ok(())
+    solutions_data.insert(
+        job.benchmark_id.clone(),
+        CompressedSolutionData::new(config().storage.solutions_data_compression.clone()),
+    );
+    state.query_data.touch(&job.benchmark_id);
+    Ok(None)
+}
+
+// restores the benchmark of a checkpoint found at startup, unless it has left the lifespan
+// period since
+async fn resume_from_checkpoint() -> Option<Vec<u64>> {
+    let mut state = state().lock().await;
+    let State {
+        query_data,
+        checkpoints,
+        job: current_job,
+        ..
+    } = &mut *state;
+    let latest_block = &query_data.latest_block;
+    let block_started_cutoff = latest_block
+        .details
+        .height
+        .saturating_sub(latest_block.config().benchmark_submissions.lifespan_period);
+    while let Some(checkpoint) = checkpoints.pop() {
+        let Checkpoint {
+            job,
+            block_started,
+            next_nonces,
+            solutions_meta_data,
+            solutions_data,
+        } = checkpoint;
+        if block_started < block_started_cutoff {
+            info!(
+                "Discarding checkpoint of expired benchmark {}",
+                job.benchmark_id
+            );
+            remove_checkpoint(job.benchmark_id.as_str());
+            continue;
+        }
+        info!(
+            "Resuming benchmark {} from checkpoint with {} solutions",
+            job.benchmark_id,
+            solutions_meta_data.len()
+        );
+        query_data.benchmarks.insert(
+            job.benchmark_id.clone(),
+            Benchmark {
+                id: job.benchmark_id.to_string(),
+                settings: job.settings.clone(),
+                details: BenchmarkDetails {
+                    block_started,
+                    num_solutions: solutions_meta_data.len() as u32,
+                },
+                state: None,
+                solutions_meta_data: Some(solutions_meta_data),
+                solution_data: None,
+            },
+        );
+        query_data.proofs.insert(
+            job.benchmark_id.clone(),
+            Proof {
+                benchmark_id: job.benchmark_id.to_string(),
+                state: None,
+                solutions_data: None,
+            },
+        );
+        query_data
+            .solutions_data
+            .insert(job.benchmark_id.clone(), solutions_data);
+        query_data.touch(&job.benchmark_id);
+        current_job.replace(job);
+        return Some(next_nonces);
+    }
+    None
+}
+
+pub fn remove_checkpoint(benchmark_id: &str) {
+    if let Some(dir) = config().storage.checkpoint_dir.as_ref() {
+        if let Err(e) = checkpoint::remove(dir, benchmark_id) {
+            warn!("Failed to remove checkpoint of {}: {}", benchmark_id, e);
+        }
+    }
 }
 
 async fn find_settings_to_recompute() -> Result<Option<Job>> {
@@ -65,18 +154,18 @@ async fn find_settings_to_recompute() -> Result<Option<Job>> {
             && benchmark.state.is_some()
         {
             let sampled_nonces = benchmark.state().sampled_nonces.clone().ok_or_else(|| {
-                format!(
-                    "Expecting benchmark '{}' to have sampled_nonces",
-                    benchmark_id
-                )
+                BenchmarkerError::MissingSampledNonces {
+                    benchmark_id: benchmark_id.clone(),
+                }
             })?;
             return Ok(Some(Job {
-                benchmark_id: benchmark.id.clone(),
+                benchmark_id: benchmark.id.clone().into(),
                 download_url: get_download_url(&benchmark.settings.algorithm_id, download_urls)?,
                 settings: benchmark.settings.clone(),
                 solution_signature_threshold: u32::MAX, // is fine unless the player has committed fraud
                 sampled_nonces: Some(sampled_nonces),
                 wasm_vm_config: latest_block.config().wasm_vm.clone(),
+                signer: signer(),
             }));
         }
     }
@@ -99,7 +188,7 @@ async fn pick_settings_to_benchmark() -> Result<Job> {
         ..
     } = query_data;
     let mut rng = StdRng::seed_from_u64(time() as u64);
-    let challenge = pick_challenge(&mut rng, player_data, challenges, selected_algorithms)?;
+    let challenge = pick_challenge(&mut rng, query_data, selected_algorithms)?;
     let selected_algorithm_id = get_algorithm_id(
         algorithms_by_challenge,
         challenge,
@@ -108,7 +197,7 @@ async fn pick_settings_to_benchmark() -> Result<Job> {
     )?;
     let difficulty = difficulty_samplers[&challenge.id].sample(&mut rng);
     Ok(Job {
-        benchmark_id: Alphanumeric.sample_string(&mut rng, 32),
+        benchmark_id: Alphanumeric.sample_string(&mut rng, 32).into(),
         download_url: get_download_url(&selected_algorithm_id, download_urls)?,
         settings: BenchmarkSettings {
             player_id: player_id().clone(),
@@ -120,15 +209,20 @@ async fn pick_settings_to_benchmark() -> Result<Job> {
         solution_signature_threshold: *challenge.block_data().solution_signature_threshold(),
         sampled_nonces: None,
         wasm_vm_config: latest_block.config().wasm_vm.clone(),
+        signer: signer(),
     })
 }
 
 fn pick_challenge<'a>(
     rng: &mut StdRng,
-    player_data: &'a Option<PlayerBlockData>,
-    challenges: &'a Vec<Challenge>,
+    query_data: &'a QueryData,
     selected_algorithms: &HashMap<String, String>,
 ) -> Result<&'a Challenge> {
+    let QueryData {
+        player_data,
+        challenges,
+        ..
+    } = query_data;
     let num_qualifiers_by_challenge = match player_data
         .as_ref()
         .map(|x| x.num_qualifiers_by_challenge.as_ref())
@@ -154,39 +248,41 @@ fn pick_challenge<'a>(
         })
         .collect();
     if selected_algorithms.len() == 0 {
-        return Err("Your <algorithm_selection>.json is empty".to_string());
+        return Err(BenchmarkerError::EmptyAlgorithmSelection);
     };
-    let mut challenge_weights = Vec::<(String, f64)>::new();
+    let mut available = Vec::<ChallengeConfig>::new();
     for challenge_name in selected_algorithms.keys() {
         let challenge_id = challenge_name_2_id.get(challenge_name).ok_or_else(|| {
-            format!(
-                "Your <algorithm_selection>.json contains a non-existent challenge '{}'",
-                challenge_name
-            )
+            BenchmarkerError::InvalidChallengeSelection {
+                challenge_name: challenge_name.clone(),
+            }
         })?;
         let max_percent_qualifiers = *percent_qualifiers_by_challenge
             .values()
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap();
-        challenge_weights.push((
-            challenge_id.clone(),
-            4.0 * max_percent_qualifiers / 3.0 - percent_qualifiers_by_challenge[challenge_id]
+        available.push(ChallengeConfig {
+            challenge_id: challenge_id.clone(),
+            challenge_name: challenge_name.clone(),
+            fee: 4.0 * max_percent_qualifiers / 3.0 - percent_qualifiers_by_challenge[challenge_id]
                 + 1e-10f64,
-        ));
+        });
+    }
+    let selected = challenge_selector().select(&available, query_data);
+    if selected.is_empty() {
+        return Err(BenchmarkerError::NoChallengesSelected);
     }
-    let dist = WeightedIndex::new(
-        &challenge_weights
-            .iter()
-            .map(|w| w.1.clone())
-            .collect::<Vec<f64>>(),
-    )
-    .map_err(|e| format!("Failed to create WeightedIndex: {}", e))?;
+    let dist = WeightedIndex::new(&selected.iter().map(|c| c.fee).collect::<Vec<f64>>())
+        .map_err(BenchmarkerError::InvalidChallengeWeights)?;
     let index = dist.sample(rng);
-    let random_challenge_id = challenge_weights[index].0.clone();
+    let random_challenge_id = selected[index].challenge_id.clone();
     let challenge = challenges
         .iter()
         .find(|c| c.id == *random_challenge_id)
-        .ok_or_else(|| "Selected challenge should exist")?;
+        .ok_or_else(|| BenchmarkerError::StateNotFound {
+            what: "challenge",
+            id: random_challenge_id.clone(),
+        })?;
     Ok(challenge)
 }
 
@@ -199,11 +295,8 @@ fn get_algorithm_id(
     let selected_algorithm_id = algorithms_by_challenge[&challenge.id]
         .iter()
         .find(|a| download_urls.contains_key(&a.id) && a.details.name == *selected_algorithm_name)
-        .ok_or_else(|| {
-            format!(
-                "Your <algorithm_selection>.json contains a non-existent algorithm '{}'",
-                selected_algorithm_name
-            )
+        .ok_or_else(|| BenchmarkerError::InvalidAlgorithmSelection {
+            algorithm_name: selected_algorithm_name.clone(),
         })?
         .id
         .clone();
@@ -216,6 +309,8 @@ fn get_download_url(
 ) -> Result<String> {
     Ok(download_urls
         .get(algorithm_id)
-        .ok_or_else(|| format!("Algorithm {} does not have wasm download_url", algorithm_id))?
+        .ok_or_else(|| BenchmarkerError::MissingDownloadUrl {
+            algorithm_id: algorithm_id.clone(),
+        })?
         .clone())
 }
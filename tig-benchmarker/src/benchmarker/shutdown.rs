@@ -0,0 +1,53 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+#[derive(Debug)]
+pub struct ShutdownSignal {
+    triggered: AtomicBool,
+}
+
+static SHUTDOWN: ShutdownSignal = ShutdownSignal::new();
+
+pub fn shutdown_signal() -> &'static ShutdownSignal {
+    &SHUTDOWN
+}
+
+impl ShutdownSignal {
+    const fn new() -> Self {
+        Self {
+            triggered: AtomicBool::new(false),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.triggered.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+
+    // the signal is process wide, so a test that triggers it has to undo that for the next
+    #[cfg(test)]
+    pub fn reset(&self) {
+        self.triggered.store(false, Ordering::SeqCst);
+    }
+}
+
+// cancels a single submission, as opposed to `ShutdownSignal` which stops all of them
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use tig_api::SubmitBenchmarkResp;
+
+// what the server made of a submitted benchmark
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SubmissionOutcome {
+    Accepted {
+        benchmark_id: String,
+        reward: Option<u64>,
+    },
+    Fraud {
+        benchmark_id: String,
+        reason: String,
+    },
+}
+
+impl SubmissionOutcome {
+    pub fn benchmark_id(&self) -> &str {
+        match self {
+            SubmissionOutcome::Accepted { benchmark_id, .. }
+            | SubmissionOutcome::Fraud { benchmark_id, .. } => benchmark_id,
+        }
+    }
+}
+
+impl From<SubmitBenchmarkResp> for SubmissionOutcome {
+    fn from(resp: SubmitBenchmarkResp) -> Self {
+        match resp.verified {
+            Ok(()) => SubmissionOutcome::Accepted {
+                benchmark_id: resp.benchmark_id,
+                reward: resp.reward,
+            },
+            Err(reason) => SubmissionOutcome::Fraud {
+                benchmark_id: resp.benchmark_id,
+                reason,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_resp() {
+        let outcome: SubmissionOutcome = SubmitBenchmarkResp {
+            benchmark_id: "a".to_string(),
+            verified: Ok(()),
+            reward: Some(5),
+        }
+        .into();
+        assert_eq!(
+            outcome,
+            SubmissionOutcome::Accepted {
+                benchmark_id: "a".to_string(),
+                reward: Some(5),
+            }
+        );
+        let outcome: SubmissionOutcome = SubmitBenchmarkResp {
+            benchmark_id: "b".to_string(),
+            verified: Err("invalid solution".to_string()),
+            reward: Some(5),
+        }
+        .into();
+        assert_eq!(outcome.benchmark_id(), "b");
+        assert!(matches!(
+            outcome,
+            SubmissionOutcome::Fraud { reason, .. } if reason == "invalid solution"
+        ));
+    }
+}
@@ -1,51 +1,1180 @@
-use super::{api, state, Job, QueryData, Result, utils::handle_submission_error, query_data::query_latest_block};
-use tig_api::SubmitBenchmarkReq;
+use super::metrics::AttemptOutcome;
+use super::timing::{as_us, SubmissionTiming};
+use super::{
+    api,
+    block_height_watcher::{self, check_block_height},
+    download_wasm, record_history, save_snapshot, server_protocol_version,
+    shutdown::{shutdown_signal, CancellationToken},
+    signer, state,
+    utils::{backoff, handle_submission_error, is_payload_too_large, RetryDecision},
+    verify::{verify_proof_locally, VerificationError},
+    BenchmarkerError, CompressedSolutionData, Job, QueryData, Result, SampleVerifier,
+    SubmissionConfig, SubmissionOutcome,
+};
+use crate::future_utils::{sleep, timeout};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tig_api::{
+    BenchmarkApi, Signer, SubmitBenchmarkReq, SubmitBenchmarkResp, Uuid, PROTOCOL_VERSION,
+};
+use tig_proof::{calc_solutions_merkle_root, hash_leaf, verify_solutions_merkle};
+use tig_structs::core::{BenchmarkSettings, SolutionData, SolutionMetaData};
+use tig_utils::jsonify;
+use tig_worker::compute_solution;
+use tracing::{info, info_span, instrument, warn, Instrument};
 
-const MAX_RETRIES: u32 = 3;
+pub const DRY_RUN_BENCHMARK_ID: &str = "dry-run";
 
-pub async fn execute(job: &Job) -> Result<String> {
-    let req = {
-        let QueryData {
-            proofs, benchmarks, ..
-        } = &mut state().lock().await.query_data;
-        let benchmark = benchmarks
+// order of solutions_meta_data in a submitted benchmark, which decides its merkle root
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortStrategy {
+    /// lexicographic on the hash of each entry, so the request does not depend on the order
+    /// solutions were found in
+    #[default]
+    ByHash,
+    /// ascending nonce
+    ByIndex,
+    AsGenerated,
+}
+
+impl SortStrategy {
+    pub fn apply(&self, solutions_meta_data: &mut [SolutionMetaData]) {
+        match self {
+            SortStrategy::ByHash => solutions_meta_data.sort_by_cached_key(hash_leaf),
+            SortStrategy::ByIndex => solutions_meta_data.sort_by_key(|d| d.nonce),
+            SortStrategy::AsGenerated => {}
+        }
+    }
+}
+
+pub struct PreparedSubmission {
+    pub req: SubmitBenchmarkReq,
+    pub block_started: u32,
+    pub lifespan_period: u32,
+    pub signer: Option<Arc<dyn Signer>>,
+}
+
+// which solution's data is sent to prove solutions_meta_data
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SolutionSelector {
+    /// that of the first entry once sorted
+    #[default]
+    First,
+    Last,
+    /// the one computed with the least fuel, see `SolutionData::score`
+    HighestScore,
+    /// a seeded pick, so the same seed picks the same solution
+    Random(u64),
+}
+
+// `solutions` are in solutions_meta_data order
+pub fn select_solution(
+    solutions: &[SolutionData],
+    selector: SolutionSelector,
+) -> Result<&SolutionData> {
+    let selected = match selector {
+        SolutionSelector::First => solutions.first(),
+        SolutionSelector::Last => solutions.last(),
+        // min of the reversed score, so ties go to the earliest entry
+        SolutionSelector::HighestScore => solutions.iter().min_by_key(|d| Reverse(d.score())),
+        SolutionSelector::Random(_) if solutions.is_empty() => None,
+        SolutionSelector::Random(seed) => {
+            solutions.get(StdRng::seed_from_u64(seed).gen_range(0..solutions.len()))
+        }
+    };
+    selected.ok_or(BenchmarkerError::MissingData {
+        what: "solution data",
+    })
+}
+
+// takes the job's solutions_meta_data out of the benchmark state once the request is built, so
+// a failure leaves it in place; caller must hold the state lock
+pub fn prepare_submission(
+    query_data: &mut QueryData,
+    job: &Job,
+    sort_strategy: SortStrategy,
+    selector: SolutionSelector,
+    idempotency_key: Uuid,
+) -> Result<PreparedSubmission> {
+    let QueryData {
+        latest_block,
+        solutions_data,
+        benchmarks,
+        ..
+    } = query_data;
+    let benchmark =
+        benchmarks
             .get_mut(&job.benchmark_id)
-            .ok_or_else(|| format!("Job benchmark should exist"))?;
-        let proof = proofs
+            .ok_or_else(|| BenchmarkerError::StateNotFound {
+                what: "benchmark",
+                id: job.benchmark_id.to_string(),
+            })?;
+    let solutions_data =
+        solutions_data
             .get(&job.benchmark_id)
-            .ok_or_else(|| format!("Job proof should exist"))?;
-        let settings = benchmark.settings.clone();
-        let solutions_meta_data = benchmark.solutions_meta_data.take().unwrap();
-        let solution_data = proof.solutions_data().first().unwrap().clone();
-        SubmitBenchmarkReq {
-            settings,
-            solutions_meta_data,
-            solution_data,
+            .ok_or_else(|| BenchmarkerError::StateNotFound {
+                what: "solutions data",
+                id: job.benchmark_id.to_string(),
+            })?;
+    verify_proof_locally(solutions_data, benchmark)
+        .map_err(BenchmarkerError::LocalVerificationFailed)?;
+    let solutions_meta_data =
+        benchmark
+            .solutions_meta_data
+            .clone()
+            .ok_or_else(|| BenchmarkerError::StateNotFound {
+                what: "solutions meta data",
+                id: job.benchmark_id.to_string(),
+            })?;
+    let req = build_req(
+        &benchmark.settings,
+        solutions_meta_data,
+        solutions_data,
+        sort_strategy,
+        selector,
+        idempotency_key,
+    )?;
+    benchmark.solutions_meta_data = None;
+    Ok(PreparedSubmission {
+        req,
+        block_started: benchmark.details.block_started,
+        lifespan_period: latest_block.config().benchmark_submissions.lifespan_period,
+        signer: job.signer.clone().or_else(signer),
+    })
+}
+
+// the solution_data sent is picked from the sorted entries, so with `SolutionSelector::First` it
+// does not depend on the order solutions were found in either. solutions without an entry are
+// never picked, as they could not be proven
+fn build_req(
+    settings: &BenchmarkSettings,
+    mut solutions_meta_data: Vec<SolutionMetaData>,
+    solutions_data: &CompressedSolutionData,
+    sort_strategy: SortStrategy,
+    selector: SolutionSelector,
+    idempotency_key: Uuid,
+) -> Result<SubmitBenchmarkReq> {
+    sort_strategy.apply(&mut solutions_meta_data);
+    let positions: HashMap<u64, usize> = solutions_meta_data
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (d.nonce, i))
+        .collect();
+    let mut candidates: Vec<SolutionData> = solutions_data
+        .iter()
+        .filter(|d| positions.contains_key(&d.nonce))
+        .collect();
+    candidates.sort_by_key(|d| positions[&d.nonce]);
+    let solution_data = select_solution(&candidates, selector)?.clone();
+    SubmitBenchmarkReq::builder()
+        .settings(settings.clone())
+        .solutions_meta_data(solutions_meta_data)
+        .solution_data(solution_data)
+        .idempotency_key(idempotency_key)
+        .build()
+        .map_err(BenchmarkerError::InvalidSubmission)
+}
+
+// keeps the first half of solutions_meta_data, along with the entry of solution_data. the merkle
+// root the server computes over the truncated set has to prove it
+fn truncate_req(req: &SubmitBenchmarkReq) -> Result<SubmitBenchmarkReq> {
+    let mut truncated = req.clone();
+    truncated.truncate_solutions_meta_data(req.solutions_meta_data().len() / 2);
+    let solutions_meta_data = truncated.solutions_meta_data();
+    calc_solutions_merkle_root(solutions_meta_data)
+        .and_then(|root| verify_solutions_merkle(&root, solutions_meta_data, req.solution_data()))
+        .map_err(|e| {
+            BenchmarkerError::LocalVerificationFailed(VerificationError::InvalidMerkleProof(e))
+        })?;
+    Ok(truncated)
+}
+
+// halving more often than this would leave fewer than one entry
+fn max_truncations(num_solutions: usize) -> u32 {
+    num_solutions.max(1).ilog2()
+}
+
+type SendResult = std::result::Result<tig_api::Result<SubmitBenchmarkResp>, ()>;
+
+// sends `req`, signed if there is a `signer`, halving its solutions_meta_data and resending it
+// while the server rejects it as too large. each halving uses up one of `truncations_left`
+async fn send_truncating(
+    api: &dyn BenchmarkApi,
+    req: &mut SubmitBenchmarkReq,
+    signer: Option<&dyn Signer>,
+    config: &SubmissionConfig,
+    truncations_left: &mut u32,
+    mut on_response: impl FnMut(&SendResult, Duration),
+) -> Result<SendResult> {
+    loop {
+        let start = Instant::now();
+        let send = match signer {
+            Some(signer) => api.submit_benchmark_signed(req.clone(), config.chunk_size, signer),
+            None => api.submit_benchmark_chunked(req.clone(), config.chunk_size),
+        };
+        let result = timeout(
+            config.submission_timeout_ms.min(u32::MAX as u64) as u32,
+            send,
+        )
+        .await;
+        on_response(&result, start.elapsed());
+        match &result {
+            Ok(Err(e)) if is_payload_too_large(e) => {
+                if *truncations_left == 0 {
+                    return Err(BenchmarkerError::PayloadTooLarge {
+                        num_solutions: req.solutions_meta_data().len(),
+                    });
+                }
+                *truncations_left -= 1;
+                let truncated = truncate_req(req)?;
+                warn!(
+                    "Payload too large. Truncating solutions_meta_data from {} to {} entries",
+                    req.solutions_meta_data().len(),
+                    truncated.solutions_meta_data().len()
+                );
+                *req = truncated;
+            }
+            _ => return Ok(result),
         }
+    }
+}
+
+// puts solutions_meta_data back so a cancelled submission can be resumed later
+pub fn restore_submission(
+    query_data: &mut QueryData,
+    job: &Job,
+    solutions_meta_data: Vec<SolutionMetaData>,
+) {
+    if let Some(benchmark) = query_data.benchmarks.get_mut(&job.benchmark_id) {
+        benchmark.solutions_meta_data = Some(solutions_meta_data);
+    }
+}
+
+// regenerates `submission.verify_sample_fraction` of the job's solutions. runs before
+// `prepare_submission` so a failing sample never gets as far as a SubmitBenchmarkReq
+pub async fn verify_sample(job: &Job, config: &SubmissionConfig) -> Result<()> {
+    let verifier = SampleVerifier::new(config.verify_sample_fraction, config.verify_sample_seed);
+    if !verifier.is_enabled() {
+        return Ok(());
+    }
+    let solutions_meta_data = state()
+        .lock()
+        .await
+        .query_data
+        .benchmarks
+        .get(&job.benchmark_id)
+        .and_then(|b| b.solutions_meta_data.clone())
+        .ok_or_else(|| BenchmarkerError::StateNotFound {
+            what: "benchmark",
+            id: job.benchmark_id.to_string(),
+        })?;
+    let wasm = download_wasm::execute(job).await?;
+    verifier
+        .verify(&solutions_meta_data, |nonce| {
+            compute_solution(
+                &job.settings,
+                nonce,
+                wasm.as_slice(),
+                job.wasm_vm_config.max_memory,
+                job.wasm_vm_config.max_fuel,
+            )
+            .ok()
+            .flatten()
+        })
+        .map_err(BenchmarkerError::LocalVerificationFailed)
+}
+
+// a server on another protocol version rejects every submission, so retrying is pointless.
+// `server` is None if the server predates protocol versioning
+pub fn check_protocol_version(server: Option<u16>) -> Result<()> {
+    match server {
+        Some(server) if server != PROTOCOL_VERSION => {
+            Err(BenchmarkerError::ProtocolVersionMismatch {
+                server,
+                client: PROTOCOL_VERSION,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+// the root span of a submission. each attempt and the api calls it makes are its children
+#[instrument(
+    name = "benchmarker.submit_benchmark",
+    skip_all,
+    fields(benchmark_id = %job.benchmark_id)
+)]
+pub async fn execute(
+    job: &Job,
+    config: &SubmissionConfig,
+    token: &CancellationToken,
+) -> Result<SubmissionOutcome> {
+    if state()
+        .lock()
+        .await
+        .submitted
+        .contains(job.benchmark_id.as_str())
+    {
+        info!(
+            "Benchmark {} was already submitted. Skipping",
+            job.benchmark_id
+        );
+        return Ok(SubmissionOutcome::Accepted {
+            benchmark_id: job.benchmark_id.to_string(),
+            reward: None,
+        });
+    }
+    let start = Instant::now();
+    check_protocol_version(server_protocol_version())?;
+    verify_sample(job, config).await?;
+    let sample_verified = Instant::now();
+    // generated once, so every attempt below is recognisable to the server as the same one
+    let idempotency_key = Uuid::new_v4();
+    info!(%idempotency_key, "Submitting benchmark {}", job.benchmark_id);
+    let (proof_fetched, prepared) = {
+        let query_data = &mut state().lock().await.query_data;
+        let proof_fetched = Instant::now();
+        let prepared = prepare_submission(
+            query_data,
+            job,
+            config.sort_strategy,
+            config.solution_selector,
+            idempotency_key,
+        )?;
+        (proof_fetched, prepared)
     };
+    let req_built = Instant::now();
+    let mut timing = SubmissionTiming {
+        benchmark_id: job.benchmark_id.to_string(),
+        sample_verify_us: as_us(sample_verified - start),
+        proof_fetch_us: as_us(proof_fetched - sample_verified),
+        req_build_us: as_us(req_built - proof_fetched),
+        attempt_durations_us: Vec::new(),
+        total_us: 0,
+    };
+    if config.dry_run {
+        info!(
+            "Dry run. Benchmark {}: {} solutions, {} byte payload. Not submitted",
+            job.benchmark_id,
+            prepared.req.solutions_meta_data().len(),
+            jsonify(&prepared.req).len()
+        );
+        restore_submission(
+            &mut state().lock().await.query_data,
+            job,
+            prepared.req.into_solutions_meta_data(),
+        );
+        timing.total_us = as_us(start.elapsed());
+        record_timing(timing).await;
+        return Ok(SubmissionOutcome::Accepted {
+            benchmark_id: DRY_RUN_BENCHMARK_ID.to_string(),
+            reward: None,
+        });
+    }
+    let result = submit(&prepared, config, token, &mut timing.attempt_durations_us).await;
+    timing.total_us = as_us(start.elapsed());
+    record_timing(timing).await;
+    match &result {
+        Ok(_) => {
+            state()
+                .lock()
+                .await
+                .submitted
+                .insert(job.benchmark_id.clone());
+            if let Err(e) = save_snapshot().await {
+                warn!("Failed to save submitted registry: {}", e);
+            }
+        }
+        Err(BenchmarkerError::ShuttingDown | BenchmarkerError::Cancelled) => {
+            restore_submission(
+                &mut state().lock().await.query_data,
+                job,
+                prepared.req.into_solutions_meta_data(),
+            );
+        }
+        Err(_) => {}
+    }
+    result
+}
+
+// a benchmark flagged as fraud is only an error with `fraud_is_fatal`
+pub fn check_outcome(
+    outcome: SubmissionOutcome,
+    config: &SubmissionConfig,
+) -> Result<SubmissionOutcome> {
+    match outcome {
+        SubmissionOutcome::Fraud { reason, .. } if config.fraud_is_fatal => {
+            Err(BenchmarkerError::FraudDetected(reason))
+        }
+        SubmissionOutcome::Fraud {
+            ref benchmark_id,
+            ref reason,
+        } => {
+            warn!("Benchmark {} flagged as fraud: {}", benchmark_id, reason);
+            Ok(outcome)
+        }
+        SubmissionOutcome::Accepted { .. } => Ok(outcome),
+    }
+}
+
+async fn record_timing(timing: SubmissionTiming) {
+    info!(
+        sample_verify_us = timing.sample_verify_us,
+        proof_fetch_us = timing.proof_fetch_us,
+        req_build_us = timing.req_build_us,
+        attempts = timing.attempt_durations_us.len(),
+        total_us = timing.total_us,
+        "Submission timing"
+    );
+    let timings = state().lock().await.timings.clone();
+    timings.record(timing);
+}
 
-    let mut current_height = query_latest_block().await?.details.height;
+// pushes the duration of every API call made onto `attempt_durations_us`
+pub async fn submit(
+    prepared: &PreparedSubmission,
+    config: &SubmissionConfig,
+    token: &CancellationToken,
+    attempt_durations_us: &mut Vec<u64>,
+) -> Result<SubmissionOutcome> {
+    let PreparedSubmission {
+        req,
+        block_started,
+        lifespan_period,
+        signer,
+    } = prepared;
+    let (block_started, lifespan_period) = (*block_started, *lifespan_period);
+    // a truncated request is what later attempts send
+    let mut req = req.clone();
+    let mut truncations_left = max_truncations(req.solutions_meta_data().len());
 
-    for attempt in 1..=MAX_RETRIES {
-        println!("Submission attempt {} of {}", attempt, MAX_RETRIES);
-        match api().submit_benchmark(req.clone()).await {
-            Ok(resp) => {
-                return match resp.verified {
-                    Ok(_) => Ok(resp.benchmark_id),
-                    Err(e) => Err(format!("Benchmark flagged as fraud: {}", e)),
+    let max_retries = config.max_retries;
+    let retry_policy = config.retry_policy();
+    let mut current_height = block_height_watcher::current_height().await?;
+    let captured_height = current_height;
+    let metrics = state().lock().await.metrics.clone();
+
+    for attempt in 1..=max_retries {
+        if token.is_cancelled() {
+            return Err(BenchmarkerError::Cancelled);
+        }
+        if attempt > 1 && shutdown_signal().is_triggered() {
+            return Err(BenchmarkerError::ShuttingDown);
+        }
+        if attempt > 1 {
+            current_height = block_height_watcher::current_height().await?;
+        }
+        check_block_height(
+            captured_height,
+            current_height,
+            block_started,
+            lifespan_period,
+        )?;
+        info!("Submission attempt {} of {}", attempt, max_retries);
+        let on_response = |result: &SendResult, elapsed: Duration| {
+            let outcome = match result {
+                Ok(Ok(resp)) if resp.verified.is_ok() => AttemptOutcome::Success,
+                Ok(Ok(_)) => AttemptOutcome::Fraud,
+                _ => AttemptOutcome::Error,
+            };
+            metrics.record_attempt(outcome, elapsed.as_millis() as u64);
+            attempt_durations_us.push(as_us(elapsed));
+        };
+        let result = send_truncating(
+            api(),
+            &mut req,
+            signer.as_deref(),
+            config,
+            &mut truncations_left,
+            on_response,
+        )
+        .instrument(info_span!(
+            "benchmarker.submit_attempt",
+            attempt,
+            max_retries
+        ))
+        .await?;
+        match result {
+            Err(_) => {
+                if attempt == max_retries {
+                    return Err(BenchmarkerError::SubmissionTimeout {
+                        attempts: attempt,
+                        timeout_ms: config.submission_timeout_ms,
+                    });
                 }
+                warn!(
+                    "Failed to submit benchmark: timed out after {} ms",
+                    config.submission_timeout_ms
+                );
+                backoff(attempt, &retry_policy).await;
             }
-            Err(e) => {
-                let err_msg = format!("Failed to submit benchmark after {} attempts: {:?}", attempt, e);
-                if attempt < MAX_RETRIES {
-                    if !handle_submission_error(&e, "benchmark", &mut current_height).await {
-                        return Err(err_msg);
+            Ok(Ok(resp)) => {
+                record_history(&resp, current_height);
+                return check_outcome(resp.into(), config);
+            }
+            Ok(Err(e)) => {
+                if attempt == max_retries {
+                    return Err(BenchmarkerError::MaxRetriesExceeded {
+                        attempts: attempt,
+                        trace_id: tig_api::error_trace_id(&e),
+                        last_error: e,
+                    });
+                }
+                match handle_submission_error(
+                    e,
+                    "benchmark",
+                    &mut current_height,
+                    attempt,
+                    &retry_policy,
+                    token,
+                )
+                .await
+                {
+                    RetryDecision::Retry { after } => {
+                        if !after.is_zero() {
+                            info!("Retrying in {} ms...", after.as_millis());
+                            sleep(after.as_millis().min(u32::MAX as u128) as u32).await;
+                        }
                     }
-                } else {
-                    return Err(err_msg);
+                    RetryDecision::Abort(e) => return Err(BenchmarkerError::aborted(e)),
+                    RetryDecision::Fatal(e) => return Err(e),
                 }
             }
         }
     }
+    // `BenchmarkerConfig::validate` rejects max_retries == 0, so the last attempt returns above
     unreachable!()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "standalone")]
+    use crate::benchmarker::test_utils;
+    use tig_structs::core::Solution;
+    use tig_utils::CompressionConfig;
+
+    fn settings() -> BenchmarkSettings {
+        BenchmarkSettings {
+            player_id: "player".to_string(),
+            block_id: "block".to_string(),
+            challenge_id: "c001".to_string(),
+            algorithm_id: "c001_a001".to_string(),
+            difficulty: vec![50, 300],
+        }
+    }
+
+    // what a benchmarker that found `nonces` in this order would hold
+    fn generated(nonces: &[u64]) -> (Vec<SolutionMetaData>, CompressedSolutionData) {
+        let solutions_data: Vec<SolutionData> = nonces
+            .iter()
+            .map(|&nonce| {
+                let mut solution = Solution::new();
+                solution.insert("variables".to_string(), serde_json::json!([nonce % 2 == 0]));
+                SolutionData {
+                    nonce,
+                    runtime_signature: 1000 + nonce as u32,
+                    fuel_consumed: 2000 + nonce,
+                    solution,
+                }
+            })
+            .collect();
+        let mut data = CompressedSolutionData::new(CompressionConfig::default());
+        data.extend(solutions_data.iter());
+        let meta_data = solutions_data
+            .into_iter()
+            .map(SolutionMetaData::from)
+            .collect();
+        (meta_data, data)
+    }
+
+    fn build(
+        meta_data: Vec<SolutionMetaData>,
+        data: &CompressedSolutionData,
+        sort_strategy: SortStrategy,
+        selector: SolutionSelector,
+    ) -> Result<SubmitBenchmarkReq> {
+        build_req(
+            &settings(),
+            meta_data,
+            data,
+            sort_strategy,
+            selector,
+            Uuid::nil(),
+        )
+    }
+
+    fn req(nonces: &[u64], sort_strategy: SortStrategy) -> String {
+        let (meta_data, data) = generated(nonces);
+        jsonify(&build(meta_data, &data, sort_strategy, SolutionSelector::First).unwrap())
+    }
+
+    #[test]
+    fn test_by_hash_is_independent_of_generation_order() {
+        let a = req(&[3, 1, 4, 0, 2], SortStrategy::ByHash);
+        let b = req(&[0, 2, 4, 1, 3], SortStrategy::ByHash);
+        assert_eq!(a, b);
+        assert_ne!(
+            req(&[3, 1, 4, 0, 2], SortStrategy::AsGenerated),
+            req(&[0, 2, 4, 1, 3], SortStrategy::AsGenerated)
+        );
+    }
+
+    #[test]
+    fn test_sort_strategies() {
+        let (mut meta_data, _) = generated(&[3, 1, 4, 0, 2]);
+        SortStrategy::AsGenerated.apply(&mut meta_data);
+        let nonces = |m: &[SolutionMetaData]| m.iter().map(|d| d.nonce).collect::<Vec<u64>>();
+        assert_eq!(nonces(&meta_data), vec![3, 1, 4, 0, 2]);
+        SortStrategy::ByIndex.apply(&mut meta_data);
+        assert_eq!(nonces(&meta_data), vec![0, 1, 2, 3, 4]);
+        SortStrategy::ByHash.apply(&mut meta_data);
+        let hashes: Vec<[u8; 32]> = meta_data.iter().map(hash_leaf).collect();
+        assert!(hashes.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_solution_data_is_first_sorted_entry() {
+        let (meta_data, data) = generated(&[3, 1, 4, 0, 2]);
+        let req = build(
+            meta_data,
+            &data,
+            SortStrategy::ByIndex,
+            SolutionSelector::First,
+        )
+        .unwrap();
+        assert_eq!(req.solution_data().nonce, 0);
+    }
+
+    #[test]
+    fn test_select_solution() {
+        let (_, data) = generated(&[3, 1, 4, 0, 2]);
+        let solutions = data.into_vec();
+        let nonce = |selector| select_solution(&solutions, selector).unwrap().nonce;
+        assert_eq!(nonce(SolutionSelector::First), 3);
+        assert_eq!(nonce(SolutionSelector::Last), 2);
+        // fuel consumed grows with the nonce
+        assert_eq!(nonce(SolutionSelector::HighestScore), 0);
+        assert_eq!(
+            nonce(SolutionSelector::Random(7)),
+            nonce(SolutionSelector::Random(7))
+        );
+        assert!(matches!(
+            select_solution(&[], SolutionSelector::Random(7)),
+            Err(BenchmarkerError::MissingData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_truncation_keeps_selected_solution() {
+        let (meta_data, data) = generated(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let req = build(
+            meta_data,
+            &data,
+            SortStrategy::ByIndex,
+            SolutionSelector::Last,
+        )
+        .unwrap();
+        assert_eq!(req.solution_data().nonce, 7);
+        let truncated = truncate_req(&req).unwrap();
+        assert_eq!(truncated.solutions_meta_data().len(), 4);
+        assert!(truncated.solutions_meta_data().iter().any(|d| d.nonce == 7));
+    }
+
+    #[cfg(feature = "standalone")]
+    fn accepted(benchmark_id: &str) -> SubmitBenchmarkResp {
+        SubmitBenchmarkResp {
+            benchmark_id: benchmark_id.to_string(),
+            verified: Ok(()),
+            reward: None,
+        }
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_execute_submits_through_installed_api() {
+        let ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("local", &[0, 1, 2], 100).await;
+        test_utils::push_block(&ctx.api, 100);
+        ctx.api
+            .push_response("submit_benchmark", &accepted("server"));
+        let config = test_utils::submission_config();
+        let outcome = execute(&job, &config, &CancellationToken::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            outcome,
+            SubmissionOutcome::Accepted { ref benchmark_id, .. } if benchmark_id == "server"
+        ));
+        ctx.api.assert_called_n_times("submit_benchmark", 1);
+        let sent: SubmitBenchmarkReq =
+            tig_utils::dejsonify(&ctx.api.calls("submit_benchmark")[0]).unwrap();
+        assert_eq!(sent.solutions_meta_data().len(), 3);
+        assert!(state().lock().await.submitted.contains("local"));
+        // a second execute is answered from the registry without another request
+        execute(&job, &config, &CancellationToken::default())
+            .await
+            .unwrap();
+        ctx.api.assert_called_n_times("submit_benchmark", 1);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_single_retry_fails_after_one_attempt() {
+        let ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("local", &[0, 1], 100).await;
+        test_utils::push_block(&ctx.api, 100);
+        ctx.api
+            .push_error(
+                "submit_benchmark",
+                "Request error (status: 503, body: busy)",
+            )
+            .push_response("submit_benchmark", &accepted("server"));
+        let config = SubmissionConfig {
+            max_retries: 1,
+            ..test_utils::submission_config()
+        };
+        let result = execute(&job, &config, &CancellationToken::default()).await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::MaxRetriesExceeded { attempts: 1, .. })
+        ));
+        ctx.api.assert_called_n_times("submit_benchmark", 1);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_state_is_not_locked_while_a_submission_is_in_flight() {
+        let ctx = test_utils::setup().await;
+        let first = test_utils::add_benchmark("first", &[0], 100).await;
+        let second = test_utils::add_benchmark("second", &[1], 100).await;
+        for _ in 0..2 {
+            test_utils::push_block(&ctx.api, 100);
+        }
+        let release = ctx
+            .api
+            .push_held_response("submit_benchmark", &accepted("first"));
+        ctx.api
+            .push_response("submit_benchmark", &accepted("second"));
+        let config = test_utils::submission_config();
+        let in_flight = tokio::spawn({
+            let config = config.clone();
+            async move { execute(&first, &config, &CancellationToken::default()).await }
+        });
+        while ctx.api.calls("submit_benchmark").is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        // would wait forever on the state lock if the first submission held it
+        let outcome = tokio::time::timeout(
+            Duration::from_secs(5),
+            execute(&second, &config, &CancellationToken::default()),
+        )
+        .await
+        .expect("second submission should not wait for the first")
+        .unwrap();
+        assert_eq!(outcome.benchmark_id(), "second");
+        assert!(!in_flight.is_finished());
+        release.send(()).unwrap();
+        assert_eq!(in_flight.await.unwrap().unwrap().benchmark_id(), "first");
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_cancelled_job_restores_solutions_meta_data() {
+        let ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("local", &[0, 1, 2], 100).await;
+        let nonces = || async {
+            let mut nonces: Vec<u64> = test_utils::solutions_meta_data("local")
+                .await
+                .unwrap()
+                .iter()
+                .map(|d| d.nonce)
+                .collect();
+            nonces.sort();
+            nonces
+        };
+        let before = nonces().await;
+        test_utils::push_block(&ctx.api, 100);
+        let token = CancellationToken::default();
+        token.cancel();
+        let result = execute(&job, &test_utils::submission_config(), &token).await;
+        assert!(matches!(result, Err(BenchmarkerError::Cancelled)));
+        ctx.api.assert_called_n_times("submit_benchmark", 0);
+        // taken out to build the request, and put back in the order it was sent in
+        assert_eq!(nonces().await, before);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_falling_height_between_attempts_is_a_reorg() {
+        let ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("local", &[0, 1], 100).await;
+        test_utils::push_block(&ctx.api, 105);
+        test_utils::push_block(&ctx.api, 103);
+        ctx.api.push_error(
+            "submit_benchmark",
+            "Request error (status: 503, body: busy)",
+        );
+        let result = execute(
+            &job,
+            &test_utils::submission_config(),
+            &CancellationToken::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::BlockReorg {
+                captured_height: 105,
+                current_height: 103,
+            })
+        ));
+        // one height query per attempt, and no second request once the reorg is seen
+        ctx.api.assert_called_n_times("get_block", 2);
+        ctx.api.assert_called_n_times("submit_benchmark", 1);
+    }
+
+    #[test]
+    fn test_other_protocol_version_is_rejected() {
+        assert!(check_protocol_version(None).is_ok());
+        assert!(check_protocol_version(Some(PROTOCOL_VERSION)).is_ok());
+        let other = PROTOCOL_VERSION + 1;
+        assert!(matches!(
+            check_protocol_version(Some(other)),
+            Err(BenchmarkerError::ProtocolVersionMismatch { server, .. }) if server == other
+        ));
+    }
+
+    // submits a benchmark started at block 100 with the height at `height`, once the test has
+    // queued the responses for submit_benchmark
+    #[cfg(feature = "standalone")]
+    async fn execute_at(
+        ctx: &test_utils::TestContext,
+        height: u32,
+        config: &SubmissionConfig,
+    ) -> Result<SubmissionOutcome> {
+        let job = test_utils::add_benchmark("local", &[0, 1], 100).await;
+        for _ in 0..config.max_retries {
+            test_utils::push_block(&ctx.api, height);
+        }
+        execute(&job, config, &CancellationToken::default()).await
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_missing_benchmark_is_state_not_found() {
+        let _ctx = test_utils::setup().await;
+        let mut job = test_utils::add_benchmark("local", &[0], 100).await;
+        job.benchmark_id = "unknown".into();
+        let result = execute(
+            &job,
+            &test_utils::submission_config(),
+            &CancellationToken::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::StateNotFound { what: "benchmark", ref id }) if id == "unknown"
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_invalid_proof_fails_before_any_request() {
+        let ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("local", &[0, 1], 100).await;
+        state()
+            .lock()
+            .await
+            .query_data
+            .benchmarks
+            .get_mut("local")
+            .unwrap()
+            .details
+            .num_solutions = 3;
+        let result = execute(
+            &job,
+            &test_utils::submission_config(),
+            &CancellationToken::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::LocalVerificationFailed(
+                VerificationError::NumSolutionsMismatch { .. }
+            ))
+        ));
+        ctx.api.assert_called_n_times("get_block", 0);
+        ctx.api.assert_called_n_times("submit_benchmark", 0);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_closed_window_is_block_height_stale() {
+        let ctx = test_utils::setup().await;
+        let result = execute_at(
+            &ctx,
+            100 + test_utils::LIFESPAN_PERIOD,
+            &test_utils::submission_config(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::BlockHeightStale {
+                block_started: 100,
+                ..
+            })
+        ));
+        ctx.api.assert_called_n_times("submit_benchmark", 0);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_unanswered_attempts_are_a_timeout() {
+        let ctx = test_utils::setup().await;
+        let _release = ctx
+            .api
+            .push_held_response("submit_benchmark", &accepted("server"));
+        let config = SubmissionConfig {
+            max_retries: 1,
+            submission_timeout_ms: 20,
+            ..test_utils::submission_config()
+        };
+        let result = execute_at(&ctx, 100, &config).await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::SubmissionTimeout {
+                attempts: 1,
+                timeout_ms: 20,
+            })
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_last_retriable_error_is_max_retries_exceeded() {
+        let ctx = test_utils::setup().await;
+        for _ in 0..3 {
+            ctx.api.push_error(
+                "submit_benchmark",
+                "Request error (status: 503, body: busy)",
+            );
+        }
+        let result = execute_at(&ctx, 100, &test_utils::submission_config()).await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::MaxRetriesExceeded { attempts: 3, .. })
+        ));
+        ctx.api.assert_called_n_times("submit_benchmark", 3);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_unauthorized_is_an_api_error() {
+        let ctx = test_utils::setup().await;
+        ctx.api.push_error(
+            "submit_benchmark",
+            "Request error (status: 401, body: invalid api key)",
+        );
+        let result = execute_at(&ctx, 100, &test_utils::submission_config()).await;
+        assert!(matches!(result, Err(BenchmarkerError::ApiError { .. })));
+        ctx.api.assert_called_n_times("submit_benchmark", 1);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_non_retriable_error_is_submission_aborted() {
+        let ctx = test_utils::setup().await;
+        ctx.api.push_error(
+            "submit_benchmark",
+            "Request error (status: 400, body: Proof already submitted)",
+        );
+        let result = execute_at(&ctx, 100, &test_utils::submission_config()).await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::SubmissionAborted { .. })
+        ));
+        ctx.api.assert_called_n_times("submit_benchmark", 1);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_flagged_benchmark_is_fraud_detected() {
+        let ctx = test_utils::setup().await;
+        ctx.api.push_response(
+            "submit_benchmark",
+            &SubmitBenchmarkResp {
+                benchmark_id: "server".to_string(),
+                verified: Err("bad solution".to_string()),
+                reward: None,
+            },
+        );
+        let result = execute_at(&ctx, 100, &test_utils::submission_config()).await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::FraudDetected(ref reason)) if reason == "bad solution"
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_payload_too_large_without_truncations_left() {
+        let ctx = test_utils::setup().await;
+        let job = test_utils::add_benchmark("local", &[0], 100).await;
+        test_utils::push_block(&ctx.api, 100);
+        ctx.api.push_error("submit_benchmark", PAYLOAD_TOO_LARGE);
+        let result = execute(
+            &job,
+            &test_utils::submission_config(),
+            &CancellationToken::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::PayloadTooLarge { num_solutions: 1 })
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_shutdown_between_attempts_is_shutting_down() {
+        let ctx = test_utils::setup().await;
+        ctx.api.push_error(
+            "submit_benchmark",
+            "Request error (status: 503, body: busy)",
+        );
+        shutdown_signal().trigger();
+        let result = execute_at(&ctx, 100, &test_utils::submission_config()).await;
+        assert!(matches!(result, Err(BenchmarkerError::ShuttingDown)));
+        ctx.api.assert_called_n_times("submit_benchmark", 1);
+        // kept so the submission can be resumed after the restart
+        assert!(test_utils::solutions_meta_data("local").await.is_some());
+    }
+
+    #[cfg(feature = "standalone")]
+    const PAYLOAD_TOO_LARGE: &str = "Request error (status: 413, body: Payload Too Large)";
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_truncates_on_payload_too_large() {
+        let api = tig_api::mock::MockBenchmarkApi::new();
+        api.push_error("submit_benchmark", PAYLOAD_TOO_LARGE)
+            .push_response(
+                "submit_benchmark",
+                &SubmitBenchmarkResp {
+                    benchmark_id: "benchmark".to_string(),
+                    verified: Ok(()),
+                    reward: None,
+                },
+            );
+        let (meta_data, data) = generated(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let mut req = build(
+            meta_data,
+            &data,
+            SortStrategy::ByIndex,
+            SolutionSelector::First,
+        )
+        .unwrap();
+        let mut truncations_left = max_truncations(8);
+        let config = SubmissionConfig::default();
+        let result = send_truncating(
+            &api,
+            &mut req,
+            None,
+            &config,
+            &mut truncations_left,
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+        assert!(matches!(result, Ok(Ok(_))));
+        let sent: Vec<SubmitBenchmarkReq> = api
+            .calls("submit_benchmark")
+            .iter()
+            .map(|c| tig_utils::dejsonify(c).unwrap())
+            .collect();
+        assert_eq!(sent[0].solutions_meta_data().len(), 8);
+        assert_eq!(sent[1].solutions_meta_data().len(), 4);
+        assert_eq!(
+            sent[1].solution_data().nonce,
+            sent[1].solutions_meta_data()[0].nonce
+        );
+        assert_eq!(truncations_left, 2);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_gives_up_after_log2_truncations() {
+        let api = tig_api::mock::MockBenchmarkApi::new();
+        for _ in 0..3 {
+            api.push_error("submit_benchmark", PAYLOAD_TOO_LARGE);
+        }
+        let (meta_data, data) = generated(&[0, 1, 2, 3]);
+        let mut req = build(
+            meta_data,
+            &data,
+            SortStrategy::ByIndex,
+            SolutionSelector::First,
+        )
+        .unwrap();
+        let mut truncations_left = max_truncations(4);
+        let config = SubmissionConfig::default();
+        let result = send_truncating(
+            &api,
+            &mut req,
+            None,
+            &config,
+            &mut truncations_left,
+            |_, _| {},
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BenchmarkerError::PayloadTooLarge { num_solutions: 1 })
+        ));
+        api.assert_called_n_times("submit_benchmark", 3);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_signed_when_there_is_a_signer() {
+        let api = tig_api::mock::MockBenchmarkApi::new();
+        api.push_response(
+            "submit_benchmark_signed",
+            &SubmitBenchmarkResp {
+                benchmark_id: "benchmark".to_string(),
+                verified: Ok(()),
+                reward: None,
+            },
+        );
+        let (meta_data, data) = generated(&[0, 1]);
+        let mut req = build(
+            meta_data,
+            &data,
+            SortStrategy::ByIndex,
+            SolutionSelector::First,
+        )
+        .unwrap();
+        let signer = tig_api::Ed25519Signer::from_bytes(&[7u8; 32]);
+        let result = send_truncating(
+            &api,
+            &mut req,
+            Some(&signer),
+            &SubmissionConfig::default(),
+            &mut 0,
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+        assert!(matches!(result, Ok(Ok(_))));
+        api.assert_called_n_times("submit_benchmark_signed", 1);
+        api.assert_called_n_times("submit_benchmark", 0);
+    }
+}
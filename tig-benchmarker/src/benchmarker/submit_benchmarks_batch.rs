@@ -0,0 +1,386 @@
+use super::{
+    api,
+    block_height_watcher::current_height,
+    record_history, save_snapshot, server_protocol_version,
+    shutdown::{shutdown_signal, CancellationToken},
+    state,
+    submit_benchmark::{
+        self, check_outcome, check_protocol_version, prepare_submission, restore_submission,
+        verify_sample, PreparedSubmission,
+    },
+    utils::{backoff, handle_submission_error, is_unsupported_endpoint, RetryDecision},
+    BenchmarkId, BenchmarkerError, Job, Result, SubmissionConfig, SubmissionOutcome,
+};
+use crate::future_utils::{sleep, timeout};
+use tig_api::{SubmitBatchReq, Uuid};
+use tracing::{info, instrument, warn};
+
+type JobResults = Vec<(Job, Result<SubmissionOutcome>)>;
+
+struct Member {
+    job: Job,
+    token: CancellationToken,
+    prepared: PreparedSubmission,
+}
+
+// submits `jobs` in one request, each with its own token. a server without the batch endpoint
+// gets them one at a time instead. accepted benchmarks are registered as submitted, as by
+// `submit_benchmark::execute`
+#[instrument(skip_all, fields(num_jobs = jobs.len()))]
+pub async fn execute(
+    jobs: Vec<(Job, CancellationToken)>,
+    config: &SubmissionConfig,
+) -> Result<JobResults> {
+    let results = submit_all(jobs, config).await?;
+    let accepted: Vec<BenchmarkId> = results
+        .iter()
+        .filter(|(_, result)| result.is_ok())
+        .map(|(job, _)| job.benchmark_id.clone())
+        .collect();
+    if !accepted.is_empty() {
+        let mut state = state().lock().await;
+        for benchmark_id in accepted {
+            state.submitted.insert(benchmark_id);
+        }
+        drop(state);
+        if let Err(e) = save_snapshot().await {
+            warn!("Failed to save submitted registry: {}", e);
+        }
+    }
+    Ok(results)
+}
+
+async fn submit_all(
+    jobs: Vec<(Job, CancellationToken)>,
+    config: &SubmissionConfig,
+) -> Result<JobResults> {
+    check_protocol_version(server_protocol_version())?;
+    let mut results = Vec::new();
+    let mut sampled = Vec::new();
+    for (job, token) in jobs {
+        let already_submitted = state()
+            .lock()
+            .await
+            .submitted
+            .contains(job.benchmark_id.as_str());
+        if already_submitted {
+            info!(
+                "Benchmark {} was already submitted. Skipping",
+                job.benchmark_id
+            );
+            let outcome = SubmissionOutcome::Accepted {
+                benchmark_id: job.benchmark_id.to_string(),
+                reward: None,
+            };
+            results.push((job, Ok(outcome)));
+            continue;
+        }
+        match verify_sample(&job, config).await {
+            Ok(()) => sampled.push((job, token)),
+            Err(e) => results.push((job, Err(e))),
+        }
+    }
+    let mut pending: Vec<Member> = Vec::new();
+    {
+        let query_data = &mut state().lock().await.query_data;
+        for (job, token) in sampled {
+            let idempotency_key = Uuid::new_v4();
+            info!(%idempotency_key, "Submitting benchmark {}", job.benchmark_id);
+            match prepare_submission(
+                query_data,
+                &job,
+                config.sort_strategy,
+                config.solution_selector,
+                idempotency_key,
+            ) {
+                Ok(prepared) => pending.push(Member {
+                    job,
+                    token,
+                    prepared,
+                }),
+                Err(e) => results.push((job, Err(e))),
+            }
+        }
+    }
+    if pending.is_empty() {
+        return Ok(results);
+    }
+
+    let mut current_height = current_height().await?;
+    pending.retain(|m| {
+        let PreparedSubmission {
+            block_started,
+            lifespan_period,
+            ..
+        } = m.prepared;
+        if current_height >= block_started + lifespan_period {
+            results.push((
+                m.job.clone(),
+                Err(BenchmarkerError::BlockHeightStale {
+                    block_started,
+                    current_height,
+                }),
+            ));
+            false
+        } else {
+            true
+        }
+    });
+
+    // a batch request has no room for signatures, so signed benchmarks are submitted one by one
+    let (signed, mut pending): (Vec<_>, Vec<_>) = pending
+        .into_iter()
+        .partition(|m| m.prepared.signer.is_some());
+    submit_sequentially(signed, config, &mut results).await;
+
+    let max_retries = config.max_retries;
+    let retry_policy = config.retry_policy();
+    for attempt in 1..=max_retries {
+        if attempt > 1 && shutdown_signal().is_triggered() {
+            restore(pending, || BenchmarkerError::ShuttingDown, &mut results).await;
+            return Ok(results);
+        }
+        // a cancelled benchmark is left out of this and every later attempt
+        let (cancelled, kept): (Vec<_>, Vec<_>) =
+            pending.into_iter().partition(|m| m.token.is_cancelled());
+        restore(cancelled, || BenchmarkerError::Cancelled, &mut results).await;
+        pending = kept;
+        if pending.is_empty() {
+            return Ok(results);
+        }
+        let req = SubmitBatchReq {
+            benchmarks: pending.iter().map(|m| m.prepared.req.clone()).collect(),
+        };
+        info!(
+            "Batch submission attempt {} of {} ({} benchmarks)",
+            attempt,
+            max_retries,
+            req.benchmarks.len()
+        );
+        match timeout(
+            config.submission_timeout_ms.min(u32::MAX as u64) as u32,
+            api().submit_benchmarks_batch(req),
+        )
+        .await
+        {
+            Err(_) => {
+                if attempt == max_retries {
+                    return Err(BenchmarkerError::SubmissionTimeout {
+                        attempts: attempt,
+                        timeout_ms: config.submission_timeout_ms,
+                    });
+                }
+                warn!(
+                    "Failed to submit benchmarks batch: timed out after {} ms",
+                    config.submission_timeout_ms
+                );
+                backoff(attempt, &retry_policy).await;
+            }
+            Ok(Ok(resp)) => {
+                for r in resp.results.iter() {
+                    record_history(r, current_height);
+                }
+                let mut resps = resp.results.into_iter();
+                for m in pending {
+                    let result = match resps.next() {
+                        Some(resp) => check_outcome(resp.into(), config),
+                        None => Err(BenchmarkerError::MissingData {
+                            what: "batch submission result",
+                        }),
+                    };
+                    results.push((m.job, result));
+                }
+                return Ok(results);
+            }
+            Ok(Err(e)) if is_unsupported_endpoint(&e) => {
+                warn!("Batch submission not supported by server. Submitting sequentially...");
+                submit_sequentially(pending, config, &mut results).await;
+                return Ok(results);
+            }
+            Ok(Err(e)) => {
+                if attempt == max_retries {
+                    return Err(BenchmarkerError::MaxRetriesExceeded {
+                        attempts: attempt,
+                        trace_id: tig_api::error_trace_id(&e),
+                        last_error: e,
+                    });
+                }
+                // members are cancelled one at a time at the next attempt, so only shutdown cuts
+                // a wait for a new block short
+                match handle_submission_error(
+                    e,
+                    "benchmarks batch",
+                    &mut current_height,
+                    attempt,
+                    &retry_policy,
+                    &CancellationToken::default(),
+                )
+                .await
+                {
+                    RetryDecision::Retry { after } => {
+                        if !after.is_zero() {
+                            info!("Retrying in {} ms...", after.as_millis());
+                            sleep(after.as_millis().min(u32::MAX as u128) as u32).await;
+                        }
+                    }
+                    RetryDecision::Abort(e) => return Err(BenchmarkerError::aborted(e)),
+                    RetryDecision::Fatal(BenchmarkerError::ShuttingDown) => {
+                        restore(pending, || BenchmarkerError::ShuttingDown, &mut results).await;
+                        return Ok(results);
+                    }
+                    RetryDecision::Fatal(e) => return Err(e),
+                }
+            }
+        }
+    }
+    // validate rejects max_retries of 0, so the last attempt always returns
+    unreachable!()
+}
+
+async fn submit_sequentially(
+    members: Vec<Member>,
+    config: &SubmissionConfig,
+    results: &mut JobResults,
+) {
+    for m in members {
+        let result = submit_benchmark::submit(&m.prepared, config, &m.token, &mut Vec::new()).await;
+        if let Err(BenchmarkerError::ShuttingDown | BenchmarkerError::Cancelled) = result {
+            restore_submission(
+                &mut state().lock().await.query_data,
+                &m.job,
+                m.prepared.req.into_solutions_meta_data(),
+            );
+        }
+        results.push((m.job, result));
+    }
+}
+
+// puts back the solutions_meta_data of benchmarks that will not be sent, so they can be resumed
+async fn restore(
+    members: Vec<Member>,
+    error: impl Fn() -> BenchmarkerError,
+    results: &mut JobResults,
+) {
+    if members.is_empty() {
+        return;
+    }
+    let query_data = &mut state().lock().await.query_data;
+    for m in members {
+        restore_submission(
+            query_data,
+            &m.job,
+            m.prepared.req.into_solutions_meta_data(),
+        );
+        results.push((m.job, Err(error())));
+    }
+}
+
+#[cfg(all(test, feature = "standalone"))]
+mod tests {
+    use super::*;
+    use crate::benchmarker::test_utils;
+    use tig_api::{SubmitBatchResp, SubmitBenchmarkResp};
+
+    fn accepted(benchmark_id: &str) -> SubmitBenchmarkResp {
+        SubmitBenchmarkResp {
+            benchmark_id: benchmark_id.to_string(),
+            verified: Ok(()),
+            reward: None,
+        }
+    }
+
+    async fn jobs(ids: &[&str]) -> Vec<(Job, CancellationToken)> {
+        let mut jobs = Vec::new();
+        for id in ids {
+            let job = test_utils::add_benchmark(id, &[0, 1], 100).await;
+            jobs.push((job, CancellationToken::default()));
+        }
+        jobs
+    }
+
+    fn server_ids(results: &JobResults) -> Vec<String> {
+        results
+            .iter()
+            .map(|(_, result)| match result {
+                Ok(outcome) => outcome.benchmark_id().to_string(),
+                Err(e) => panic!("expected an accepted benchmark, got {:?}", e),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_benchmarks_are_submitted_in_one_request() {
+        let ctx = test_utils::setup().await;
+        let jobs = jobs(&["a", "b"]).await;
+        test_utils::push_block(&ctx.api, 100);
+        ctx.api.push_response(
+            "submit_benchmarks_batch",
+            &SubmitBatchResp {
+                results: vec![accepted("server-a"), accepted("server-b")],
+            },
+        );
+        let results = execute(jobs, &test_utils::submission_config())
+            .await
+            .unwrap();
+        assert_eq!(server_ids(&results), vec!["server-a", "server-b"]);
+        ctx.api.assert_called_n_times("submit_benchmarks_batch", 1);
+        ctx.api.assert_called_n_times("submit_benchmark", 0);
+        let state = state().lock().await;
+        assert!(state.submitted.contains("a"));
+        assert!(state.submitted.contains("b"));
+    }
+
+    #[tokio::test]
+    async fn test_server_without_batch_endpoint_gets_them_one_at_a_time() {
+        let ctx = test_utils::setup().await;
+        let jobs = jobs(&["a", "b"]).await;
+        // one for the batch and one for each single submission
+        for _ in 0..3 {
+            test_utils::push_block(&ctx.api, 100);
+        }
+        ctx.api
+            .push_error(
+                "submit_benchmarks_batch",
+                "Request error (status: 404, body: not found)",
+            )
+            .push_response("submit_benchmark", &accepted("server-a"))
+            .push_response("submit_benchmark", &accepted("server-b"));
+        let results = execute(jobs, &test_utils::submission_config())
+            .await
+            .unwrap();
+        assert_eq!(server_ids(&results), vec!["server-a", "server-b"]);
+        ctx.api.assert_called_n_times("submit_benchmarks_batch", 1);
+        ctx.api.assert_called_n_times("submit_benchmark", 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_benchmark_is_left_out_of_the_batch() {
+        let ctx = test_utils::setup().await;
+        let jobs = jobs(&["a", "b"]).await;
+        jobs[1].1.cancel();
+        test_utils::push_block(&ctx.api, 100);
+        ctx.api.push_response(
+            "submit_benchmarks_batch",
+            &SubmitBatchResp {
+                results: vec![accepted("server-a")],
+            },
+        );
+        let results = execute(jobs, &test_utils::submission_config())
+            .await
+            .unwrap();
+        let calls = ctx.api.calls("submit_benchmarks_batch");
+        let req: SubmitBatchReq = serde_json::from_str(&calls[0]).unwrap();
+        assert_eq!(req.benchmarks.len(), 1);
+        let (cancelled, result) = results
+            .iter()
+            .find(|(job, _)| job.benchmark_id == "b")
+            .unwrap();
+        assert!(matches!(result, Err(BenchmarkerError::Cancelled)));
+        assert!(
+            test_utils::solutions_meta_data(cancelled.benchmark_id.as_str())
+                .await
+                .is_some()
+        );
+        assert!(!state().lock().await.submitted.contains("b"));
+    }
+}
@@ -1,37 +1,98 @@
-use super::{api, Result, utils::handle_submission_error, query_data::query_latest_block};
+use super::{
+    api,
+    block_height_watcher::current_height,
+    shutdown::{shutdown_signal, CancellationToken},
+    utils::{backoff, handle_submission_error, RetryDecision},
+    BenchmarkId, BenchmarkerError, Result, SubmissionConfig,
+};
+use crate::future_utils::{sleep, timeout};
 use tig_api::SubmitProofReq;
 use tig_worker::SolutionData;
+use tracing::{info, instrument, warn};
 
-const MAX_RETRIES: u32 = 3;
-
-pub async fn execute(benchmark_id: String, solutions_data: Vec<SolutionData>) -> Result<()> {
+#[instrument(skip_all, fields(benchmark_id = %benchmark_id))]
+pub async fn execute(
+    benchmark_id: BenchmarkId,
+    solutions_data: Vec<SolutionData>,
+    config: &SubmissionConfig,
+    token: &CancellationToken,
+) -> Result<()> {
     let req = SubmitProofReq {
-        benchmark_id,
+        benchmark_id: benchmark_id.into_string(),
         solutions_data,
     };
 
-    let mut current_height = query_latest_block().await?.details.height;
+    let max_retries = config.max_retries;
+    let retry_policy = config.retry_policy();
+    let mut current_height = current_height().await?;
 
-    for attempt in 1..=MAX_RETRIES {
-        println!("Submission attempt {} of {}", attempt, MAX_RETRIES);
-        match api().submit_proof(req.clone()).await {
-            Ok(resp) => {
+    for attempt in 1..=max_retries {
+        if token.is_cancelled() {
+            return Err(BenchmarkerError::Cancelled);
+        }
+        if attempt > 1 && shutdown_signal().is_triggered() {
+            return Err(BenchmarkerError::ShuttingDown);
+        }
+        info!("Submission attempt {} of {}", attempt, max_retries);
+        match timeout(
+            config.submission_timeout_ms.min(u32::MAX as u64) as u32,
+            api().submit_proof(req.clone()),
+        )
+        .await
+        {
+            Err(_) => {
+                if attempt == max_retries {
+                    return Err(BenchmarkerError::SubmissionTimeout {
+                        attempts: attempt,
+                        timeout_ms: config.submission_timeout_ms,
+                    });
+                }
+                warn!(
+                    "Failed to submit proof: timed out after {} ms",
+                    config.submission_timeout_ms
+                );
+                backoff(attempt, &retry_policy).await;
+            }
+            Ok(Ok(resp)) => {
                 return match resp.verified {
                     Ok(_) => Ok(()),
-                    Err(e) => Err(format!("Proof flagged as fraud: {}", e)),
+                    Err(e) if config.fraud_is_fatal => Err(BenchmarkerError::FraudDetected(e)),
+                    Err(e) => {
+                        warn!("Proof flagged as fraud: {}", e);
+                        Ok(())
+                    }
                 }
             }
-            Err(e) => {
-                let err_msg = format!("Failed to submit proof after {} attempts: {:?}", attempt, e);
-                if attempt < MAX_RETRIES {
-                    if !handle_submission_error(&e, "proof", &mut current_height).await {
-                        return Err(err_msg);
+            Ok(Err(e)) => {
+                if attempt == max_retries {
+                    return Err(BenchmarkerError::MaxRetriesExceeded {
+                        attempts: attempt,
+                        trace_id: tig_api::error_trace_id(&e),
+                        last_error: e,
+                    });
+                }
+                match handle_submission_error(
+                    e,
+                    "proof",
+                    &mut current_height,
+                    attempt,
+                    &retry_policy,
+                    token,
+                )
+                .await
+                {
+                    RetryDecision::Retry { after } => {
+                        if !after.is_zero() {
+                            info!("Retrying in {} ms...", after.as_millis());
+                            sleep(after.as_millis().min(u32::MAX as u128) as u32).await;
+                        }
                     }
-                } else {
-                    return Err(err_msg);
+                    RetryDecision::Abort(e) => return Err(BenchmarkerError::aborted(e)),
+                    RetryDecision::Fatal(e) => return Err(e),
                 }
             }
         }
     }
+    // `BenchmarkerConfig::validate` rejects max_retries == 0, so the last attempt returns above
     unreachable!()
 }
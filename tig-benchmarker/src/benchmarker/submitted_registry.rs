@@ -0,0 +1,28 @@
+use super::BenchmarkId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// ids of benchmarks that were accepted by the api. persisted in the snapshot so a restart
+// between submitting and saving state does not submit the same benchmark twice
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SubmittedRegistry {
+    benchmark_ids: BTreeSet<BenchmarkId>,
+}
+
+impl SubmittedRegistry {
+    pub fn contains(&self, benchmark_id: &str) -> bool {
+        self.benchmark_ids.contains(benchmark_id)
+    }
+
+    pub fn insert(&mut self, benchmark_id: impl Into<BenchmarkId>) -> bool {
+        self.benchmark_ids.insert(benchmark_id.into())
+    }
+
+    pub fn len(&self) -> usize {
+        self.benchmark_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.benchmark_ids.is_empty()
+    }
+}
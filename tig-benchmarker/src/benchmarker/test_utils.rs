@@ -0,0 +1,245 @@
+use super::{
+    set_api, shutdown_signal, BenchmarkId, BenchmarkerConfig, CompressedSolutionData, Job,
+    JobScheduler, MetricsHandle, QueryData, State, Status, SubmissionConfig, SubmittedRegistry,
+    TimingLog, CONFIG, PLAYER_ID, SERVER_PROTOCOL_VERSION, SIGNER, SNAPSHOT_PATH, STATE,
+};
+use crate::future_utils::Mutex;
+use linked_hash_map::LinkedHashMap;
+use once_cell::sync::OnceCell;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+use tig_api::{mock::MockBenchmarkApi, GetBlockResp};
+use tig_structs::{config::WasmVMConfig, core::*};
+use tig_utils::CompressionConfig;
+use tokio::sync::MutexGuard;
+
+pub const LIFESPAN_PERIOD: u32 = 10;
+
+static MOCK: OnceCell<Arc<MockBenchmarkApi>> = OnceCell::new();
+static SERIAL: OnceCell<Mutex<()>> = OnceCell::new();
+
+// the benchmarker's globals are process wide, so tests that use them take turns. the state and
+// the mock are the holder's until this is dropped
+pub struct TestContext {
+    pub api: Arc<MockBenchmarkApi>,
+    _serial: MutexGuard<'static, ()>,
+}
+
+// installs the mock as `api()` and resets it, the state and the shutdown signal
+pub async fn setup() -> TestContext {
+    let serial = SERIAL.get_or_init(|| Mutex::new(())).lock().await;
+    let api = MOCK
+        .get_or_init(|| {
+            let api = Arc::new(MockBenchmarkApi::new());
+            assert!(
+                set_api(Box::new(api.clone())),
+                "API should not be initialised before the mock"
+            );
+            api
+        })
+        .clone();
+    api.reset();
+    CONFIG.get_or_init(config);
+    PLAYER_ID.get_or_init(|| "player".to_string());
+    SNAPSHOT_PATH.get_or_init(|| None);
+    SERVER_PROTOCOL_VERSION.get_or_init(|| None);
+    SIGNER.get_or_init(|| None);
+    shutdown_signal().reset();
+    match STATE.get() {
+        Some(state) => *state.lock().await = empty_state(),
+        None => {
+            let _ = STATE.set(Mutex::new(empty_state()));
+        }
+    }
+    TestContext {
+        api,
+        _serial: serial,
+    }
+}
+
+// no waiting between attempts, and every height is queried from the api so tests can push one
+// block per attempt
+fn config() -> BenchmarkerConfig {
+    let mut config = BenchmarkerConfig::default();
+    config.submission = SubmissionConfig {
+        retry_base_delay_ms: 0,
+        retry_max_delay_ms: 0,
+        retry_max_jitter_ms: 0,
+        block_stale_timeout_ms: 0,
+        block_cache_ttl_ms: 0,
+        submission_timeout_ms: 1000,
+        high_volume_poll_interval_ms: 10,
+        ..Default::default()
+    };
+    config.scheduler.poll_interval_ms = 10;
+    config
+}
+
+pub fn submission_config() -> SubmissionConfig {
+    config().submission
+}
+
+pub fn block(height: u32) -> Block {
+    let config = serde_json::json!({
+        "erc20": {
+            "rpc_url": "",
+            "chain_id": "",
+            "token_address": "",
+            "burn_address": "",
+        },
+        "benchmark_submissions": {
+            "min_num_solutions": 1,
+            "submission_delay_multiplier": 1,
+            "max_samples": 1,
+            "lifespan_period": LIFESPAN_PERIOD,
+        },
+        "wasm_vm": { "max_memory": 1000000000u64, "max_fuel": 10000000000u64 },
+        "solution_signature": { "equilibrium_rate_multiplier": 1.0 },
+        "qualifiers": { "cutoff_multiplier": 1.0, "total_qualifiers_threshold": 1 },
+        "difficulty": { "max_scaling_factor": 1.0, "parameters": {} },
+        "optimisable_proof_of_work": { "imbalance_multiplier": 1.0 },
+        "rounds": { "blocks_per_round": 10 },
+        "algorithm_submissions": {
+            "submission_fee": "0",
+            "adoption_threshold": 0.25,
+            "merge_points_threshold": 1,
+            "push_delay": 1,
+        },
+        "rewards": {
+            "distribution": { "benchmarkers": 1.0, "optimisations": 0.0, "breakthroughs": 0.0 },
+            "schedule": [],
+        },
+    });
+    Block {
+        id: format!("block{}", height),
+        details: BlockDetails {
+            prev_block_id: String::new(),
+            height,
+            round: 0,
+            eth_block_num: None,
+        },
+        data: None,
+        config: Some(serde_json::from_value(config).unwrap()),
+    }
+}
+
+// queues the latest block returned by the next height query
+pub fn push_block(api: &MockBenchmarkApi, height: u32) {
+    api.push_response(
+        "get_block",
+        &GetBlockResp {
+            block: Some(block(height)),
+        },
+    );
+}
+
+fn empty_state() -> State {
+    State {
+        status: Status::Stopped,
+        timer: None,
+        query_data: QueryData {
+            latest_block: block(0),
+            player_data: None,
+            challenges: Vec::new(),
+            download_urls: HashMap::new(),
+            algorithms_by_challenge: HashMap::new(),
+            benchmarks: HashMap::new(),
+            proofs: HashMap::new(),
+            frauds: HashMap::new(),
+            solutions_data: HashMap::new(),
+            recency: LinkedHashMap::new(),
+        },
+        selected_algorithms: HashMap::new(),
+        job: None,
+        submission_errors: HashMap::new(),
+        pending_submissions: VecDeque::new(),
+        job_scheduler: JobScheduler::default(),
+        submitted: SubmittedRegistry::default(),
+        metrics: Arc::new(MetricsHandle::new()),
+        timings: Arc::new(TimingLog::new(10)),
+        difficulty_samplers: HashMap::new(),
+        checkpoints: Vec::new(),
+    }
+}
+
+// a satisfiability instance without clauses, so that any assignment is a valid solution
+pub fn solution_data(nonce: u64) -> SolutionData {
+    let mut solution = Solution::new();
+    solution.insert("variables".to_string(), serde_json::json!(vec![0; 50]));
+    SolutionData {
+        nonce,
+        runtime_signature: 1000 + nonce as u32,
+        fuel_consumed: 2000 + nonce,
+        solution,
+    }
+}
+
+// a benchmark started at `block_started` that found a solution for each of `nonces`, as held
+// just before it is submitted
+pub async fn add_benchmark(benchmark_id: &str, nonces: &[u64], block_started: u32) -> Job {
+    let settings = BenchmarkSettings {
+        player_id: "player".to_string(),
+        block_id: format!("block{}", block_started),
+        challenge_id: "c001".to_string(),
+        algorithm_id: "c001_a001".to_string(),
+        difficulty: vec![50, 0],
+    };
+    let solutions_data: Vec<SolutionData> = nonces.iter().map(|&n| solution_data(n)).collect();
+    let mut data = CompressedSolutionData::new(CompressionConfig::default());
+    data.extend(solutions_data.iter());
+    let benchmark_id = BenchmarkId::from(benchmark_id);
+    let mut state = super::state().lock().await;
+    let query_data = &mut state.query_data;
+    query_data.benchmarks.insert(
+        benchmark_id.clone(),
+        Benchmark {
+            id: benchmark_id.to_string(),
+            settings: settings.clone(),
+            details: BenchmarkDetails {
+                block_started,
+                num_solutions: nonces.len() as u32,
+            },
+            state: None,
+            solutions_meta_data: Some(
+                solutions_data
+                    .into_iter()
+                    .map(SolutionMetaData::from)
+                    .collect(),
+            ),
+            solution_data: None,
+        },
+    );
+    query_data.proofs.insert(
+        benchmark_id.clone(),
+        Proof {
+            benchmark_id: benchmark_id.to_string(),
+            state: None,
+            solutions_data: None,
+        },
+    );
+    query_data.solutions_data.insert(benchmark_id.clone(), data);
+    Job {
+        download_url: String::new(),
+        benchmark_id,
+        settings,
+        solution_signature_threshold: u32::MAX,
+        sampled_nonces: None,
+        wasm_vm_config: WasmVMConfig {
+            max_memory: 1000000000,
+            max_fuel: 10000000000,
+        },
+        signer: None,
+    }
+}
+
+pub async fn solutions_meta_data(benchmark_id: &str) -> Option<Vec<SolutionMetaData>> {
+    super::state()
+        .lock()
+        .await
+        .query_data
+        .benchmarks
+        .get(benchmark_id)
+        .and_then(|b| b.solutions_meta_data.clone())
+}
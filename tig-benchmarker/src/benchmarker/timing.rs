@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+// wall-clock time spent in each phase of `submit_benchmark::execute`, in microseconds
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SubmissionTiming {
+    pub benchmark_id: String,
+    /// recomputing the sampled solutions with `verify_sample_fraction`
+    #[serde(default)]
+    pub sample_verify_us: u64,
+    /// waiting for the state that holds the benchmark's solutions
+    pub proof_fetch_us: u64,
+    /// local proof verification and building the SubmitBenchmarkReq
+    pub req_build_us: u64,
+    /// one entry per API call, including ones that timed out or failed
+    pub attempt_durations_us: Vec<u64>,
+    pub total_us: u64,
+}
+
+pub fn as_us(duration: Duration) -> u64 {
+    duration.as_micros().min(u64::MAX as u128) as u64
+}
+
+// the most recent `max_len` timings. the oldest entry is dropped once full
+pub struct TimingLog {
+    entries: Mutex<VecDeque<SubmissionTiming>>,
+    max_len: usize,
+}
+
+impl TimingLog {
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(max_len)),
+            max_len,
+        }
+    }
+
+    pub fn record(&self, timing: SubmissionTiming) {
+        if self.max_len == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        while entries.len() >= self.max_len {
+            entries.pop_front();
+        }
+        entries.push_back(timing);
+    }
+
+    // most recent first
+    pub fn last(&self, n: usize) -> Vec<SubmissionTiming> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(n)
+            .cloned()
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for TimingLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimingLog")
+            .field("max_len", &self.max_len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(benchmark_id: &str) -> SubmissionTiming {
+        SubmissionTiming {
+            benchmark_id: benchmark_id.to_string(),
+            sample_verify_us: 5,
+            proof_fetch_us: 10,
+            req_build_us: 20,
+            attempt_durations_us: vec![30],
+            total_us: 60,
+        }
+    }
+
+    fn ids(timings: Vec<SubmissionTiming>) -> Vec<String> {
+        timings.into_iter().map(|t| t.benchmark_id).collect()
+    }
+
+    #[test]
+    fn test_drops_oldest_when_full() {
+        let log = TimingLog::new(2);
+        log.record(timing("a"));
+        log.record(timing("b"));
+        log.record(timing("c"));
+        assert_eq!(ids(log.last(10)), vec!["c", "b"]);
+        assert_eq!(ids(log.last(1)), vec!["c"]);
+    }
+
+    #[test]
+    fn test_zero_len_records_nothing() {
+        let log = TimingLog::new(0);
+        log.record(timing("a"));
+        assert!(log.last(10).is_empty());
+    }
+}
@@ -1,49 +1,418 @@
-use super::query_data::query_latest_block;
-use crate::future_utils::sleep;
-use std::time::Instant;
+use super::{
+    block_height_watcher,
+    shutdown::{shutdown_signal, CancellationToken},
+    submission_config, BenchmarkerError,
+};
+use crate::future_utils::{sleep, time};
+use once_cell::sync::OnceCell;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
 
-const WAIT_TIME_MS: u32 = 5000;
 const LOG_INTERVAL_SECS: u64 = 10;
 
-pub async fn handle_submission_error(e: &anyhow::Error, submit_name: &str, current_height: &mut u32) -> bool {
-    let err_msg = format!("Failed to submit {}: {:?}", submit_name, e);
-    
-    if let Some(err_str) = e.downcast_ref::<String>() {
-        if err_str.to_lowercase().contains("high transaction volume") {
-            println!("High transaction volume detected. Waiting for a new block...");
-
-            let start_time = Instant::now();
-            let mut last_log_time = start_time;
-            loop {
-                sleep(WAIT_TIME_MS).await;
-                let elapsed_time = start_time.elapsed();
-                let time_since_last_log = last_log_time.elapsed();
-
-                let new_height = query_latest_block().await.expect("Failed to query latest block").details.height;
-                if new_height > *current_height {
-                    *current_height = new_height;
-                    println!("New block {} mined after waiting for {} seconds. Retrying submission...", 
-                             current_height, elapsed_time.as_secs());
-                    break;
-                } else if time_since_last_log.as_secs() >= LOG_INTERVAL_SECS {
-                    last_log_time = Instant::now();
-                    println!("Waiting for a new block... ({} seconds elapsed)", elapsed_time.as_secs());
-                } else {
-                    /* Do Nothing */
-                }
-            }
-        } else if err_str.to_lowercase().contains("proof already submitted") {
-            return false;
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub min_jitter_ms: u64,
+    pub max_jitter_ms: u64,
+}
+
+impl RetryPolicy {
+    // truncated exponential backoff: base * 2^(attempt - 1), capped at max, plus random jitter
+    pub fn delay_ms(&self, attempt: u32, rng: &mut StdRng) -> u64 {
+        let multiplier = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(multiplier)
+            .min(self.max_delay_ms);
+        let jitter = if self.max_jitter_ms > self.min_jitter_ms {
+            rng.gen_range(self.min_jitter_ms..=self.max_jitter_ms)
         } else {
-            println!("{}", err_msg);
-            println!("Retrying in {} seconds...", WAIT_TIME_MS / 1000);
-            sleep(WAIT_TIME_MS).await;
+            self.min_jitter_ms
+        };
+        backoff.saturating_add(jitter)
+    }
+
+    pub fn is_retriable(&self, e: &anyhow::Error) -> bool {
+        match e.downcast_ref::<String>() {
+            Some(err_str) => !err_str.to_lowercase().contains("proof already submitted"),
+            None => true,
         }
+    }
+}
+
+// seeded once. seeding from the clock on every call gives each call in the same millisecond the
+// same jitter, which is what jitter is meant to avoid
+pub fn jitter_rng() -> MutexGuard<'static, StdRng> {
+    static RNG: OnceCell<Mutex<StdRng>> = OnceCell::new();
+    RNG.get_or_init(|| Mutex::new(StdRng::seed_from_u64(time())))
+        .lock()
+        .unwrap()
+}
+
+// servers without an endpoint respond with 404 Not Found or 405 Method Not Allowed
+pub fn is_unsupported_endpoint(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<String>()
+        .is_some_and(|err_str| err_str.contains("status: 404") || err_str.contains("status: 405"))
+}
+
+// 413 Payload Too Large, or a server saying so in the body of some other status
+pub fn is_payload_too_large(e: &anyhow::Error) -> bool {
+    match tig_api::StatusError::parse(e) {
+        Some(s) => s.status == 413 || s.body.to_lowercase().contains("payload too large"),
+        None => false,
+    }
+}
+
+#[derive(Debug)]
+pub enum RetryDecision {
+    Retry { after: Duration },
+    Abort(anyhow::Error),
+    Fatal(BenchmarkerError),
+}
+
+// 401 Unauthorized and 403 Forbidden fail the same way on every attempt
+fn is_unauthorized(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<String>()
+        .is_some_and(|err_str| err_str.contains("status: 401") || err_str.contains("status: 403"))
+}
+
+// the delay asked for by a 429 Too Many Requests. only the delay-seconds form of the header
+// is understood, an http-date falls back to the backoff
+fn retry_after(e: &anyhow::Error) -> Option<Duration> {
+    let err_str = e.downcast_ref::<String>()?;
+    if !err_str.contains("status: 429") {
+        return None;
+    }
+    let (_, rest) = err_str.split_once("retry-after: ")?;
+    let secs = rest.split(',').next()?.trim().parse::<u64>().ok()?;
+    Some(Duration::from_secs(secs))
+}
+
+// decides what to do after a failed attempt without sleeping, so it can be tested directly
+pub fn retry_decision(
+    e: anyhow::Error,
+    attempt: u32,
+    policy: &RetryPolicy,
+    rng: &mut StdRng,
+) -> RetryDecision {
+    if is_unauthorized(&e) {
+        RetryDecision::Fatal(e.into())
+    } else if !policy.is_retriable(&e) {
+        RetryDecision::Abort(e)
+    } else if let Some(after) = retry_after(&e) {
+        RetryDecision::Retry { after }
     } else {
-        println!("{}", err_msg);
-        println!("Retrying in {} seconds...", WAIT_TIME_MS / 1000);
-        sleep(WAIT_TIME_MS).await;
+        RetryDecision::Retry {
+            after: Duration::from_millis(policy.delay_ms(attempt, rng)),
+        }
+    }
+}
+
+// waits for a new block when the api reports high transaction volume, as retrying before
+// then is rejected in the same way
+pub async fn handle_submission_error(
+    e: anyhow::Error,
+    submit_name: &str,
+    current_height: &mut u32,
+    attempt: u32,
+    policy: &RetryPolicy,
+    token: &CancellationToken,
+) -> RetryDecision {
+    if e.downcast_ref::<String>()
+        .is_some_and(|err_str| err_str.to_lowercase().contains("high transaction volume"))
+    {
+        return wait_for_new_block(e, current_height, attempt, token).await;
     }
 
-    true
+    match tig_api::error_benchmark_id(&e) {
+        Some(benchmark_id) => warn!("Failed {} {}: {:?}", submit_name, benchmark_id, e),
+        None => warn!("Failed to submit {}: {:?}", submit_name, e),
+    }
+    retry_decision(e, attempt, policy, &mut jitter_rng())
+}
+
+// the wait gets the time the remaining attempts would have had to time out in, and ends early on
+// cancellation or shutdown
+async fn wait_for_new_block(
+    e: anyhow::Error,
+    current_height: &mut u32,
+    attempt: u32,
+    token: &CancellationToken,
+) -> RetryDecision {
+    warn!("High transaction volume detected. Waiting for a new block...");
+    let config = submission_config();
+    let remaining_attempts = config.max_retries.saturating_sub(attempt).max(1);
+    let budget = Duration::from_millis(
+        config
+            .submission_timeout_ms
+            .saturating_mul(remaining_attempts as u64),
+    );
+
+    let start_time = Instant::now();
+    let mut last_log_time = start_time;
+    loop {
+        sleep(config.high_volume_poll_interval_ms).await;
+        if token.is_cancelled() {
+            return RetryDecision::Fatal(BenchmarkerError::Cancelled);
+        }
+        if shutdown_signal().is_triggered() {
+            return RetryDecision::Fatal(BenchmarkerError::ShuttingDown);
+        }
+        let elapsed_time = start_time.elapsed();
+        let new_height = match block_height_watcher::current_height().await {
+            Ok(new_height) => new_height,
+            Err(e) => return RetryDecision::Fatal(e),
+        };
+        if new_height > *current_height {
+            *current_height = new_height;
+            info!(
+                "New block {} mined after waiting for {} seconds. Retrying submission...",
+                current_height,
+                elapsed_time.as_secs()
+            );
+            return RetryDecision::Retry {
+                after: Duration::ZERO,
+            };
+        }
+        if elapsed_time >= budget {
+            warn!(
+                "No new block after waiting for {} seconds. Giving up",
+                elapsed_time.as_secs()
+            );
+            return RetryDecision::Fatal(BenchmarkerError::MaxRetriesExceeded {
+                attempts: attempt,
+                trace_id: tig_api::error_trace_id(&e),
+                last_error: e,
+            });
+        }
+        if last_log_time.elapsed().as_secs() >= LOG_INTERVAL_SECS {
+            last_log_time = Instant::now();
+            info!(
+                "Waiting for a new block... ({} seconds elapsed)",
+                elapsed_time.as_secs()
+            );
+        }
+    }
+}
+
+pub async fn backoff(attempt: u32, policy: &RetryPolicy) {
+    let delay_ms = policy.delay_ms(attempt, &mut jitter_rng());
+    info!("Retrying in {} ms...", delay_ms);
+    sleep(delay_ms.min(u32::MAX as u64) as u32).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "standalone")]
+    use crate::benchmarker::test_utils;
+    use anyhow::anyhow;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay_ms: 1000,
+            max_delay_ms: 8000,
+            min_jitter_ms: 0,
+            max_jitter_ms: 0,
+        }
+    }
+
+    fn request_error(status: u16, body: &str) -> anyhow::Error {
+        anyhow!(format!(
+            "Request error (status: {}, body: {})",
+            status, body
+        ))
+    }
+
+    #[test]
+    fn test_delay_doubles_per_attempt() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let delays: Vec<u64> = (1..=4).map(|a| policy().delay_ms(a, &mut rng)).collect();
+        assert_eq!(delays, vec![1000, 2000, 4000, 8000]);
+    }
+
+    #[test]
+    fn test_delay_is_capped() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(policy().delay_ms(5, &mut rng), 8000);
+        // a shift past 64 bits would overflow without the saturating multiplier
+        assert_eq!(policy().delay_ms(200, &mut rng), 8000);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = RetryPolicy {
+            min_jitter_ms: 100,
+            max_jitter_ms: 500,
+            ..policy()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let delays: Vec<u64> = (0..1000).map(|_| policy.delay_ms(1, &mut rng)).collect();
+        assert!(delays.iter().all(|d| (1100..=1500).contains(d)));
+        // not every draw is the same
+        assert!(delays.iter().any(|d| *d != delays[0]));
+        let fixed = RetryPolicy {
+            min_jitter_ms: 300,
+            max_jitter_ms: 300,
+            ..policy
+        };
+        assert_eq!(fixed.delay_ms(1, &mut rng), 1300);
+    }
+
+    #[test]
+    fn test_retries_with_backoff() {
+        let mut rng = StdRng::seed_from_u64(0);
+        match retry_decision(request_error(503, "busy"), 3, &policy(), &mut rng) {
+            RetryDecision::Retry { after } => assert_eq!(after, Duration::from_millis(4000)),
+            other => panic!("expected Retry, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_aborts_when_not_retriable() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let e = request_error(400, "Proof already submitted");
+        match retry_decision(e, 1, &policy(), &mut rng) {
+            RetryDecision::Abort(e) => assert_eq!(
+                tig_api::StatusError::parse(&e).unwrap().body,
+                "Proof already submitted"
+            ),
+            other => panic!("expected Abort, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_api_errors_keep_trace_id() {
+        let trace_id = tig_api::Uuid::new_v4();
+        let e = request_error(400, "bad").context(tig_api::TraceContext { trace_id });
+        let e: BenchmarkerError = e.into();
+        assert!(matches!(e, BenchmarkerError::ApiError { .. }));
+        assert_eq!(e.trace_id(), Some(trace_id));
+        let e = request_error(400, "bad").context(tig_api::TraceContext { trace_id });
+        let e = BenchmarkerError::aborted(e);
+        assert!(matches!(e, BenchmarkerError::SubmissionAborted { .. }));
+        assert_eq!(e.trace_id(), Some(trace_id));
+    }
+
+    #[test]
+    fn test_unauthorized_is_fatal() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(matches!(
+            retry_decision(
+                request_error(401, "invalid api key"),
+                1,
+                &policy(),
+                &mut rng
+            ),
+            RetryDecision::Fatal(BenchmarkerError::ApiError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rate_limit_without_retry_after_backs_off() {
+        let mut rng = StdRng::seed_from_u64(0);
+        match retry_decision(request_error(429, "slow down"), 1, &policy(), &mut rng) {
+            RetryDecision::Retry { after } => assert_eq!(after, Duration::from_millis(1000)),
+            other => panic!("expected Retry, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "standalone")]
+    async fn handle_high_volume(
+        current_height: &mut u32,
+        attempt: u32,
+        token: &CancellationToken,
+    ) -> RetryDecision {
+        let e = request_error(503, "High transaction volume");
+        handle_submission_error(e, "benchmark", current_height, attempt, &policy(), token).await
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_high_volume_retries_once_a_new_block_is_mined() {
+        let ctx = test_utils::setup().await;
+        test_utils::push_block(&ctx.api, 100);
+        test_utils::push_block(&ctx.api, 101);
+        let mut current_height = 100;
+        let token = CancellationToken::default();
+        assert!(matches!(
+            handle_high_volume(&mut current_height, 1, &token).await,
+            RetryDecision::Retry { after } if after.is_zero()
+        ));
+        assert_eq!(current_height, 101);
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_high_volume_wait_ends_on_cancellation() {
+        let _ctx = test_utils::setup().await;
+        let token = CancellationToken::default();
+        token.cancel();
+        assert!(matches!(
+            handle_high_volume(&mut 100, 1, &token).await,
+            RetryDecision::Fatal(BenchmarkerError::Cancelled)
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_high_volume_wait_is_bounded_by_the_remaining_attempts() {
+        let ctx = test_utils::setup().await;
+        // the last attempt left gets submission_timeout_ms, polled every 10 ms
+        for _ in 0..500 {
+            test_utils::push_block(&ctx.api, 100);
+        }
+        let last_retry = test_utils::submission_config().max_retries - 1;
+        assert!(matches!(
+            handle_high_volume(&mut 100, last_retry, &CancellationToken::default()).await,
+            RetryDecision::Fatal(BenchmarkerError::MaxRetriesExceeded { .. })
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_failed_height_query_ends_the_wait() {
+        let ctx = test_utils::setup().await;
+        ctx.api
+            .push_error("get_block", "Request error (status: 503, body: busy)");
+        assert!(matches!(
+            handle_high_volume(&mut 100, 1, &CancellationToken::default()).await,
+            RetryDecision::Fatal(BenchmarkerError::ApiError { .. })
+        ));
+    }
+
+    #[cfg(feature = "standalone")]
+    #[tokio::test]
+    async fn test_honours_retry_after_from_server() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 5\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let e = tig_utils::get::<String>(&url, None).await.unwrap_err();
+        let mut rng = StdRng::seed_from_u64(0);
+        match retry_decision(e, 1, &policy(), &mut rng) {
+            RetryDecision::Retry { after } => assert!(after >= Duration::from_secs(5)),
+            other => panic!("expected Retry, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,188 @@
+use super::{utils::is_unsupported_endpoint, BenchmarkerConfig};
+use tig_api::{ApiClientBuilder, ApiConfig, PROTOCOL_VERSION};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub passed: bool,
+    pub message: String,
+}
+
+impl Check {
+    fn pass(message: String) -> Self {
+        Self {
+            passed: true,
+            message,
+        }
+    }
+
+    fn fail(message: String) -> Self {
+        Self {
+            passed: false,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            if self.passed { "✓" } else { "✗" },
+            self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+    pub checks: Vec<Check>,
+}
+
+impl ValidationReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in self.checks.iter() {
+            writeln!(f, "{}", check)?;
+        }
+        Ok(())
+    }
+}
+
+// checks the config, then each api endpoint on its own so an unreachable fallback is not
+// hidden by a healthy primary. nothing is submitted
+pub async fn validate(config: &BenchmarkerConfig, api_key: String) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    match config.validate() {
+        Ok(()) => report.checks.push(Check::pass("config valid".to_string())),
+        Err(e) => {
+            report.checks.push(Check::fail(e.to_string()));
+            return report;
+        }
+    }
+    for endpoint in config.api.endpoints.iter() {
+        let api = ApiClientBuilder::from_config(&ApiConfig {
+            endpoints: vec![endpoint.clone()],
+            ..config.api.clone()
+        })
+        .with_api_key(api_key.clone())
+        .build();
+        let api = match api {
+            Ok(api) => api,
+            Err(e) => {
+                let message = format!("endpoint {} unusable: {}", endpoint, e);
+                report.checks.push(Check::fail(message));
+                continue;
+            }
+        };
+        if let Err(e) = api.get_health().await {
+            let message = format!("endpoint {} unreachable: {}", endpoint, e);
+            report.checks.push(Check::fail(message));
+            continue;
+        }
+        report
+            .checks
+            .push(Check::pass(format!("API reachable at {}", endpoint)));
+        report.checks.push(match api.get_protocol().await {
+            Ok(resp) if resp.protocol_version == PROTOCOL_VERSION => Check::pass(format!(
+                "protocol v{} supported by {}",
+                resp.protocol_version, endpoint
+            )),
+            Ok(resp) => Check::fail(format!(
+                "{} speaks protocol v{} but this benchmarker speaks v{}",
+                endpoint, resp.protocol_version, PROTOCOL_VERSION
+            )),
+            // such servers are not version checked when benchmarking either
+            Err(e) if is_unsupported_endpoint(&e) => Check::pass(format!(
+                "{} predates protocol versioning. Assuming v{}",
+                endpoint, PROTOCOL_VERSION
+            )),
+            Err(e) => Check::fail(format!(
+                "failed to query protocol version from {}: {}",
+                endpoint, e
+            )),
+        });
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    // answers /v1/health with 200 and /v1/protocol with `protocol_version`
+    async fn fake_server(protocol_version: u16) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap();
+                let body = if buf[..n].starts_with(b"GET /v1/protocol") {
+                    format!("{{\"protocol_version\":{}}}", protocol_version)
+                } else {
+                    "OK".to_string()
+                };
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    fn config(endpoints: Vec<String>) -> BenchmarkerConfig {
+        BenchmarkerConfig {
+            api: ApiConfig {
+                endpoints,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let url = fake_server(PROTOCOL_VERSION).await;
+        let report = validate(&config(vec![url.clone()]), String::new()).await;
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checks.len(), 3);
+        assert_eq!(
+            report.checks[2].to_string(),
+            format!("✓ protocol v{} supported by {}", PROTOCOL_VERSION, url)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reports_each_failing_endpoint() {
+        let healthy = fake_server(PROTOCOL_VERSION).await;
+        let outdated = fake_server(PROTOCOL_VERSION + 1).await;
+        // nothing listens on port 1
+        let unreachable = "http://127.0.0.1:1".to_string();
+        let report = validate(
+            &config(vec![healthy, outdated, unreachable.clone()]),
+            String::new(),
+        )
+        .await;
+        assert!(!report.passed());
+        let failed: Vec<&Check> = report.checks.iter().filter(|c| !c.passed).collect();
+        assert_eq!(failed.len(), 2);
+        assert!(failed[0].message.contains("speaks protocol"));
+        assert!(failed[1]
+            .message
+            .starts_with(&format!("endpoint {} unreachable", unreachable)));
+    }
+}
@@ -0,0 +1,247 @@
+use super::CompressedSolutionData;
+use std::collections::HashSet;
+use tig_proof::{verify_solution_hash, ProofError};
+use tig_structs::core::Benchmark;
+use tig_worker::verify_solution;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+    DuplicateNonce {
+        nonce: u64,
+    },
+    InvalidMerkleProof(ProofError),
+    InvalidSolution {
+        nonce: u64,
+    },
+    MissingSolutionsData,
+    MissingSolutionsMetaData,
+    NumSolutionsMismatch {
+        num_solutions: u32,
+        num_meta_data: usize,
+    },
+    SampledSolutionMismatch {
+        nonce: u64,
+    },
+}
+
+impl std::fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationError::DuplicateNonce { nonce } => {
+                write!(f, "Nonce '{}' is submitted more than once", nonce)
+            }
+            VerificationError::InvalidMerkleProof(e) => {
+                write!(f, "Solution data is not in solutions_meta_data: {}", e)
+            }
+            VerificationError::InvalidSolution { nonce } => {
+                write!(f, "Solution for nonce '{}' is invalid", nonce)
+            }
+            VerificationError::MissingSolutionsData => {
+                write!(f, "Proof has no solutions_data")
+            }
+            VerificationError::MissingSolutionsMetaData => {
+                write!(f, "Benchmark has no solutions_meta_data")
+            }
+            VerificationError::NumSolutionsMismatch {
+                num_solutions,
+                num_meta_data,
+            } => write!(
+                f,
+                "Benchmark claims '{}' solutions but has '{}' solutions_meta_data",
+                num_solutions, num_meta_data
+            ),
+            VerificationError::SampledSolutionMismatch { nonce } => write!(
+                f,
+                "Regenerated solution for sampled nonce '{}' does not match its solutions_meta_data",
+                nonce
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerificationError::InvalidMerkleProof(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// mirrors the checks in tig-protocol's submit_benchmark and submit_proof that can be run without chain state
+pub fn verify_proof_locally(
+    solutions_data: &CompressedSolutionData,
+    benchmark: &Benchmark,
+) -> Result<(), VerificationError> {
+    let solutions_meta_data = benchmark
+        .solutions_meta_data
+        .as_ref()
+        .ok_or(VerificationError::MissingSolutionsMetaData)?;
+    if solutions_meta_data.len() != benchmark.details.num_solutions as usize {
+        return Err(VerificationError::NumSolutionsMismatch {
+            num_solutions: benchmark.details.num_solutions,
+            num_meta_data: solutions_meta_data.len(),
+        });
+    }
+
+    let mut seen = HashSet::new();
+    if let Some(d) = solutions_meta_data.iter().find(|d| !seen.insert(d.nonce)) {
+        return Err(VerificationError::DuplicateNonce { nonce: d.nonce });
+    }
+
+    if solutions_data.is_empty() {
+        return Err(VerificationError::MissingSolutionsData);
+    }
+    // the benchmark does not carry a merkle root yet, so there is no root to check the meta data
+    // against. each entry is checked against its own leaf instead. entries are decompressed one
+    // at a time
+    for d in solutions_data.iter() {
+        // a recomputed hash that differs is the usual reason a benchmark is flagged as fraud
+        verify_solution_hash(solutions_meta_data, &d)
+            .map_err(VerificationError::InvalidMerkleProof)?;
+        if verify_solution(&benchmark.settings, d.nonce, &d.solution).is_err() {
+            return Err(VerificationError::InvalidSolution { nonce: d.nonce });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tig_structs::core::*;
+    use tig_utils::CompressionConfig;
+
+    // a satisfiability instance without clauses, so that any assignment of its 50 variables is
+    // a valid solution
+    fn solution_data(nonce: u64, num_variables: usize) -> SolutionData {
+        let mut solution = Solution::new();
+        solution.insert("variables".to_string(), json!(vec![0; num_variables]));
+        SolutionData {
+            nonce,
+            runtime_signature: 1000 + nonce as u32,
+            fuel_consumed: 2000 + nonce,
+            solution,
+        }
+    }
+
+    fn compressed(solutions_data: &[SolutionData]) -> CompressedSolutionData {
+        let mut data = CompressedSolutionData::new(CompressionConfig::default());
+        data.extend(solutions_data.iter());
+        data
+    }
+
+    fn benchmark(solutions_data: &[SolutionData]) -> Benchmark {
+        Benchmark {
+            id: "benchmark".to_string(),
+            settings: BenchmarkSettings {
+                player_id: "player".to_string(),
+                block_id: "block".to_string(),
+                challenge_id: "c001".to_string(),
+                algorithm_id: "c001_a001".to_string(),
+                difficulty: vec![50, 0],
+            },
+            details: BenchmarkDetails {
+                block_started: 0,
+                num_solutions: solutions_data.len() as u32,
+            },
+            state: None,
+            solutions_meta_data: Some(
+                solutions_data
+                    .iter()
+                    .cloned()
+                    .map(SolutionMetaData::from)
+                    .collect(),
+            ),
+            solution_data: None,
+        }
+    }
+
+    fn valid() -> (CompressedSolutionData, Benchmark) {
+        let solutions_data: Vec<SolutionData> = (0..3).map(|n| solution_data(n, 50)).collect();
+        (compressed(&solutions_data), benchmark(&solutions_data))
+    }
+
+    #[test]
+    fn test_valid_proof() {
+        let (data, benchmark) = valid();
+        assert_eq!(verify_proof_locally(&data, &benchmark), Ok(()));
+    }
+
+    #[test]
+    fn test_missing_solutions_meta_data() {
+        let (data, mut benchmark) = valid();
+        benchmark.solutions_meta_data = None;
+        assert_eq!(
+            verify_proof_locally(&data, &benchmark),
+            Err(VerificationError::MissingSolutionsMetaData)
+        );
+    }
+
+    #[test]
+    fn test_num_solutions_mismatch() {
+        let (data, mut benchmark) = valid();
+        benchmark.details.num_solutions = 4;
+        assert_eq!(
+            verify_proof_locally(&data, &benchmark),
+            Err(VerificationError::NumSolutionsMismatch {
+                num_solutions: 4,
+                num_meta_data: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_duplicate_nonce() {
+        let solutions_data: Vec<SolutionData> =
+            [0, 1, 1].iter().map(|&n| solution_data(n, 50)).collect();
+        assert_eq!(
+            verify_proof_locally(&compressed(&solutions_data), &benchmark(&solutions_data)),
+            Err(VerificationError::DuplicateNonce { nonce: 1 })
+        );
+    }
+
+    #[test]
+    fn test_missing_solutions_data() {
+        let (_, benchmark) = valid();
+        assert_eq!(
+            verify_proof_locally(&compressed(&[]), &benchmark),
+            Err(VerificationError::MissingSolutionsData)
+        );
+    }
+
+    #[test]
+    fn test_hash_mismatch() {
+        let (_, benchmark) = valid();
+        let mut tampered = solution_data(1, 50);
+        tampered.fuel_consumed += 1;
+        assert!(matches!(
+            verify_proof_locally(&compressed(&[tampered]), &benchmark),
+            Err(VerificationError::InvalidMerkleProof(
+                ProofError::HashMismatch { index: 1, .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_nonce_not_in_meta_data() {
+        let (_, benchmark) = valid();
+        assert_eq!(
+            verify_proof_locally(&compressed(&[solution_data(7, 50)]), &benchmark),
+            Err(VerificationError::InvalidMerkleProof(
+                ProofError::NonceNotFound { nonce: 7 }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_solution() {
+        let solutions_data = vec![solution_data(0, 50), solution_data(1, 49)];
+        assert_eq!(
+            verify_proof_locally(&compressed(&solutions_data), &benchmark(&solutions_data)),
+            Err(VerificationError::InvalidSolution { nonce: 1 })
+        );
+    }
+}
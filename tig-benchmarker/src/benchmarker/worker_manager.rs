@@ -0,0 +1,440 @@
+use super::{config, BenchmarkerError, Job, NonceIterator, Result, RetryPolicy};
+use crate::future_utils::{spawn, time, Mutex};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    future::Future,
+    path::PathBuf,
+    process::Stdio,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tig_structs::{
+    config::WasmVMConfig,
+    core::{BenchmarkSettings, SolutionData},
+};
+use tig_utils::{dejsonify, jsonify};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    sync::{watch, Semaphore},
+    task::JoinHandle,
+};
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkerManagerConfig {
+    /// path to the tig-worker binary
+    pub binary: PathBuf,
+    /// worker processes running at the same time
+    pub num_workers: usize,
+    /// a worker whose resident memory exceeds this is killed and restarted. linux only
+    pub max_memory_mb: Option<u64>,
+    pub memory_poll_interval_ms: u64,
+    /// restarts per task before it is reported as failed
+    pub max_restarts: u32,
+    pub restart_policy: RetryPolicy,
+}
+
+impl Default for WorkerManagerConfig {
+    fn default() -> Self {
+        Self {
+            binary: PathBuf::from("tig-worker"),
+            num_workers: 1,
+            max_memory_mb: None,
+            memory_poll_interval_ms: 500,
+            max_restarts: 3,
+            restart_policy: RetryPolicy {
+                base_delay_ms: 500,
+                max_delay_ms: 10000,
+                min_jitter_ms: 0,
+                max_jitter_ms: 250,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerTask {
+    pub settings: BenchmarkSettings,
+    pub nonce: u64,
+    pub wasm_path: PathBuf,
+    pub wasm_vm_config: WasmVMConfig,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkerResult {
+    Solution(SolutionData),
+    NoSolution,
+    Failed { reason: String },
+    ShuttingDown,
+}
+
+enum Exit {
+    Finished {
+        found_solution: bool,
+        stdout: String,
+    },
+    Crashed(String),
+    ShuttingDown,
+}
+
+// runs each task in its own `tig-worker compute_solution` process. exit code 1 is the worker
+// reporting no (valid) solution; any other failure is a crash and the task is restarted
+#[derive(Clone)]
+pub struct WorkerManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: WorkerManagerConfig,
+    slots: Semaphore,
+    running: AtomicUsize,
+    shutdown_tx: watch::Sender<bool>,
+}
+
+struct RunningGuard<'a>(&'a AtomicUsize);
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl WorkerManager {
+    pub fn new(config: WorkerManagerConfig) -> Self {
+        let (shutdown_tx, _) = watch::channel(false);
+        Self {
+            inner: Arc::new(Inner {
+                slots: Semaphore::new(config.num_workers.max(1)),
+                config,
+                running: AtomicUsize::new(0),
+                shutdown_tx,
+            }),
+        }
+    }
+
+    // waits for a free worker slot, so at most `num_workers` processes run at once
+    pub fn submit_task(&self, task: WorkerTask) -> impl Future<Output = WorkerResult> {
+        let inner = self.inner.clone();
+        async move { inner.run(task).await }
+    }
+
+    // kills running workers and fails queued tasks with `ShuttingDown`
+    pub fn shutdown(&self) -> impl Future<Output = ()> {
+        let inner = self.inner.clone();
+        async move {
+            inner.slots.close();
+            inner.shutdown_tx.send_replace(true);
+            while inner.running.load(Ordering::SeqCst) > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    }
+}
+
+// computes the job's nonces in worker processes when `workers.binary` is configured, in place
+// of `run_benchmark::execute`. returns None if the nonces should be computed in-process
+pub fn start(
+    job: &Job,
+    wasm: &[u8],
+    nonce_iters: Vec<Arc<Mutex<NonceIterator>>>,
+    solutions_data: Arc<Mutex<Vec<SolutionData>>>,
+    solutions_count: Arc<Mutex<u32>>,
+) -> Result<Option<WorkerManager>> {
+    let workers = &config().workers;
+    let Some(binary) = workers.binary.clone() else {
+        return Ok(None);
+    };
+    // the worker loads the algorithm from disk
+    let wasm_path = std::env::temp_dir().join(format!("tig-{}.wasm", job.settings.algorithm_id));
+    std::fs::write(&wasm_path, wasm).map_err(BenchmarkerError::WorkerIo)?;
+    info!(
+        "Running {} workers of {}",
+        nonce_iters.len(),
+        binary.display()
+    );
+    let manager = WorkerManager::new(WorkerManagerConfig {
+        binary,
+        num_workers: nonce_iters.len(),
+        max_memory_mb: workers.max_memory_mb,
+        max_restarts: workers.max_restarts,
+        ..Default::default()
+    });
+    for nonce_iter in nonce_iters {
+        let manager = manager.clone();
+        let job = job.clone();
+        let wasm_path = wasm_path.clone();
+        let solutions_data = solutions_data.clone();
+        let solutions_count = solutions_count.clone();
+        spawn(async move {
+            loop {
+                let next = nonce_iter.lock().await.next();
+                let Some(nonce) = next else {
+                    break;
+                };
+                let task = WorkerTask {
+                    settings: job.settings.clone(),
+                    nonce,
+                    wasm_path: wasm_path.clone(),
+                    wasm_vm_config: job.wasm_vm_config.clone(),
+                };
+                match manager.submit_task(task).await {
+                    WorkerResult::Solution(solution_data) => {
+                        *solutions_count.lock().await += 1;
+                        if solution_data.calc_solution_signature()
+                            <= job.solution_signature_threshold
+                        {
+                            solutions_data.lock().await.push(solution_data);
+                        }
+                    }
+                    WorkerResult::NoSolution => {}
+                    WorkerResult::Failed { reason } => {
+                        warn!("Nonce {} failed: {}", nonce, reason)
+                    }
+                    WorkerResult::ShuttingDown => break,
+                }
+            }
+        });
+    }
+    Ok(Some(manager))
+}
+
+impl Inner {
+    async fn run(&self, task: WorkerTask) -> WorkerResult {
+        let _slot = match self.slots.acquire().await {
+            Ok(slot) => slot,
+            Err(_) => return WorkerResult::ShuttingDown,
+        };
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let _running = RunningGuard(&self.running);
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        let mut rng = StdRng::seed_from_u64(time());
+        let mut restarts = 0;
+        loop {
+            let reason = match self.run_once(&task).await {
+                Exit::Finished {
+                    found_solution: true,
+                    stdout,
+                } => {
+                    return match dejsonify::<SolutionData>(stdout.trim()) {
+                        Ok(solution_data) => WorkerResult::Solution(solution_data),
+                        Err(e) => WorkerResult::Failed {
+                            reason: format!("Failed to parse worker output: {}", e),
+                        },
+                    }
+                }
+                Exit::Finished { .. } => return WorkerResult::NoSolution,
+                Exit::ShuttingDown => return WorkerResult::ShuttingDown,
+                Exit::Crashed(reason) => reason,
+            };
+            if restarts >= self.config.max_restarts {
+                return WorkerResult::Failed { reason };
+            }
+            restarts += 1;
+            let delay_ms = self.config.restart_policy.delay_ms(restarts, &mut rng);
+            warn!(
+                "Worker for nonce {} crashed: {}. Restarting in {} ms",
+                task.nonce, reason, delay_ms
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(delay_ms)) => {}
+                _ = shutdown_rx.wait_for(|shutting_down| *shutting_down) => {
+                    return WorkerResult::ShuttingDown;
+                }
+            }
+        }
+    }
+
+    async fn run_once(&self, task: &WorkerTask) -> Exit {
+        let mut child = match Command::new(&self.config.binary)
+            .arg("compute_solution")
+            .arg(jsonify(&task.settings))
+            .arg(task.nonce.to_string())
+            .arg(&task.wasm_path)
+            .arg("--fuel")
+            .arg(task.wasm_vm_config.max_fuel.to_string())
+            .arg("--mem")
+            .arg(task.wasm_vm_config.max_memory.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                return Exit::Crashed(format!(
+                    "Failed to spawn {}: {}",
+                    self.config.binary.display(),
+                    e
+                ))
+            }
+        };
+        let pid = child.id();
+        let stdout = forward(child.stdout.take(), pid, "stdout");
+        let _stderr = forward(child.stderr.take(), pid, "stderr");
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        let mut memory_check =
+            tokio::time::interval(Duration::from_millis(self.config.memory_poll_interval_ms));
+        let exit = loop {
+            tokio::select! {
+                status = child.wait() => break match status {
+                    Ok(status) => match status.code() {
+                        Some(0) | Some(1) => Exit::Finished {
+                            found_solution: status.success(),
+                            stdout: String::new(),
+                        },
+                        _ => Exit::Crashed(format!("Worker {}", status)),
+                    },
+                    Err(e) => Exit::Crashed(format!("Failed to wait for worker: {}", e)),
+                },
+                _ = shutdown_rx.wait_for(|shutting_down| *shutting_down) => {
+                    let _ = child.kill().await;
+                    break Exit::ShuttingDown;
+                }
+                _ = memory_check.tick() => {
+                    let (Some(max_memory_mb), Some(pid)) = (self.config.max_memory_mb, pid) else {
+                        continue;
+                    };
+                    match resident_memory_mb(pid) {
+                        Some(memory_mb) if memory_mb > max_memory_mb => {
+                            let _ = child.kill().await;
+                            break Exit::Crashed(format!(
+                                "Worker used {} MB, exceeding max_memory_mb {}",
+                                memory_mb, max_memory_mb
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        };
+        match exit {
+            Exit::Finished { found_solution, .. } => Exit::Finished {
+                found_solution,
+                stdout: stdout.await.unwrap_or_default(),
+            },
+            exit => exit,
+        }
+    }
+}
+
+// logs every line the worker writes and returns all of it
+fn forward<R>(pipe: Option<R>, pid: Option<u32>, stream: &'static str) -> JoinHandle<String>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut output = String::new();
+        if let Some(pipe) = pipe {
+            let mut lines = BufReader::new(pipe).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!(worker = ?pid, stream, "{}", line);
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        output
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn resident_memory_mb(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    parse_vm_rss_kb(&status).map(|kb| kb / 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory_mb(_pid: u32) -> Option<u64> {
+    None
+}
+
+fn parse_vm_rss_kb(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    // stands in for tig-worker
+    fn fake_worker(name: &str, script: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tig-fake-worker-{}-{}", name, time()));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn manager(binary: PathBuf) -> WorkerManager {
+        WorkerManager::new(WorkerManagerConfig {
+            binary,
+            restart_policy: RetryPolicy {
+                base_delay_ms: 1,
+                max_delay_ms: 1,
+                min_jitter_ms: 0,
+                max_jitter_ms: 0,
+            },
+            ..Default::default()
+        })
+    }
+
+    fn task() -> WorkerTask {
+        WorkerTask {
+            settings: BenchmarkSettings {
+                player_id: String::new(),
+                block_id: String::new(),
+                challenge_id: String::new(),
+                algorithm_id: String::new(),
+                difficulty: vec![],
+            },
+            nonce: 7,
+            wasm_path: PathBuf::from("algorithm.wasm"),
+            wasm_vm_config: WasmVMConfig {
+                max_memory: 0,
+                max_fuel: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_vm_rss() {
+        let status = "Name:\ttig-worker\nVmPeak:\t  204800 kB\nVmRSS:\t  102400 kB\n";
+        assert_eq!(parse_vm_rss_kb(status), Some(102400));
+        assert_eq!(parse_vm_rss_kb("Name:\ttig-worker\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_exit_code_1_is_no_solution() {
+        let binary = fake_worker("no-solution", "echo 'No solution found' >&2\nexit 1");
+        assert_eq!(
+            manager(binary).submit_task(task()).await,
+            WorkerResult::NoSolution
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restarts_crashed_worker() {
+        let marker = std::env::temp_dir().join(format!("tig-fake-worker-marker-{}", time()));
+        let binary = fake_worker(
+            "crash-once",
+            &format!(
+                "if [ -f {0} ]; then\n  echo '{{\"nonce\":7,\"runtime_signature\":1,\"fuel_consumed\":2,\"solution\":{{}}}}'\n  exit 0\nfi\ntouch {0}\nkill -9 $$",
+                marker.display()
+            ),
+        );
+        match manager(binary).submit_task(task()).await {
+            WorkerResult::Solution(solution_data) => assert_eq!(solution_data.nonce, 7),
+            other => panic!("expected Solution, got {:?}", other),
+        }
+    }
+}
@@ -11,6 +11,8 @@ mod utils {
     pub use tokio::sync::Mutex;
     use tokio::{join, task, time};
 
+    pub type BoxFuture<T> = futures::future::BoxFuture<'static, T>;
+
     pub async fn join<T, U, V, W>(
         a: impl Future<Output = T> + 'static,
         b: impl Future<Output = U> + 'static,
@@ -38,6 +40,16 @@ mod utils {
         time::sleep(time::Duration::from_millis(ms as u64)).await;
     }
 
+    pub async fn timeout<T>(ms: u32, f: impl Future<Output = T>) -> Result<T, ()> {
+        time::timeout(time::Duration::from_millis(ms as u64), f)
+            .await
+            .map_err(|_| ())
+    }
+
+    pub async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+        task::spawn_blocking(f).await.unwrap()
+    }
+
     pub fn time() -> u64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -50,6 +62,8 @@ mod utils {
 mod utils {
     use super::*;
     pub use futures::lock::Mutex;
+    pub type BoxFuture<T> = futures::future::LocalBoxFuture<'static, T>;
+    use futures::future::{select, Either};
     use gloo_timers::future::TimeoutFuture;
     use js_sys::{Array, Date, Promise};
     use serde_wasm_bindgen::{from_value, to_value};
@@ -113,6 +127,18 @@ mod utils {
         TimeoutFuture::new(ms).await;
     }
 
+    pub async fn timeout<T>(ms: u32, f: impl Future<Output = T>) -> Result<T, ()> {
+        match select(Box::pin(f), TimeoutFuture::new(ms)).await {
+            Either::Left((value, _)) => Ok(value),
+            Either::Right(_) => Err(()),
+        }
+    }
+
+    // there are no threads to move it to, so it runs in place
+    pub async fn spawn_blocking<T>(f: impl FnOnce() -> T) -> T {
+        f()
+    }
+
     pub fn time() -> u64 {
         Date::now() as u64
     }
@@ -29,6 +29,19 @@ mod exports {
 
     #[wasm_bindgen]
     pub async fn setup(api_url: String, api_key: String, player_id: String) {
-        benchmarker::setup(api_url, api_key, player_id.to_string()).await;
+        benchmarker::setup(
+            benchmarker::BenchmarkerConfig {
+                api: benchmarker::ApiConfig {
+                    endpoints: vec![api_url],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            api_key,
+            player_id.to_string(),
+            None,
+            None,
+        )
+        .await;
     }
 }
@@ -3,18 +3,94 @@
 
 mod benchmarker;
 mod future_utils;
-use benchmarker::{Job, NonceIterator};
-use clap::{value_parser, Arg, Command};
+use benchmarker::{
+    BenchmarkId, BenchmarkerConfig, HistoryStore, Job, NonceIterator, SubmissionTiming,
+};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
 use future_utils::{sleep, Mutex};
 use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
 use tig_structs::core::*;
 use tig_utils::{dejsonify, get, jsonify, post};
+use tracing::{debug, error, info, Subscriber};
+use tracing_subscriber::{
+    layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, Layer,
+};
 use warp::Filter;
 
 fn cli() -> Command {
     Command::new("TIG Benchmarker")
         .about("Standalone benchmarker")
         .arg_required_else_help(true)
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            Command::new("history")
+                .about("Prints the most recently submitted benchmarks and their outcomes")
+                .arg(
+                    Arg::new("config")
+                        .long("config")
+                        .help("(Optional) Set path to TOML config file. History is read from its [history] path")
+                        .env("TIG_CONFIG")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .help("(Optional) Set number of entries to print")
+                        .default_value("20")
+                        .value_parser(value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("timings")
+                .about("Prints how long each phase of the most recent benchmark submissions took")
+                .arg(
+                    Arg::new("master")
+                        .long("master")
+                        .help("(Optional) Set hostname of the running master node")
+                        .default_value("localhost")
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .help("(Optional) Set port for cluster communication of the master node")
+                        .default_value("5115")
+                        .value_parser(value_parser!(u16)),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .help("(Optional) Set number of entries to print")
+                        .default_value("20")
+                        .value_parser(value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("Checks the config, API reachability and protocol version without submitting anything")
+                .arg(
+                    Arg::new("config")
+                        .long("config")
+                        .help("(Optional) Set path to TOML config file")
+                        .env("TIG_CONFIG")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("api")
+                        .long("api")
+                        .help("(Optional) Set api_url. Ignored if the config file lists [api] endpoints")
+                        .default_value("https://mainnet-api.tig.foundation")
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    Arg::new("api-key")
+                        .long("api-key")
+                        .help("(Optional) Set API key sent with the checks")
+                        .default_value("")
+                        .value_parser(value_parser!(String)),
+                ),
+        )
         .arg(
             Arg::new("PLAYER_ID")
                 .help("Your wallet address")
@@ -50,7 +126,7 @@ fn cli() -> Command {
         .arg(
             Arg::new("api")
                 .long("api")
-                .help("(Optional) Set api_url")
+                .help("(Optional) Set api_url. Ignored if the config file lists [api] endpoints")
                 .default_value("https://mainnet-api.tig.foundation")
                 .value_parser(value_parser!(String)),
         )
@@ -74,11 +150,90 @@ fn cli() -> Command {
                 .default_value("5000000")
                 .value_parser(value_parser!(u64)),
         )
+        .arg(
+            Arg::new("snapshot")
+                .long("snapshot")
+                .help("(Optional) Set path to file for persisting benchmarker state across restarts")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("(Optional) Set path to TOML config file with [api], [submission], [scheduler], [metrics], [storage], [selection] and [history] sections")
+                .env("TIG_CONFIG")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("(Optional) Build and verify benchmark submissions without sending them")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+// RUST_LOG sets the level filter (default info). LOG_FORMAT=json switches to json lines.
+// spans are exported over OTLP when OTEL_EXPORTER_OTLP_ENDPOINT is set
+fn init_logging() {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let fmt = if std::env::var("LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("json")) {
+        tracing_subscriber::fmt::layer().json().boxed()
+    } else {
+        tracing_subscriber::fmt::layer().boxed()
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(otel_layer())
+        .init();
+}
+
+// none when the endpoint is unset, so spans stay local to the fmt layer. the exporter reads
+// the endpoint and the other OTEL_EXPORTER_OTLP_* variables itself
+fn otel_layer<S>() -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT")?;
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+        .with_trace_config(opentelemetry_sdk::trace::config().with_resource(
+            opentelemetry_sdk::Resource::new(vec![opentelemetry::KeyValue::new(
+                "service.name",
+                "tig-benchmarker",
+            )]),
+        ))
+        .install_batch(opentelemetry_sdk::runtime::Tokio);
+    match tracer {
+        Ok(tracer) => Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+        Err(e) => {
+            // logging is not initialised yet
+            eprintln!("Failed to initialise OTLP exporter: {}", e);
+            None
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
+    init_logging();
     let matches = cli().get_matches();
+    match matches.subcommand() {
+        Some(("history", history_matches)) => {
+            print_history(history_matches);
+            return;
+        }
+        Some(("timings", timings_matches)) => {
+            print_timings(timings_matches).await;
+            return;
+        }
+        Some(("validate", validate_matches)) => {
+            run_validate(validate_matches).await;
+            return;
+        }
+        _ => {}
+    }
 
     let algorithms_path = matches.get_one::<PathBuf>("ALGORITHMS_SELECTION").unwrap();
     let num_workers = *matches.get_one::<u32>("workers").unwrap();
@@ -88,21 +243,167 @@ async fn main() {
     let api_key = matches.get_one::<String>("API_KEY").unwrap().clone();
     let player_id = matches.get_one::<String>("PLAYER_ID").unwrap().clone();
     let nonce_offset = matches.get_one::<u64>("offset").unwrap().clone();
+    let snapshot_path = matches.get_one::<PathBuf>("snapshot").cloned();
+    let mut config = load_config(matches.get_one::<PathBuf>("config"));
+    if matches.get_flag("dry-run") {
+        config.submission.dry_run = true;
+    }
+    if config.api.endpoints.is_empty() {
+        config.api.endpoints.push(api_url);
+    }
+    if let Err(e) = config.validate() {
+        error!("{}", e);
+        std::process::exit(1);
+    }
     if let Some(master) = matches.get_one::<String>("master") {
         slave_node(master, port, num_workers).await;
     } else {
-        master_node(
-            api_url,
-            api_key,
-            player_id,
-            num_workers,
-            duration,
-            algorithms_path,
-            port,
-            nonce_offset,
-        )
-        .await
+        tokio::select! {
+            _ = master_node(
+                config,
+                api_key,
+                player_id,
+                num_workers,
+                duration,
+                algorithms_path,
+                port,
+                nonce_offset,
+                snapshot_path,
+            ) => {}
+            _ = shutdown_requested() => {
+                if let Err(e) = benchmarker::shutdown().await {
+                    error!("Error during shutdown: {}", e);
+                }
+                // flushes spans the batch exporter has not sent yet
+                opentelemetry::global::shutdown_tracer_provider();
+                std::process::exit(0);
+            }
+        }
+    }
+}
+
+fn load_config(path: Option<&PathBuf>) -> BenchmarkerConfig {
+    match path {
+        Some(path) => match BenchmarkerConfig::from_toml_file(path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => BenchmarkerConfig::default(),
+    }
+}
+
+fn print_history(matches: &ArgMatches) {
+    let config = load_config(matches.get_one::<PathBuf>("config"));
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let path = config.history.path.unwrap_or_else(|| {
+        error!("No [history] path is set in the config");
+        std::process::exit(1);
+    });
+    let history = HistoryStore::open(&path).and_then(|h| Ok((h.last(limit)?, h.summary()?)));
+    let (entries, summary) = history.unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    });
+    println!(
+        "{:<32}  {:>13}  {:>8}  {:>8}  {:>10}  {}",
+        "BENCHMARK", "SUBMITTED_AT", "BLOCK", "VERIFIED", "REWARD", "FRAUD_REASON"
+    );
+    for entry in entries {
+        println!(
+            "{:<32}  {:>13}  {:>8}  {:>8}  {:>10}  {}",
+            entry.benchmark_id,
+            entry.submitted_at,
+            entry.block_height,
+            entry.verified,
+            entry.reward.map_or("-".to_string(), |r| r.to_string()),
+            entry.fraud_reason.unwrap_or_default()
+        );
+    }
+    println!(
+        "\n{} benchmarks, {:.1}% verified, {} total reward",
+        summary.num_entries,
+        summary.success_rate() * 100.0,
+        summary.total_reward
+    );
+}
+
+// timings are only kept in memory, so they are read from the running master node
+async fn print_timings(matches: &ArgMatches) {
+    let master = matches.get_one::<String>("master").unwrap();
+    let port = *matches.get_one::<u16>("port").unwrap();
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let url = format!("http://{}:{}/timings/{}", master, port, limit);
+    let timings = match get::<String>(&url, None).await {
+        Ok(resp) => dejsonify::<Vec<SubmissionTiming>>(&resp).unwrap_or_else(|e| {
+            error!("Failed to parse timings from {}: {}", url, e);
+            std::process::exit(1);
+        }),
+        Err(e) => {
+            error!("Failed to get timings from {}: {:?}", url, e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "{:<32}  {:>16}  {:>14}  {:>12}  {:>12}  {}",
+        "BENCHMARK",
+        "SAMPLE_VERIFY_US",
+        "PROOF_FETCH_US",
+        "REQ_BUILD_US",
+        "TOTAL_US",
+        "ATTEMPTS_US"
+    );
+    for timing in timings {
+        println!(
+            "{:<32}  {:>16}  {:>14}  {:>12}  {:>12}  {}",
+            timing.benchmark_id,
+            timing.sample_verify_us,
+            timing.proof_fetch_us,
+            timing.req_build_us,
+            timing.total_us,
+            timing
+                .attempt_durations_us
+                .iter()
+                .map(|us| us.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        );
+    }
+}
+
+// exits non-zero if any check failed
+async fn run_validate(matches: &ArgMatches) {
+    let mut config = load_config(matches.get_one::<PathBuf>("config"));
+    if config.api.endpoints.is_empty() {
+        config
+            .api
+            .endpoints
+            .push(matches.get_one::<String>("api").unwrap().clone());
+    }
+    let api_key = matches.get_one::<String>("api-key").unwrap().clone();
+    let report = benchmarker::validate(&config, api_key).await;
+    print!("{}", report);
+    if !report.passed() {
+        std::process::exit(1);
+    }
+}
+
+async fn shutdown_requested() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = sigterm.recv() => {}
+        }
     }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to install SIGINT handler");
 }
 
 async fn slave_node(master: &String, port: u16, num_workers: u32) {
@@ -116,14 +417,14 @@ async fn slave_node(master: &String, port: u16, num_workers: u32) {
         let next_job = match get::<String>(&format!("{}/job", master_url), None).await {
             Ok(resp) => dejsonify::<Option<Job>>(&resp).unwrap(),
             Err(e) => {
-                println!("Error getting job: {:?}", e);
+                error!("Error getting job: {:?}", e);
                 sleep(5000).await;
                 continue;
             }
         };
 
         if job != next_job {
-            println!("Ending job");
+            info!("Ending job");
 
             for nonce_iter in nonce_iters.iter() {
                 (*(*nonce_iter).lock().await).empty();
@@ -137,21 +438,21 @@ async fn slave_node(master: &String, port: u16, num_workers: u32) {
                 .is_some_and(|x| x.sampled_nonces.is_none())
             {
                 let job = next_job.as_ref().unwrap();
-                println!("Starting new job: {:?}", job);
-                println!(
+                info!("Starting new job: {:?}", job);
+                info!(
                     "Downloading algorithm {}",
                     job.download_url.split("/").last().unwrap()
                 );
                 let wasm = match benchmarker::download_wasm::execute(job).await {
                     Ok(wasm) => wasm,
                     Err(e) => {
-                        println!("Error downloading wasm: {:?}", e);
+                        error!("Error downloading wasm: {:?}", e);
                         sleep(5000).await;
                         continue;
                     }
                 };
 
-                println!("Getting nonce offset from master");
+                info!("Getting nonce offset from master");
                 let offset = match get::<String>(
                     &format!("{}/nonce_offset/{:?}", master_url, hostname::get().unwrap()),
                     None,
@@ -160,12 +461,12 @@ async fn slave_node(master: &String, port: u16, num_workers: u32) {
                 {
                     Ok(resp) => dejsonify::<u64>(&resp).unwrap(),
                     Err(e) => {
-                        println!("Error getting nonce offset: {:?}", e);
+                        error!("Error getting nonce offset: {:?}", e);
                         sleep(5000).await;
                         continue;
                     }
                 };
-                println!("Got nonce offset: {}", offset);
+                info!("Got nonce offset: {}", offset);
 
                 // variables that are shared by workers
                 nonce_iters = (0..num_workers)
@@ -176,7 +477,7 @@ async fn slave_node(master: &String, port: u16, num_workers: u32) {
                         )))
                     })
                     .collect();
-                println!("Starting benchmark");
+                info!("Starting benchmark");
                 benchmarker::run_benchmark::execute(
                     nonce_iters.iter().cloned().collect(),
                     job,
@@ -191,12 +492,12 @@ async fn slave_node(master: &String, port: u16, num_workers: u32) {
         }
         if job.as_ref().is_some_and(|x| x.sampled_nonces.is_none()) {
             let job = job.as_ref().unwrap();
-            let mut solutions_data = solutions_data.lock().await;
-            let n = solutions_data.len();
+            // taken out of the lock so workers are not blocked while the solutions are posted
+            let data: Vec<SolutionData> = solutions_data.lock().await.drain(..).collect();
+            let n = data.len();
             if n > 0 {
                 num_solutions += n as u32;
-                let data: Vec<SolutionData> = solutions_data.drain(..).collect();
-                println!("Posting {} solutions", n);
+                debug!("Posting {} solutions", n);
                 if let Err(e) = post::<String>(
                     &format!("{}/solutions_data/{}", master_url, job.benchmark_id),
                     &jsonify(&data),
@@ -207,7 +508,7 @@ async fn slave_node(master: &String, port: u16, num_workers: u32) {
                 )
                 .await
                 {
-                    println!("Error posting solutions data: {:?}", e);
+                    error!("Error posting solutions data: {:?}", e);
                     sleep(5000).await;
                     continue;
                 }
@@ -217,20 +518,20 @@ async fn slave_node(master: &String, port: u16, num_workers: u32) {
                 let nonce_iter = (*nonce_iter).lock().await;
                 num_attempts += nonce_iter.attempts();
             }
-            println!(
+            info!(
                 "Computed {} solutions out of {} instances",
                 num_solutions, num_attempts
             );
             sleep(100).await;
         } else {
-            println!("No job, sleeping 100ms");
+            debug!("No job, sleeping 100ms");
             sleep(100).await;
         }
     }
 }
 
 async fn master_node(
-    api_url: String,
+    config: BenchmarkerConfig,
     api_key: String,
     player_id: String,
     num_workers: u32,
@@ -238,9 +539,26 @@ async fn master_node(
     algorithms_path: &PathBuf,
     port: u16,
     nonce_offset: u64,
+    snapshot_path: Option<PathBuf>,
 ) {
-    benchmarker::setup(api_url, api_key, player_id).await;
+    let metrics_config = config.metrics.clone();
+    benchmarker::setup(config, api_key, player_id, snapshot_path, None).await;
     benchmarker::start(num_workers, duration).await;
+    if metrics_config.enabled {
+        let metrics = benchmarker::state().lock().await.metrics.clone();
+        future_utils::spawn(async move {
+            let get_metrics = warp::path("metrics").and(warp::get()).map(move || {
+                warp::reply::with_header(
+                    metrics.encode(),
+                    "content-type",
+                    "text/plain; version=0.0.4",
+                )
+            });
+            warp::serve(get_metrics)
+                .run(([0, 0, 0, 0], metrics_config.port))
+                .await;
+        });
+    }
     future_utils::spawn(async move {
         let offsets = Arc::new(Mutex::new(HashMap::new()));
         let get_nonce_offset = warp::path!("nonce_offset" / String)
@@ -260,11 +578,18 @@ async fn master_node(
             let state = (*benchmarker::state()).lock().await;
             Ok::<_, warp::Rejection>(warp::reply::json(&state.job))
         });
-        let post_solutions_data = warp::path!("solutions_data" / String)
+        let get_timings =
+            warp::path!("timings" / usize)
+                .and(warp::get())
+                .and_then(|n: usize| async move {
+                    let timings = benchmarker::state().lock().await.timings.clone();
+                    Ok::<_, warp::Rejection>(warp::reply::json(&timings.last(n)))
+                });
+        let post_solutions_data = warp::path!("solutions_data" / BenchmarkId)
             .and(warp::post())
             .and(warp::body::json())
             .and_then(
-                |benchmark_id: String, mut solutions_data: Vec<SolutionData>| async move {
+                |benchmark_id: BenchmarkId, mut solutions_data: Vec<SolutionData>| async move {
                     benchmarker::drain_solutions(&benchmark_id, &mut solutions_data).await;
                     Ok::<_, warp::Rejection>(warp::reply::with_status(
                         "SolutionsData received",
@@ -272,9 +597,14 @@ async fn master_node(
                     ))
                 },
             );
-        warp::serve(get_nonce_offset.or(get_job).or(post_solutions_data))
-            .run(([0, 0, 0, 0], port))
-            .await;
+        warp::serve(
+            get_nonce_offset
+                .or(get_job)
+                .or(get_timings)
+                .or(post_solutions_data),
+        )
+        .run(([0, 0, 0, 0], port))
+        .await;
     });
     loop {
         let selection = serde_json::from_str::<HashMap<String, String>>(
@@ -0,0 +1,169 @@
+use sha3::{Digest, Keccak256};
+use tig_structs::core::{SolutionData, SolutionMetaData};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProofError {
+    EmptySolutionsMetaData,
+    HashMismatch {
+        index: usize,
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+    InvalidSolutionSignature {
+        nonce: u64,
+        expected_signature: u32,
+        actual_signature: u32,
+    },
+    NonceNotFound {
+        nonce: u64,
+    },
+    RootMismatch {
+        expected: [u8; 32],
+        actual: [u8; 32],
+    },
+}
+
+impl std::fmt::Display for ProofError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProofError::EmptySolutionsMetaData => write!(f, "solutions_meta_data is empty"),
+            ProofError::HashMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Solution data hashes to '{}' but solutions_meta_data[{}] hashes to '{}'",
+                hex::encode(actual),
+                index,
+                hex::encode(expected)
+            ),
+            ProofError::InvalidSolutionSignature {
+                nonce,
+                expected_signature,
+                actual_signature,
+            } => write!(
+                f,
+                "Solution data for nonce '{}' has signature '{}' but its leaf has '{}'",
+                nonce, actual_signature, expected_signature
+            ),
+            ProofError::NonceNotFound { nonce } => {
+                write!(f, "Nonce '{}' is not a leaf of solutions_meta_data", nonce)
+            }
+            ProofError::RootMismatch { expected, actual } => write!(
+                f,
+                "Merkle root '{}' does not match expected root '{}'",
+                hex::encode(actual),
+                hex::encode(expected)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+// the hash a solutions_meta_data entry is committed to the merkle tree with
+pub fn hash_leaf(d: &SolutionMetaData) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(d.nonce.to_le_bytes());
+    hasher.update(d.solution_signature.to_le_bytes());
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// the leaf a solution is committed to, hashed the same way as by the on-chain verifier
+pub fn calc_solution_hash(solution_data: &SolutionData) -> [u8; 32] {
+    hash_leaf(&SolutionMetaData {
+        nonce: solution_data.nonce,
+        solution_signature: solution_data.calc_solution_signature(),
+    })
+}
+
+// recomputes the hash of `solution_data` and compares it with that of its entry in
+// `solutions_meta_data`
+pub fn verify_solution_hash(
+    solutions_meta_data: &[SolutionMetaData],
+    solution_data: &SolutionData,
+) -> Result<(), ProofError> {
+    let (index, leaf) = solutions_meta_data
+        .iter()
+        .enumerate()
+        .find(|(_, d)| d.nonce == solution_data.nonce)
+        .ok_or(ProofError::NonceNotFound {
+            nonce: solution_data.nonce,
+        })?;
+    let expected = hash_leaf(leaf);
+    let actual = calc_solution_hash(solution_data);
+    if actual != expected {
+        return Err(ProofError::HashMismatch {
+            index,
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+// odd nodes are carried up rather than paired with themselves, so appending a copy of the
+// last leaf changes the root
+pub fn calc_solutions_merkle_root(
+    solutions_meta_data: &[SolutionMetaData],
+) -> Result<[u8; 32], ProofError> {
+    if solutions_meta_data.is_empty() {
+        return Err(ProofError::EmptySolutionsMetaData);
+    }
+    let mut level: Vec<[u8; 32]> = solutions_meta_data.iter().map(hash_leaf).collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(left, right),
+                [node] => *node,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    Ok(level[0])
+}
+
+// checks that `solutions_meta_data` commits to `root` and that `solution_data` is one of its
+// leaves. the leaf check mirrors tig-protocol's signature check for submitted solution data
+pub fn verify_solutions_merkle(
+    root: &[u8; 32],
+    solutions_meta_data: &[SolutionMetaData],
+    solution_data: &SolutionData,
+) -> Result<(), ProofError> {
+    let actual = calc_solutions_merkle_root(solutions_meta_data)?;
+    if actual != *root {
+        return Err(ProofError::RootMismatch {
+            expected: *root,
+            actual,
+        });
+    }
+    let leaf = solutions_meta_data
+        .iter()
+        .find(|d| d.nonce == solution_data.nonce)
+        .ok_or(ProofError::NonceNotFound {
+            nonce: solution_data.nonce,
+        })?;
+    let actual_signature = solution_data.calc_solution_signature();
+    if leaf.solution_signature != actual_signature {
+        return Err(ProofError::InvalidSolutionSignature {
+            nonce: solution_data.nonce,
+            expected_signature: leaf.solution_signature,
+            actual_signature,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use serde_json::json;
+use tig_proof::{
+    calc_solution_hash, calc_solutions_merkle_root, verify_solution_hash, verify_solutions_merkle,
+    ProofError,
+};
+use tig_structs::core::{Solution, SolutionData, SolutionMetaData};
+
+fn solutions_data() -> Vec<SolutionData> {
+    (0..5)
+        .map(|nonce| {
+            let mut solution = Solution::new();
+            solution.insert("variables".to_string(), json!([nonce, nonce + 1]));
+            SolutionData {
+                nonce,
+                runtime_signature: 1000 + nonce as u32,
+                fuel_consumed: 2000 + nonce,
+                solution,
+            }
+        })
+        .collect()
+}
+
+fn solutions_meta_data(solutions_data: &[SolutionData]) -> Vec<SolutionMetaData> {
+    solutions_data
+        .iter()
+        .cloned()
+        .map(SolutionMetaData::from)
+        .collect()
+}
+
+fn is_root_mismatch(result: Result<(), ProofError>) -> bool {
+    matches!(result, Err(ProofError::RootMismatch { .. }))
+}
+
+#[test]
+fn test_valid_proof() {
+    let solutions_data = solutions_data();
+    let meta_data = solutions_meta_data(&solutions_data);
+    let root = calc_solutions_merkle_root(&meta_data).unwrap();
+    for d in solutions_data.iter() {
+        assert_eq!(verify_solutions_merkle(&root, &meta_data, d), Ok(()));
+    }
+}
+
+#[test]
+fn test_wrong_root() {
+    let solutions_data = solutions_data();
+    let meta_data = solutions_meta_data(&solutions_data);
+    let mut root = calc_solutions_merkle_root(&meta_data).unwrap();
+    root[0] ^= 1;
+    assert!(is_root_mismatch(verify_solutions_merkle(
+        &root,
+        &meta_data,
+        &solutions_data[0]
+    )));
+}
+
+#[test]
+fn test_swapped_leaves() {
+    let solutions_data = solutions_data();
+    let mut meta_data = solutions_meta_data(&solutions_data);
+    let root = calc_solutions_merkle_root(&meta_data).unwrap();
+    meta_data.swap(1, 2);
+    assert!(is_root_mismatch(verify_solutions_merkle(
+        &root,
+        &meta_data,
+        &solutions_data[0]
+    )));
+}
+
+#[test]
+fn test_extra_leaf() {
+    let solutions_data = solutions_data();
+    let mut meta_data = solutions_meta_data(&solutions_data);
+    let root = calc_solutions_merkle_root(&meta_data).unwrap();
+    // a copy of the last leaf must not produce the same root
+    meta_data.push(meta_data.last().unwrap().clone());
+    assert!(is_root_mismatch(verify_solutions_merkle(
+        &root,
+        &meta_data,
+        &solutions_data[0]
+    )));
+}
+
+#[test]
+fn test_truncated_leaves() {
+    let solutions_data = solutions_data();
+    let mut meta_data = solutions_meta_data(&solutions_data);
+    let root = calc_solutions_merkle_root(&meta_data).unwrap();
+    meta_data.pop();
+    assert!(is_root_mismatch(verify_solutions_merkle(
+        &root,
+        &meta_data,
+        &solutions_data[0]
+    )));
+}
+
+#[test]
+fn test_corrupted_solution_data() {
+    let solutions_data = solutions_data();
+    let meta_data = solutions_meta_data(&solutions_data);
+    let root = calc_solutions_merkle_root(&meta_data).unwrap();
+    let mut corrupted = solutions_data[3].clone();
+    corrupted
+        .solution
+        .insert("variables".to_string(), json!([0, 0]));
+    assert!(matches!(
+        verify_solutions_merkle(&root, &meta_data, &corrupted),
+        Err(ProofError::InvalidSolutionSignature { nonce: 3, .. })
+    ));
+}
+
+#[test]
+fn test_solution_hash_mismatch() {
+    let solutions_data = solutions_data();
+    let meta_data = solutions_meta_data(&solutions_data);
+    for d in solutions_data.iter() {
+        assert_eq!(verify_solution_hash(&meta_data, d), Ok(()));
+    }
+    let mut corrupted = solutions_data[3].clone();
+    corrupted
+        .solution
+        .insert("variables".to_string(), json!([0, 0]));
+    match verify_solution_hash(&meta_data, &corrupted) {
+        Err(ProofError::HashMismatch {
+            index,
+            expected,
+            actual,
+        }) => {
+            assert_eq!(index, 3);
+            assert_eq!(expected, calc_solution_hash(&solutions_data[3]));
+            assert_eq!(actual, calc_solution_hash(&corrupted));
+        }
+        other => panic!("expected HashMismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_nonce_not_in_tree() {
+    let solutions_data = solutions_data();
+    let meta_data = solutions_meta_data(&solutions_data[..4]);
+    let root = calc_solutions_merkle_root(&meta_data).unwrap();
+    assert_eq!(
+        verify_solutions_merkle(&root, &meta_data, &solutions_data[4]),
+        Err(ProofError::NonceNotFound { nonce: 4 })
+    );
+}
+
+#[test]
+fn test_empty_meta_data() {
+    assert_eq!(
+        calc_solutions_merkle_root(&[]),
+        Err(ProofError::EmptySolutionsMetaData)
+    );
+}
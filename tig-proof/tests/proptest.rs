@@ -0,0 +1,107 @@
+use proptest::prelude::*;
+use serde_json::json;
+use tig_proof::{calc_solutions_merkle_root, verify_solutions_merkle, ProofError};
+use tig_structs::core::{Solution, SolutionData, SolutionMetaData};
+
+const MAX_LEAVES: usize = 1024;
+
+// leaves with distinct nonces in arbitrary order
+fn leaves(min_len: usize) -> impl Strategy<Value = Vec<SolutionMetaData>> {
+    prop::collection::btree_map(any::<u64>(), any::<u32>(), min_len..=MAX_LEAVES)
+        .prop_map(|leaves| {
+            leaves
+                .into_iter()
+                .map(|(nonce, solution_signature)| SolutionMetaData {
+                    nonce,
+                    solution_signature,
+                })
+                .collect::<Vec<_>>()
+        })
+        .prop_shuffle()
+}
+
+// one of the leaves is replaced by that of a real solution, so there is something to verify
+fn leaves_with_solution(
+    min_len: usize,
+) -> impl Strategy<Value = (Vec<SolutionMetaData>, SolutionData)> {
+    (
+        leaves(min_len),
+        any::<prop::sample::Index>(),
+        any::<[i32; 2]>(),
+    )
+        .prop_map(|(mut meta_data, index, variables)| {
+            let index = index.index(meta_data.len());
+            let mut solution = Solution::new();
+            solution.insert("variables".to_string(), json!(variables));
+            let solution_data = SolutionData {
+                nonce: meta_data[index].nonce,
+                runtime_signature: 0,
+                fuel_consumed: 0,
+                solution,
+            };
+            meta_data[index] = SolutionMetaData::from(solution_data.clone());
+            (meta_data, solution_data)
+        })
+}
+
+// the bytes a leaf is hashed from, i.e. its nonce followed by its signature
+fn flip_leaf_byte(leaf: &mut SolutionMetaData, byte: usize, mask: u8) {
+    if byte < 8 {
+        let mut nonce = leaf.nonce.to_le_bytes();
+        nonce[byte] ^= mask;
+        leaf.nonce = u64::from_le_bytes(nonce);
+    } else {
+        let mut signature = leaf.solution_signature.to_le_bytes();
+        signature[byte - 8] ^= mask;
+        leaf.solution_signature = u32::from_le_bytes(signature);
+    }
+}
+
+fn is_root_mismatch(result: Result<(), ProofError>) -> bool {
+    matches!(result, Err(ProofError::RootMismatch { .. }))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_untampered_leaves_verify((meta_data, solution_data) in leaves_with_solution(1)) {
+        let root = calc_solutions_merkle_root(&meta_data).unwrap();
+        prop_assert_eq!(verify_solutions_merkle(&root, &meta_data, &solution_data), Ok(()));
+    }
+
+    #[test]
+    fn test_flipped_leaf_byte_fails(
+        (mut meta_data, solution_data) in leaves_with_solution(1),
+        leaf in any::<prop::sample::Index>(),
+        byte in 0..12usize,
+        mask in 1..=u8::MAX,
+    ) {
+        let root = calc_solutions_merkle_root(&meta_data).unwrap();
+        let leaf = leaf.index(meta_data.len());
+        flip_leaf_byte(&mut meta_data[leaf], byte, mask);
+        prop_assert!(is_root_mismatch(verify_solutions_merkle(
+            &root,
+            &meta_data,
+            &solution_data
+        )));
+    }
+
+    #[test]
+    fn test_swapped_leaves_fail(
+        (mut meta_data, solution_data) in leaves_with_solution(2),
+        a in any::<prop::sample::Index>(),
+        b in any::<prop::sample::Index>(),
+    ) {
+        let root = calc_solutions_merkle_root(&meta_data).unwrap();
+        let a = a.index(meta_data.len());
+        // offset from `a` so the two leaves are always distinct
+        let b = (a + 1 + b.index(meta_data.len() - 1)) % meta_data.len();
+        meta_data.swap(a, b);
+        prop_assert!(is_root_mismatch(verify_solutions_merkle(
+            &root,
+            &meta_data,
+            &solution_data
+        )));
+    }
+}
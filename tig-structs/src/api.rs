@@ -1,5 +1,7 @@
 use crate::{core::*, serializable_struct_with_getters};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
 
 serializable_struct_with_getters! {
     RequestApiKeyReq {
@@ -128,11 +130,165 @@ serializable_struct_with_getters! {
     }
 }
 
+// version of the SubmitBenchmarkReq format this build speaks
+pub const PROTOCOL_VERSION: u16 = 1;
+
+fn default_protocol_version() -> u16 {
+    PROTOCOL_VERSION
+}
+
 serializable_struct_with_getters! {
-    SubmitBenchmarkReq {
-        settings: BenchmarkSettings,
-        solutions_meta_data: Vec<SolutionMetaData>,
-        solution_data: SolutionData,
+    GetProtocolResp {
+        protocol_version: u16,
+    }
+}
+
+// fields are private so `SubmitBenchmarkReqBuilder` is the only way to construct one, and a
+// field added later cannot be left out by an existing call site
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SubmitBenchmarkReq {
+    // shared so retrying a submission does not deep copy them
+    settings: Arc<BenchmarkSettings>,
+    solutions_meta_data: Vec<SolutionMetaData>,
+    solution_data: Arc<SolutionData>,
+    #[serde(default = "default_protocol_version")]
+    protocol_version: u16,
+    // the same for every attempt at a submission, so a server that already accepted it can
+    // answer a retry with its earlier response. requests from older clients get a fresh one
+    #[serde(default = "Uuid::new_v4")]
+    idempotency_key: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuilderError {
+    EmptyMetadata,
+    MissingField(&'static str),
+}
+
+impl std::fmt::Display for BuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuilderError::EmptyMetadata => write!(f, "solutions_meta_data must not be empty"),
+            BuilderError::MissingField(field) => write!(f, "Missing required field '{}'", field),
+        }
+    }
+}
+
+impl std::error::Error for BuilderError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct SubmitBenchmarkReqBuilder {
+    settings: Option<Arc<BenchmarkSettings>>,
+    solutions_meta_data: Option<Vec<SolutionMetaData>>,
+    solution_data: Option<Arc<SolutionData>>,
+    idempotency_key: Option<Uuid>,
+}
+
+impl SubmitBenchmarkReqBuilder {
+    pub fn settings(mut self, settings: impl Into<Arc<BenchmarkSettings>>) -> Self {
+        self.settings = Some(settings.into());
+        self
+    }
+
+    pub fn solutions_meta_data(mut self, solutions_meta_data: Vec<SolutionMetaData>) -> Self {
+        self.solutions_meta_data = Some(solutions_meta_data);
+        self
+    }
+
+    pub fn solution_data(mut self, solution_data: impl Into<Arc<SolutionData>>) -> Self {
+        self.solution_data = Some(solution_data.into());
+        self
+    }
+
+    pub fn idempotency_key(mut self, idempotency_key: Uuid) -> Self {
+        self.idempotency_key = Some(idempotency_key);
+        self
+    }
+
+    // every field is required. the request is stamped with this build's PROTOCOL_VERSION
+    pub fn build(self) -> Result<SubmitBenchmarkReq, BuilderError> {
+        let solutions_meta_data = self
+            .solutions_meta_data
+            .ok_or(BuilderError::MissingField("solutions_meta_data"))?;
+        if solutions_meta_data.is_empty() {
+            return Err(BuilderError::EmptyMetadata);
+        }
+        Ok(SubmitBenchmarkReq {
+            settings: self
+                .settings
+                .ok_or(BuilderError::MissingField("settings"))?,
+            solutions_meta_data,
+            solution_data: self
+                .solution_data
+                .ok_or(BuilderError::MissingField("solution_data"))?,
+            protocol_version: PROTOCOL_VERSION,
+            idempotency_key: self
+                .idempotency_key
+                .ok_or(BuilderError::MissingField("idempotency_key"))?,
+        })
+    }
+}
+
+impl SubmitBenchmarkReq {
+    pub fn builder() -> SubmitBenchmarkReqBuilder {
+        SubmitBenchmarkReqBuilder::default()
+    }
+
+    pub fn settings(&self) -> &BenchmarkSettings {
+        &self.settings
+    }
+
+    pub fn solutions_meta_data(&self) -> &[SolutionMetaData] {
+        &self.solutions_meta_data
+    }
+
+    pub fn solution_data(&self) -> &SolutionData {
+        &self.solution_data
+    }
+
+    pub fn protocol_version(&self) -> u16 {
+        self.protocol_version
+    }
+
+    pub fn idempotency_key(&self) -> Uuid {
+        self.idempotency_key
+    }
+
+    pub fn into_solutions_meta_data(self) -> Vec<SolutionMetaData> {
+        self.solutions_meta_data
+    }
+
+    // keeps the first `len` entries. at least one is always kept, so the request stays valid.
+    // the entry of solution_data is swapped into the kept ones, so it is still proven
+    pub fn truncate_solutions_meta_data(&mut self, len: usize) {
+        let len = len.max(1);
+        let nonce = self.solution_data.nonce;
+        if let Some(index) = self
+            .solutions_meta_data
+            .iter()
+            .position(|d| d.nonce == nonce)
+        {
+            if index >= len {
+                self.solutions_meta_data.swap(index, len - 1);
+            }
+        }
+        self.solutions_meta_data.truncate(len);
+    }
+
+    // upgrades a request serialised by a client speaking protocol version `from`. each future
+    // version adds an arm that converts from the previous one
+    pub fn migrate(from: u16, data: &[u8]) -> Result<Self, String> {
+        let mut req = match from {
+            1 => serde_json::from_slice::<SubmitBenchmarkReq>(data).map_err(|e| e.to_string())?,
+            _ => {
+                return Err(format!(
+                    "Cannot migrate SubmitBenchmarkReq from protocol version '{}'",
+                    from
+                ))
+            }
+        };
+        req.protocol_version = PROTOCOL_VERSION;
+        Ok(req)
     }
 }
 
@@ -140,6 +296,20 @@ serializable_struct_with_getters! {
     SubmitBenchmarkResp {
         benchmark_id: String,
         verified: Result<(), String>,
+        // servers that do not report a reward leave it out
+        reward: Option<u64>,
+    }
+}
+
+serializable_struct_with_getters! {
+    SubmitBatchReq {
+        benchmarks: Vec<SubmitBenchmarkReq>,
+    }
+}
+
+serializable_struct_with_getters! {
+    SubmitBatchResp {
+        results: Vec<SubmitBenchmarkResp>,
     }
 }
 
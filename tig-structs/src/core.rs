@@ -2,7 +2,7 @@ use crate::{config::ProtocolConfig, serializable_struct_with_getters};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::collections::{HashMap, HashSet};
-use tig_utils::{jsonify, u32_from_str, u64s_from_str};
+use tig_utils::{jsonify, md5_from_str, u32_from_str, u64s_from_str};
 pub use tig_utils::{Frontier, Point, PreciseNumber, Transaction, U256};
 
 serializable_struct_with_getters! {
@@ -116,6 +116,12 @@ impl BenchmarkSettings {
         }
         seeds
     }
+
+    // settings are unique per benchmark, so this identifies a benchmark before the server
+    // has assigned it an id
+    pub fn calc_id(&self) -> String {
+        md5_from_str(jsonify(&self).as_str())
+    }
 }
 serializable_struct_with_getters! {
     BenchmarkDetails {
@@ -233,6 +239,12 @@ impl SolutionData {
     pub fn calc_solution_signature(&self) -> u32 {
         u32_from_str(&jsonify(self))
     }
+
+    // solutions computed with less fuel score higher. not a field, as that would change the
+    // solution signature
+    pub fn score(&self) -> u64 {
+        u64::MAX - self.fuel_consumed
+    }
 }
 
 // Fraud child structs
@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tig_utils::{CompressionAlgorithm, CompressionConfig};
+
+// ~1 MB of json resembling a serialized solution_data
+fn solution_data_payload() -> Vec<u8> {
+    let mut payload = String::from(
+        "{\"nonce\":1,\"runtime_signature\":2,\"fuel_consumed\":3,\"solution\":{\"routes\":[",
+    );
+    let mut i = 0u64;
+    while payload.len() < 1024 * 1024 {
+        payload.push_str(&format!(
+            "[0,{},{},{},0],",
+            i % 997,
+            (i * 31) % 997,
+            (i * 7) % 997
+        ));
+        i += 1;
+    }
+    payload.push_str("[]]}}");
+    payload.into_bytes()
+}
+
+fn bench_compression(c: &mut Criterion) {
+    let payload = solution_data_payload();
+    let mut group = c.benchmark_group("compress_solution_data");
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+    for config in [
+        CompressionConfig::default(),
+        CompressionConfig {
+            algorithm: CompressionAlgorithm::Gzip,
+            level: 6,
+        },
+        CompressionConfig {
+            algorithm: CompressionAlgorithm::Zstd,
+            level: 3,
+        },
+    ] {
+        let compressed_len = config.compress(&payload).unwrap().len();
+        println!(
+            "{:?} level {}: {} -> {} bytes",
+            config.algorithm,
+            config.level,
+            payload.len(),
+            compressed_len
+        );
+        group.bench_with_input(
+            BenchmarkId::new(format!("{:?}", config.algorithm), config.level),
+            &payload,
+            |b, payload| b.iter(|| config.compress(black_box(payload)).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_compression);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// consecutive failures in `Closed` state before the breaker opens
+    pub failure_threshold: u32,
+    /// time the breaker stays `Open` before letting a probe request through
+    pub probe_interval_ms: u64,
+    /// consecutive successes in `HalfOpen` state before the breaker closes again
+    pub success_threshold: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            probe_interval_ms: 30000,
+            success_threshold: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitOpenError {
+    pub retry_after_ms: u64,
+}
+
+impl std::fmt::Display for CircuitOpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Circuit breaker is open. Next probe allowed in {} ms",
+            self.retry_after_ms
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpenError {}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: CircuitState,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    opened_at_ms: u64,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            opened_at_ms: 0,
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    pub fn try_acquire(&mut self, now_ms: u64) -> Result<(), CircuitOpenError> {
+        if self.state == CircuitState::Open {
+            let elapsed_ms = now_ms.saturating_sub(self.opened_at_ms);
+            if elapsed_ms < self.config.probe_interval_ms {
+                return Err(CircuitOpenError {
+                    retry_after_ms: self.config.probe_interval_ms - elapsed_ms,
+                });
+            }
+            self.state = CircuitState::HalfOpen;
+            self.consecutive_successes = 0;
+        }
+        Ok(())
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        if self.state == CircuitState::HalfOpen {
+            self.consecutive_successes += 1;
+            if self.consecutive_successes >= self.config.success_threshold {
+                self.state = CircuitState::Closed;
+                self.consecutive_successes = 0;
+            }
+        }
+    }
+
+    pub fn record_failure(&mut self, now_ms: u64) {
+        self.consecutive_successes = 0;
+        self.consecutive_failures += 1;
+        if self.state == CircuitState::HalfOpen
+            || self.consecutive_failures >= self.config.failure_threshold
+        {
+            self.state = CircuitState::Open;
+            self.opened_at_ms = now_ms;
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CompressionAlgorithm {
+    None,
+    Gzip,
+    Zstd,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompressionConfig {
+    pub algorithm: CompressionAlgorithm,
+    /// gzip accepts 0-9, zstd accepts 1-22. out of range levels are clamped
+    pub level: u32,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: CompressionAlgorithm::None,
+            level: 0,
+        }
+    }
+}
+
+impl CompressionAlgorithm {
+    pub fn content_encoding(&self) -> Option<&'static str> {
+        match self {
+            CompressionAlgorithm::None => None,
+            CompressionAlgorithm::Gzip => Some("gzip"),
+            CompressionAlgorithm::Zstd => Some("zstd"),
+        }
+    }
+
+    pub fn from_content_encoding(encoding: &str) -> Result<Self> {
+        match encoding.trim().to_lowercase().as_str() {
+            "" | "identity" => Ok(CompressionAlgorithm::None),
+            "gzip" => Ok(CompressionAlgorithm::Gzip),
+            "zstd" => Ok(CompressionAlgorithm::Zstd),
+            other => Err(anyhow!("Unsupported content-encoding '{}'", other)),
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        match self.algorithm {
+            CompressionAlgorithm::None => Ok(input.to_vec()),
+            CompressionAlgorithm::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::new(self.level.min(9)));
+                encoder.write_all(input)?;
+                Ok(encoder.finish()?)
+            }
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => {
+                let level = self.level.clamp(1, 22) as i32;
+                Ok(zstd::stream::encode_all(input, level)?)
+            }
+            #[cfg(not(feature = "zstd"))]
+            CompressionAlgorithm::Zstd => {
+                Err(anyhow!("tig-utils was built without feature `zstd`"))
+            }
+        }
+    }
+}
+
+pub fn decompress(algorithm: CompressionAlgorithm, input: &[u8]) -> Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::None => Ok(input.to_vec()),
+        CompressionAlgorithm::Gzip => {
+            let mut decoder = GzDecoder::new(input);
+            let mut decompressed = Vec::new();
+            decoder.read_to_end(&mut decompressed)?;
+            Ok(decompressed)
+        }
+        #[cfg(feature = "zstd")]
+        CompressionAlgorithm::Zstd => Ok(zstd::stream::decode_all(input)?),
+        #[cfg(not(feature = "zstd"))]
+        CompressionAlgorithm::Zstd => Err(anyhow!("tig-utils was built without feature `zstd`")),
+    }
+}
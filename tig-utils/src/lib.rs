@@ -1,3 +1,7 @@
+mod circuit_breaker;
+pub use circuit_breaker::*;
+mod compression;
+pub use compression::*;
 mod eth;
 pub use eth::*;
 mod frontiers;
@@ -8,6 +12,8 @@ mod json;
 pub use json::*;
 mod number;
 pub use number::*;
+mod time;
+pub use time::*;
 #[cfg(any(feature = "request", feature = "request-js"))]
 mod request;
 #[cfg(any(feature = "request", feature = "request-js"))]
@@ -2,6 +2,85 @@
 compile_error!("features `request` and `request-js` are mutually exclusive");
 
 use anyhow::{anyhow, Result};
+use std::{path::PathBuf, time::Duration};
+
+// unset fields keep the http library's defaults
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpClientConfig {
+    /// pem file whose certificates are trusted in addition to the system roots
+    pub tls_ca_bundle: Option<PathBuf>,
+    /// every request goes through this proxy, e.g. `http://proxy.corp:3128`
+    pub proxy: Option<String>,
+    pub connect_timeout: Option<Duration>,
+    /// covers the whole request, from connecting until the response body is read
+    pub request_timeout: Option<Duration>,
+    /// a longer response body fails the request with `BodyTooLargeError`
+    pub max_response_body_bytes: Option<usize>,
+    /// speak HTTP/2 from the start instead of negotiating it with ALPN, so concurrent requests
+    /// to a server share one multiplexed connection
+    pub http2_prior_knowledge: bool,
+}
+
+// reading stops as soon as the body goes over the limit, so it is never held in full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyTooLargeError {
+    pub limit: usize,
+    pub received_so_far: usize,
+}
+
+impl std::fmt::Display for BodyTooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Response body exceeds {} bytes ({} received so far)",
+            self.limit, self.received_so_far
+        )
+    }
+}
+
+impl std::error::Error for BodyTooLargeError {}
+
+fn check_body_len(len: usize, max_body_bytes: Option<usize>) -> Result<()> {
+    match max_body_bytes {
+        Some(limit) if len > limit => Err(BodyTooLargeError {
+            limit,
+            received_so_far: len,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+// a response body along with the status and headers it came with. header names are lowercase
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse<T> {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: T,
+}
+
+impl<T> HttpResponse<T> {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// the error is kept a plain string so callers can match on it. `retry-after` is included when
+// the server sent the header, e.g. with 429 Too Many Requests
+fn status_error(status: u16, retry_after: Option<String>, body: String) -> anyhow::Error {
+    match retry_after {
+        Some(retry_after) => anyhow!(
+            "Request error (status: {}, retry-after: {}, body: {})",
+            status,
+            retry_after.trim(),
+            body
+        ),
+        None => anyhow!("Request error (status: {}, body: {})", status, body),
+    }
+}
 
 #[cfg(feature = "request-js")]
 mod request {
@@ -12,12 +91,31 @@ mod request {
 
     #[allow(async_fn_in_trait)]
     pub trait FromResponse: Sized {
-        async fn from_response(response: Response) -> Result<Self>;
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self>;
+    }
+
+    // fetch does not hand out the body in chunks, so this only catches bodies that announce
+    // their length up front. the rest are checked once read
+    fn check_content_length(response: &Response, max_body_bytes: Option<usize>) -> Result<()> {
+        match (
+            max_body_bytes,
+            response.headers().get("content-length").ok().flatten(),
+        ) {
+            (Some(limit), Some(len)) if len.parse::<usize>().is_ok_and(|len| len > limit) => {
+                Err(BodyTooLargeError {
+                    limit,
+                    received_so_far: 0,
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
     }
 
     async fn check_status(response: Response) -> Result<Response> {
         let status = response.status();
         if !(200..=299).contains(&status) {
+            let retry_after = response.headers().get("retry-after").ok().flatten();
             let msg = match response.text() {
                 Ok(promise) => match JsFuture::from(promise).await {
                     Ok(value) => value.as_string().unwrap_or("".to_string()),
@@ -25,88 +123,222 @@ mod request {
                 },
                 Err(_) => "".to_string(),
             };
-            return Err(anyhow!("Request error (status: {}, body: {})", status, msg));
+            return Err(status_error(status, retry_after, msg));
         }
         Ok(response)
     }
 
     impl FromResponse for Vec<u8> {
-        async fn from_response(response: Response) -> Result<Self> {
-            let promise = check_status(response).await?.array_buffer().unwrap();
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self> {
+            let response = check_status(response).await?;
+            check_content_length(&response, max_body_bytes)?;
+            let promise = response.array_buffer().unwrap();
             let future = JsFuture::from(promise);
             let buffer = future
                 .await
                 .map_err(|_| anyhow!("Failed to read response body as array buffer"))?;
             let uint8_array = js_sys::Uint8Array::new(&buffer);
+            check_body_len(uint8_array.length() as usize, max_body_bytes)?;
             Ok(uint8_array.to_vec())
         }
     }
 
+    // browsers only expose the headers the server lists in Access-Control-Expose-Headers
+    impl<T: FromResponse> FromResponse for HttpResponse<T> {
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self> {
+            let mut headers = Vec::new();
+            if let Ok(Some(entries)) = js_sys::try_iter(&response.headers()) {
+                for entry in entries.flatten() {
+                    let entry = js_sys::Array::from(&entry);
+                    if let (Some(name), Some(value)) =
+                        (entry.get(0).as_string(), entry.get(1).as_string())
+                    {
+                        headers.push((name.to_lowercase(), value));
+                    }
+                }
+            }
+            Ok(Self {
+                status: response.status(),
+                headers,
+                body: T::from_response(response, max_body_bytes).await?,
+            })
+        }
+    }
+
     impl FromResponse for String {
-        async fn from_response(response: Response) -> Result<Self> {
-            let promise = check_status(response)
-                .await?
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self> {
+            let response = check_status(response).await?;
+            check_content_length(&response, max_body_bytes)?;
+            let promise = response
                 .text()
                 .map_err(|_| anyhow!("Failed to read response body as text"))?;
-            JsFuture::from(promise)
+            let text = JsFuture::from(promise)
                 .await
                 .map_err(|_| anyhow!("Failed to read response body as text"))?
                 .as_string()
-                .ok_or_else(|| anyhow!("Failed to convert JsValue to String"))
+                .ok_or_else(|| anyhow!("Failed to convert JsValue to String"))?;
+            check_body_len(text.len(), max_body_bytes)?;
+            Ok(text)
         }
     }
 
-    async fn call<T>(
-        method: &str,
-        url: &str,
-        body: Option<&JsValue>,
-        headers: Option<Headers>,
-    ) -> Result<T>
-    where
-        T: FromResponse,
-    {
-        let mut opts = RequestInit::new();
-        opts.method(method);
-        opts.mode(RequestMode::Cors);
+    // fetch leaves proxies, trusted certificates and timeouts to the browser
+    #[derive(Debug, Clone, Default)]
+    pub struct HttpClient {
+        max_response_body_bytes: Option<usize>,
+    }
+
+    impl HttpClient {
+        pub fn new(config: &HttpClientConfig) -> Result<Self> {
+            let unsupported = HttpClientConfig {
+                max_response_body_bytes: None,
+                ..config.clone()
+            };
+            if unsupported != HttpClientConfig::default() {
+                return Err(anyhow!(
+                    "TLS, proxy, timeout and HTTP/2 settings are not supported in the browser"
+                ));
+            }
+            Ok(Self {
+                max_response_body_bytes: config.max_response_body_bytes,
+            })
+        }
 
-        if let Some(b) = body {
-            opts.body(Some(b));
+        pub fn with_max_response_body_bytes(mut self, max_bytes: Option<usize>) -> Self {
+            self.max_response_body_bytes = max_bytes;
+            self
         }
 
-        if let Some(h) = headers {
-            opts.headers(&h);
+        pub async fn get<T>(&self, url: &str, headers: Option<Vec<(String, String)>>) -> Result<T>
+        where
+            T: FromResponse,
+        {
+            let headers = convert_headers(headers)?;
+            self.call::<T>("GET", url, None, headers).await
         }
 
-        let request = Request::new_with_str_and_init(url, &opts)
-            .map_err(|_| anyhow!("Failed to create request"))?;
+        pub async fn post<T>(
+            &self,
+            url: &str,
+            body: &str,
+            headers: Option<Vec<(String, String)>>,
+        ) -> Result<T>
+        where
+            T: FromResponse,
+        {
+            let headers = convert_headers(headers)?;
+            let body_value = Some(JsValue::from_str(body));
+            self.call::<T>("POST", url, body_value.as_ref(), headers)
+                .await
+        }
 
-        let window = web_sys::window().ok_or_else(|| anyhow!("No global `window` exists"))?;
-        let response_value = JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .map_err(|_| anyhow!("Failed to fetch"))?;
+        pub async fn post_bytes<T>(
+            &self,
+            url: &str,
+            body: &[u8],
+            headers: Option<Vec<(String, String)>>,
+        ) -> Result<T>
+        where
+            T: FromResponse,
+        {
+            let headers = convert_headers(headers)?;
+            let body_value: Option<JsValue> = Some(js_sys::Uint8Array::from(body).into());
+            self.call::<T>("POST", url, body_value.as_ref(), headers)
+                .await
+        }
+
+        // fetch cannot stream request bodies everywhere, so the parts are joined up front
+        pub async fn post_parts<T, I>(
+            &self,
+            url: &str,
+            parts: I,
+            headers: Option<Vec<(String, String)>>,
+        ) -> Result<T>
+        where
+            T: FromResponse,
+            I: Iterator<Item = Vec<u8>> + Send + 'static,
+        {
+            let body: Vec<u8> = parts.flatten().collect();
+            self.post_bytes::<T>(url, &body, headers).await
+        }
+
+        async fn call<T>(
+            &self,
+            method: &str,
+            url: &str,
+            body: Option<&JsValue>,
+            headers: Option<Headers>,
+        ) -> Result<T>
+        where
+            T: FromResponse,
+        {
+            let mut opts = RequestInit::new();
+            opts.method(method);
+            opts.mode(RequestMode::Cors);
+
+            if let Some(b) = body {
+                opts.body(Some(b));
+            }
+
+            if let Some(h) = headers {
+                opts.headers(&h);
+            }
+
+            let request = Request::new_with_str_and_init(url, &opts)
+                .map_err(|_| anyhow!("Failed to create request"))?;
 
-        let response: Response = response_value
-            .dyn_into()
-            .map_err(|_| anyhow!("Failed to cast to Response"))?;
+            let window = web_sys::window().ok_or_else(|| anyhow!("No global `window` exists"))?;
+            let response_value = JsFuture::from(window.fetch_with_request(&request))
+                .await
+                .map_err(|_| anyhow!("Failed to fetch"))?;
+
+            let response: Response = response_value
+                .dyn_into()
+                .map_err(|_| anyhow!("Failed to cast to Response"))?;
 
-        T::from_response(response).await
+            T::from_response(response, self.max_response_body_bytes).await
+        }
     }
 
     pub async fn get<T>(url: &str, headers: Option<Vec<(String, String)>>) -> Result<T>
     where
         T: FromResponse,
     {
-        let headers = convert_headers(headers)?;
-        call::<T>("GET", url, None, headers).await
+        HttpClient::default().get::<T>(url, headers).await
     }
 
     pub async fn post<T>(url: &str, body: &str, headers: Option<Vec<(String, String)>>) -> Result<T>
     where
         T: FromResponse,
     {
-        let headers = convert_headers(headers)?;
-        let body_value = Some(JsValue::from_str(body));
-        call::<T>("POST", url, body_value.as_ref(), headers).await
+        HttpClient::default().post::<T>(url, body, headers).await
+    }
+
+    pub async fn post_bytes<T>(
+        url: &str,
+        body: &[u8],
+        headers: Option<Vec<(String, String)>>,
+    ) -> Result<T>
+    where
+        T: FromResponse,
+    {
+        HttpClient::default()
+            .post_bytes::<T>(url, body, headers)
+            .await
+    }
+
+    pub async fn post_parts<T, I>(
+        url: &str,
+        parts: I,
+        headers: Option<Vec<(String, String)>>,
+    ) -> Result<T>
+    where
+        T: FromResponse,
+        I: Iterator<Item = Vec<u8>> + Send + 'static,
+    {
+        HttpClient::default()
+            .post_parts::<T, I>(url, parts, headers)
+            .await
     }
 
     fn convert_headers(headers_option: Option<Vec<(String, String)>>) -> Result<Option<Headers>> {
@@ -128,66 +360,203 @@ mod request {
 mod request {
     use super::*;
     use reqwest::{
-        header::{HeaderMap, HeaderName, HeaderValue},
-        Response,
+        header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER},
+        Body, Certificate, Proxy, Response,
     };
 
     #[allow(async_fn_in_trait)]
     pub trait FromResponse: Sized {
-        async fn from_response(response: Response) -> Result<Self>;
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self>;
     }
 
-    async fn check_status(response: Response) -> Result<Response> {
+    // reads the body a chunk at a time and drops the connection as soon as it goes over
+    // `max_body_bytes`
+    async fn read_body(mut response: Response, max_body_bytes: Option<usize>) -> Result<Vec<u8>> {
+        let Some(limit) = max_body_bytes else {
+            return Ok(response.bytes().await?.to_vec());
+        };
+        if response
+            .content_length()
+            .is_some_and(|len| len > limit as u64)
+        {
+            return Err(BodyTooLargeError {
+                limit,
+                received_so_far: 0,
+            }
+            .into());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            check_body_len(body.len() + chunk.len(), Some(limit))?;
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    // the body of an error response is only for the message, so one over the limit is dropped
+    async fn check_status(response: Response, max_body_bytes: Option<usize>) -> Result<Response> {
         let status = response.status().as_u16();
         if !(200..=299).contains(&status) {
-            let msg = match response.text().await {
-                Ok(msg) => msg.clone(),
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+            let msg = match read_body(response, max_body_bytes).await {
+                Ok(msg) => String::from_utf8_lossy(&msg).into_owned(),
                 Err(_) => "".to_string(),
             };
-            return Err(anyhow!("Request error (status: {}, body: {})", status, msg));
+            return Err(status_error(status, retry_after, msg));
         }
         Ok(response)
     }
 
     impl FromResponse for Vec<u8> {
-        async fn from_response(response: Response) -> Result<Self> {
-            Ok(check_status(response).await?.bytes().await?.to_vec())
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self> {
+            let response = check_status(response, max_body_bytes).await?;
+            read_body(response, max_body_bytes).await
         }
     }
 
     impl FromResponse for String {
-        async fn from_response(response: Response) -> Result<Self> {
-            Ok(check_status(response).await?.text().await?)
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self> {
+            let body = Vec::<u8>::from_response(response, max_body_bytes).await?;
+            Ok(String::from_utf8_lossy(&body).into_owned())
         }
     }
 
-    async fn call<T: FromResponse>(
-        method: &str,
-        url: &str,
-        body: Option<String>,
-        headers: Option<HeaderMap>,
-    ) -> Result<T> {
-        let client = reqwest::Client::new();
-        let mut request_builder = client.request(method.parse().unwrap(), url);
+    impl<T: FromResponse> FromResponse for HttpResponse<T> {
+        async fn from_response(response: Response, max_body_bytes: Option<usize>) -> Result<Self> {
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string())))
+                .collect();
+            Ok(Self {
+                status: response.status().as_u16(),
+                headers,
+                body: T::from_response(response, max_body_bytes).await?,
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub struct HttpClient {
+        client: reqwest::Client,
+        max_response_body_bytes: Option<usize>,
+    }
+
+    impl HttpClient {
+        pub fn new(config: &HttpClientConfig) -> Result<Self> {
+            let mut builder = reqwest::Client::builder();
+            if let Some(path) = &config.tls_ca_bundle {
+                let pem = std::fs::read(path).map_err(|e| {
+                    anyhow!("Failed to read TLS CA bundle {}: {}", path.display(), e)
+                })?;
+                for certificate in Certificate::from_pem_bundle(&pem)? {
+                    builder = builder.add_root_certificate(certificate);
+                }
+            }
+            if let Some(proxy) = &config.proxy {
+                builder = builder.proxy(Proxy::all(proxy.as_str())?);
+            }
+            if let Some(timeout) = config.connect_timeout {
+                builder = builder.connect_timeout(timeout);
+            }
+            if let Some(timeout) = config.request_timeout {
+                builder = builder.timeout(timeout);
+            }
+            if config.http2_prior_knowledge {
+                builder = builder.http2_prior_knowledge();
+            }
+            Ok(Self {
+                client: builder.build()?,
+                max_response_body_bytes: config.max_response_body_bytes,
+            })
+        }
+
+        pub fn with_max_response_body_bytes(mut self, max_bytes: Option<usize>) -> Self {
+            self.max_response_body_bytes = max_bytes;
+            self
+        }
+
+        async fn call<T: FromResponse>(
+            &self,
+            method: &str,
+            url: &str,
+            body: Option<Body>,
+            headers: Option<HeaderMap>,
+        ) -> Result<T> {
+            let mut request_builder = self.client.request(method.parse().unwrap(), url);
+
+            if let Some(b) = body {
+                request_builder = request_builder.body(b);
+            }
+
+            if let Some(h) = headers {
+                request_builder = request_builder.headers(h);
+            }
 
-        if let Some(b) = body {
-            request_builder = request_builder.body(b);
+            let response = request_builder.send().await?;
+            T::from_response(response, self.max_response_body_bytes).await
         }
 
-        if let Some(h) = headers {
-            request_builder = request_builder.headers(h);
+        pub async fn get<T: FromResponse>(
+            &self,
+            url: &str,
+            headers: Option<Vec<(String, String)>>,
+        ) -> Result<T> {
+            let headers = convert_headers(headers)?;
+            self.call::<T>("GET", url, None, headers).await
         }
 
-        let response = request_builder.send().await?;
-        T::from_response(response).await
+        pub async fn post<T: FromResponse>(
+            &self,
+            url: &str,
+            body: &str,
+            headers: Option<Vec<(String, String)>>,
+        ) -> Result<T> {
+            let headers = convert_headers(headers)?;
+            let body_value = Some(body.as_bytes().to_vec().into());
+            self.call::<T>("POST", url, body_value, headers).await
+        }
+
+        pub async fn post_bytes<T: FromResponse>(
+            &self,
+            url: &str,
+            body: &[u8],
+            headers: Option<Vec<(String, String)>>,
+        ) -> Result<T> {
+            let headers = convert_headers(headers)?;
+            self.call::<T>("POST", url, Some(body.to_vec().into()), headers)
+                .await
+        }
+
+        // each part is only produced when it is about to be sent, so the whole body is never
+        // held in memory at once
+        pub async fn post_parts<T, I>(
+            &self,
+            url: &str,
+            parts: I,
+            headers: Option<Vec<(String, String)>>,
+        ) -> Result<T>
+        where
+            T: FromResponse,
+            I: Iterator<Item = Vec<u8>> + Send + 'static,
+        {
+            let headers = convert_headers(headers)?;
+            let body = Body::wrap_stream(futures::stream::iter(
+                parts.map(Ok::<Vec<u8>, std::io::Error>),
+            ));
+            self.call::<T>("POST", url, Some(body), headers).await
+        }
     }
 
     pub async fn get<T: FromResponse>(
         url: &str,
         headers: Option<Vec<(String, String)>>,
     ) -> Result<T> {
-        let headers = convert_headers(headers)?;
-        call::<T>("GET", url, None, headers).await
+        HttpClient::default().get::<T>(url, headers).await
     }
 
     pub async fn post<T: FromResponse>(
@@ -195,9 +564,31 @@ mod request {
         body: &str,
         headers: Option<Vec<(String, String)>>,
     ) -> Result<T> {
-        let headers = convert_headers(headers)?;
-        let body_value = Some(body.to_string());
-        call::<T>("POST", url, body_value, headers).await
+        HttpClient::default().post::<T>(url, body, headers).await
+    }
+
+    pub async fn post_bytes<T: FromResponse>(
+        url: &str,
+        body: &[u8],
+        headers: Option<Vec<(String, String)>>,
+    ) -> Result<T> {
+        HttpClient::default()
+            .post_bytes::<T>(url, body, headers)
+            .await
+    }
+
+    pub async fn post_parts<T, I>(
+        url: &str,
+        parts: I,
+        headers: Option<Vec<(String, String)>>,
+    ) -> Result<T>
+    where
+        T: FromResponse,
+        I: Iterator<Item = Vec<u8>> + Send + 'static,
+    {
+        HttpClient::default()
+            .post_parts::<T, I>(url, parts, headers)
+            .await
     }
 
     fn convert_headers(headers_option: Option<Vec<(String, String)>>) -> Result<Option<HeaderMap>> {
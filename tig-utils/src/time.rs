@@ -0,0 +1,13 @@
+#[cfg(feature = "request-js")]
+pub fn time_ms() -> u64 {
+    js_sys::Date::now() as u64
+}
+
+#[cfg(not(feature = "request-js"))]
+pub fn time_ms() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
@@ -0,0 +1,56 @@
+use tig_utils::{CircuitBreaker, CircuitBreakerConfig, CircuitOpenError, CircuitState};
+
+fn breaker() -> CircuitBreaker {
+    CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 3,
+        probe_interval_ms: 1000,
+        success_threshold: 2,
+    })
+}
+
+#[test]
+fn test_opens_after_consecutive_failures() {
+    let mut breaker = breaker();
+    breaker.record_failure(0);
+    breaker.record_failure(0);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    breaker.record_success();
+    breaker.record_failure(0);
+    breaker.record_failure(0);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    breaker.record_failure(100);
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert_eq!(
+        breaker.try_acquire(600),
+        Err(CircuitOpenError {
+            retry_after_ms: 500
+        })
+    );
+}
+
+#[test]
+fn test_half_open_probe() {
+    let mut breaker = breaker();
+    for _ in 0..3 {
+        breaker.record_failure(0);
+    }
+    assert!(breaker.try_acquire(1000).is_ok());
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    breaker.record_failure(1000);
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert!(breaker.try_acquire(1500).is_err());
+}
+
+#[test]
+fn test_closes_after_consecutive_successes() {
+    let mut breaker = breaker();
+    for _ in 0..3 {
+        breaker.record_failure(0);
+    }
+    assert!(breaker.try_acquire(2000).is_ok());
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert!(breaker.try_acquire(2000).is_ok());
+}
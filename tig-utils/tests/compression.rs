@@ -0,0 +1,63 @@
+use tig_utils::{decompress, CompressionAlgorithm, CompressionConfig};
+
+fn payload() -> Vec<u8> {
+    (0..10000)
+        .map(|i| format!("{{\"nonce\":{},\"solution\":[1,2,3,4]}},", i))
+        .collect::<String>()
+        .into_bytes()
+}
+
+#[test]
+fn test_none_is_passthrough() {
+    let config = CompressionConfig::default();
+    let input = payload();
+    assert_eq!(config.compress(&input).unwrap(), input);
+    assert_eq!(config.algorithm.content_encoding(), None);
+}
+
+#[test]
+fn test_gzip_roundtrip() {
+    let config = CompressionConfig {
+        algorithm: CompressionAlgorithm::Gzip,
+        level: 6,
+    };
+    let input = payload();
+    let compressed = config.compress(&input).unwrap();
+    assert!(compressed.len() < input.len());
+    assert_eq!(
+        decompress(CompressionAlgorithm::Gzip, &compressed).unwrap(),
+        input
+    );
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_zstd_roundtrip() {
+    let config = CompressionConfig {
+        algorithm: CompressionAlgorithm::Zstd,
+        level: 3,
+    };
+    let input = payload();
+    let compressed = config.compress(&input).unwrap();
+    assert!(compressed.len() < input.len());
+    assert_eq!(
+        decompress(CompressionAlgorithm::Zstd, &compressed).unwrap(),
+        input
+    );
+}
+
+#[test]
+fn test_content_encoding_roundtrip() {
+    for algorithm in [
+        CompressionAlgorithm::None,
+        CompressionAlgorithm::Gzip,
+        CompressionAlgorithm::Zstd,
+    ] {
+        let encoding = algorithm.content_encoding().unwrap_or("identity");
+        assert_eq!(
+            CompressionAlgorithm::from_content_encoding(encoding).unwrap(),
+            algorithm
+        );
+    }
+    assert!(CompressionAlgorithm::from_content_encoding("br").is_err());
+}
@@ -0,0 +1,215 @@
+#[cfg(feature = "request")]
+mod tests {
+    use http_body_util::Full;
+    use hyper::{
+        body::{Bytes, Incoming},
+        header,
+        service::service_fn,
+        Request, Response, Version,
+    };
+    use hyper_util::{rt::TokioExecutor, rt::TokioIo, server::conn::auto};
+    use std::{
+        convert::Infallible,
+        time::{Duration, Instant},
+    };
+    use tig_utils::{get, BodyTooLargeError, HttpClient, HttpClientConfig, HttpResponse};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    // answers a single request with `response`. the handle resolves to the request received
+    async fn serve_once_capturing(response: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+        (url, handle)
+    }
+
+    async fn serve_once(response: &'static str) -> String {
+        serve_once_capturing(response).await.0
+    }
+
+    #[tokio::test]
+    async fn test_error_includes_retry_after() {
+        let url = serve_once(
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 5\r\nContent-Length: 4\r\nConnection: close\r\n\r\nslow",
+        )
+        .await;
+        let e = get::<String>(&url, None).await.unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<String>().unwrap(),
+            "Request error (status: 429, retry-after: 5, body: slow)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_without_retry_after() {
+        let url = serve_once(
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 4\r\nConnection: close\r\n\r\noops",
+        )
+        .await;
+        let e = get::<String>(&url, None).await.unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<String>().unwrap(),
+            "Request error (status: 500, body: oops)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_headers() {
+        let url = serve_once(
+            "HTTP/1.1 201 Created\r\nX-Trace-Id: abc\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        )
+        .await;
+        let resp = get::<HttpResponse<String>>(&url, None).await.unwrap();
+        assert_eq!(resp.status, 201);
+        assert_eq!(resp.header("x-trace-id"), Some("abc"));
+        assert_eq!(resp.header("X-Trace-Id"), Some("abc"));
+        assert_eq!(resp.body, "ok");
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        let (proxy_url, request) = serve_once_capturing(
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        )
+        .await;
+        let client = HttpClient::new(&HttpClientConfig {
+            proxy: Some(proxy_url),
+            ..Default::default()
+        })
+        .unwrap();
+        let resp = client
+            .get::<String>("http://api.tig.invalid/get-block", None)
+            .await
+            .unwrap();
+        assert_eq!(resp, "ok");
+        assert!(request
+            .await
+            .unwrap()
+            .starts_with("GET http://api.tig.invalid/get-block HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        // accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+        let client = HttpClient::new(&HttpClientConfig {
+            request_timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        })
+        .unwrap();
+        let start = Instant::now();
+        assert!(client.get::<String>(&url, None).await.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_max_response_body_bytes() {
+        let client = HttpClient::new(&HttpClientConfig {
+            max_response_body_bytes: Some(8),
+            ..Default::default()
+        })
+        .unwrap();
+        // no content-length, so the limit is only noticed while reading
+        let url = serve_once(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n10\r\n0123456789abcdef\r\n0\r\n\r\n",
+        )
+        .await;
+        let e = client.get::<String>(&url, None).await.unwrap_err();
+        let too_large = e.downcast_ref::<BodyTooLargeError>().unwrap();
+        assert_eq!(too_large.limit, 8);
+        assert!(too_large.received_so_far > 8);
+
+        let url = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\n0123456789abcdef",
+        )
+        .await;
+        let e = client.get::<String>(&url, None).await.unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<BodyTooLargeError>(),
+            Some(&BodyTooLargeError {
+                limit: 8,
+                received_so_far: 0
+            })
+        );
+
+        let url =
+            serve_once("HTTP/1.1 200 OK\r\nContent-Length: 8\r\nConnection: close\r\n\r\n01234567")
+                .await;
+        assert_eq!(client.get::<String>(&url, None).await.unwrap(), "01234567");
+    }
+
+    // serves one connection at a time, like a server or proxy with a connection limit of 1.
+    // each request takes `delay` to answer. HTTP/1.1 connections are closed after a response
+    async fn serve_one_connection_at_a_time(delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let service = service_fn(move |req: Request<Incoming>| async move {
+                    tokio::time::sleep(delay).await;
+                    let mut resp = Response::new(Full::new(Bytes::from("ok")));
+                    if req.version() == Version::HTTP_11 {
+                        resp.headers_mut()
+                            .insert(header::CONNECTION, "close".parse().unwrap());
+                    }
+                    Ok::<_, Infallible>(resp)
+                });
+                let _ = auto::Builder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(socket), service)
+                    .await;
+            }
+        });
+        url
+    }
+
+    async fn time_concurrent_gets(client: &HttpClient, url: &str, n: usize) -> Duration {
+        let start = Instant::now();
+        let resps =
+            futures::future::join_all((0..n).map(|_| client.get::<String>(url, None))).await;
+        assert!(resps.into_iter().all(|r| r.unwrap() == "ok"));
+        start.elapsed()
+    }
+
+    #[tokio::test]
+    async fn test_http2_multiplexes_concurrent_requests() {
+        let delay = Duration::from_millis(50);
+        let http1 = HttpClient::new(&HttpClientConfig::default()).unwrap();
+        let http2 = HttpClient::new(&HttpClientConfig {
+            http2_prior_knowledge: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let http1_elapsed =
+            time_concurrent_gets(&http1, &serve_one_connection_at_a_time(delay).await, 10).await;
+        let http2_elapsed =
+            time_concurrent_gets(&http2, &serve_one_connection_at_a_time(delay).await, 10).await;
+        // 10 connections served in turn against 10 streams on one connection
+        assert!(http1_elapsed >= delay * 10);
+        assert!(http2_elapsed < http1_elapsed);
+    }
+
+    #[test]
+    fn test_missing_ca_bundle() {
+        let result = HttpClient::new(&HttpClientConfig {
+            tls_ca_bundle: Some("/nonexistent/ca.pem".into()),
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+}